@@ -13,6 +13,7 @@
 //! ```
 
 use ggrs_plot_operator::config::OperatorConfig;
+use ggrs_plot_operator::logging;
 use ggrs_plot_operator::memprof;
 use ggrs_plot_operator::pipeline;
 use std::sync::Arc;
@@ -64,12 +65,26 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     log_phase(start, "PHASE 2: Creating DevContext");
     println!("Creating DevContext from workflow/step...");
     let ctx = DevContext::from_workflow_step(client_arc.clone(), &workflow_id, &step_id).await?;
-    println!("✓ Context created\n");
+    println!("✓ Context created");
+    // Context-derived values that must match ProductionContext for the same step
+    println!("  Chart kind: {:?}", ctx.chart_kind());
+    println!("  Point size (crosstab): {:?}", ctx.point_size());
+    println!("  Crosstab dimensions: {:?}", ctx.crosstab_dimensions());
+    println!(
+        "  Per-layer colors: {}",
+        if ctx.per_layer_colors().is_some() {
+            "present"
+        } else {
+            "absent"
+        }
+    );
+    println!();
     let _ = memprof::delta("After DevContext::from_workflow_step()", m1);
     let _ = memprof::time_delta("After DevContext::from_workflow_step()", t0, t1);
 
     // Load configuration
     let config = load_dev_config(ctx.point_size())?;
+    logging::set_level(config.log_level);
     println!("Configuration loaded:");
     println!("  Chunk size: {}", config.chunk_size);
     println!(
@@ -78,12 +93,15 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         ctx.point_size()
     );
     println!("  Backend: {}", config.backend);
+    println!("  Output format: {}", config.output_format);
     println!("  PNG compression: {}", config.png_compression);
+    println!("  Log level: {:?}", config.log_level);
     println!();
 
     // Generate plots using shared pipeline
     log_phase(start, "PHASE 3: Generating plots");
-    let plot_results = pipeline::generate_plots(&ctx, &config).await?;
+    let output = pipeline::generate_plots(&ctx, &config).await?;
+    let plot_results = output.plots;
 
     // Save results to local files
     log_phase(start, "PHASE 4: Saving to local files");
@@ -106,6 +124,17 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         );
     }
 
+    for (i, file) in output.data_files.iter().enumerate() {
+        let filename = if output.data_files.len() > 1 {
+            format!("{}_{}.{}", file.filename, i + 1, file.extension)
+        } else {
+            format!("{}.{}", file.filename, file.extension)
+        };
+
+        std::fs::write(&filename, &file.content)?;
+        println!("✓ Saved {} ({} bytes)", filename, file.content.len());
+    }
+
     log_phase(start, "COMPLETE");
     println!("\n=== Development Test Complete ===");
     println!("All checks passed!");
@@ -125,7 +154,7 @@ fn load_dev_config(
         Ok(json) => json,
         Err(_) => {
             println!("  No operator_config.json found, using defaults");
-            return Ok(OperatorConfig::from_properties(None, ui_point_size)?);
+            return Ok(OperatorConfig::from_properties(None, ui_point_size, None)?);
         }
     };
 
@@ -167,5 +196,6 @@ fn load_dev_config(
     Ok(OperatorConfig::from_properties(
         Some(&operator_settings),
         ui_point_size,
+        None,
     )?)
 }
@@ -108,7 +108,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Step 3: Find the parent TableStep relation
     let parent_relation = find_parent_relation(&workflow, &data_step)?;
-    println!("[prepare] Parent relation found");
+    let relation_type = relation_kind(&parent_relation)?;
+    println!("[prepare] Parent relation found: {}", relation_type);
 
     // Step 4: Build CubeQuery from Crosstab model
     let cube_query = build_cube_query(model, parent_relation)?;
@@ -209,6 +210,38 @@ fn find_parent_relation(
     Err(format!("No parent step found for output port {}", link.output_id).into())
 }
 
+/// Name of a relation type the CubeQuery can be built on
+///
+/// Plain tables, references and the join (composite), union, rename and where
+/// wrappers around them resolve to the tables carrying the data. Other types
+/// (pairwise, range, ...) are rejected explicitly rather than producing a cube
+/// over the wrong tables.
+fn relation_kind(relation: &proto::ERelation) -> Result<&'static str, String> {
+    use proto::e_relation::Object;
+
+    let unsupported = match &relation.object {
+        Some(Object::Simplerelation(_)) => return Ok("SimpleRelation"),
+        Some(Object::Tablerelation(_)) => return Ok("TableRelation"),
+        Some(Object::Inmemoryrelation(_)) => return Ok("InMemoryRelation"),
+        Some(Object::Referencerelation(_)) => return Ok("ReferenceRelation"),
+        Some(Object::Compositerelation(_)) => return Ok("CompositeRelation (join)"),
+        Some(Object::Unionrelation(_)) => return Ok("UnionRelation"),
+        Some(Object::Renamerelation(_)) => return Ok("RenameRelation"),
+        Some(Object::Whererelation(_)) => return Ok("WhereRelation"),
+        Some(Object::Distinctrelation(_)) => "DistinctRelation",
+        Some(Object::Selectpairwiserelation(_)) => "SelectPairwiseRelation",
+        Some(Object::Gatherrelation(_)) => "GatherRelation",
+        Some(Object::Groupbyrelation(_)) => "GroupByRelation",
+        Some(Object::Rangerelation(_)) => "RangeRelation",
+        Some(Object::Pairwiserelation(_)) => "PairwiseRelation",
+        None => "empty relation",
+    };
+    Err(format!(
+        "Unsupported relation type for the parent TableStep: {}",
+        unsupported
+    ))
+}
+
 /// Build a CubeQuery from the Crosstab model
 fn build_cube_query(
     model: &proto::Crosstab,
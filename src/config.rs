@@ -6,12 +6,14 @@
 //! Property definitions and defaults are parsed from operator.json at compile time
 //! via the `OperatorPropertyReader` which ensures single-source-of-truth for defaults.
 
+use crate::logging::LogLevel;
 use crate::operator_props::OperatorPropertyReader;
+use std::collections::HashMap;
 use tercen_rs::client::proto::OperatorSettings;
 use tercen_rs::PlotDimension;
 
 /// How to aggregate multiple data points in the same heatmap cell
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
 pub enum HeatmapCellAggregation {
     /// Use the last data point (matches Tercen's default overdraw behavior)
     #[default]
@@ -22,27 +24,248 @@ pub enum HeatmapCellAggregation {
     Mean,
     /// Compute the median of all data points
     Median,
+    /// Use the most frequent categorical level (continuous colors use the mean)
+    ///
+    /// Ties are broken by the lowest level index so output is deterministic.
+    Mode,
+    /// Mean weighted by another factor, `sum(value * weight) / sum(weight)`
+    ///
+    /// Cells without a positive weight total, or tables without the weight column,
+    /// use the plain mean.
+    WeightedMean { weight_factor: String },
 }
 
 impl HeatmapCellAggregation {
     /// Parse from string value
     ///
     /// This is an internal enum - validation happens in OperatorPropertyReader.get_enum()
-    pub fn parse(s: &str) -> Self {
+    /// "weighted.mean" needs a weight factor; without one it falls back to `Mean`.
+    pub fn parse(s: &str, weight_factor: Option<String>) -> Self {
         match s.to_lowercase().as_str() {
             "first" => Self::First,
             "mean" => Self::Mean,
             "median" => Self::Median,
+            "mode" => Self::Mode,
+            "weighted.mean" => match weight_factor {
+                Some(weight_factor) => Self::WeightedMean { weight_factor },
+                None => {
+                    crate::warn_log!(
+                        "heatmap.cell.aggregation = weighted.mean needs heatmap.cell.weight, using mean"
+                    );
+                    Self::Mean
+                }
+            },
             _ => Self::Last, // "last" or any other value
         }
     }
 }
 
+/// How bars from multiple layers share a position
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BarMode {
+    /// Stack bars on top of each other using Tercen's `.y0s` baseline
+    #[default]
+    Stack,
+    /// Place bars side by side within each X position
+    Dodge,
+}
+
+impl BarMode {
+    /// Parse from string value
+    ///
+    /// This is an internal enum - validation happens in OperatorPropertyReader.get_enum()
+    pub fn parse(s: &str) -> Self {
+        match s.to_lowercase().as_str() {
+            "dodge" => Self::Dodge,
+            _ => Self::Stack, // "stack" or any other value
+        }
+    }
+}
+
+/// How facet labels longer than `facet_label_max_len` are shortened
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FacetLabelOverflow {
+    /// Cut the label and end it with an ellipsis
+    #[default]
+    Truncate,
+    /// Break the label into lines at word boundaries
+    Wrap,
+}
+
+impl FacetLabelOverflow {
+    /// Parse from string value
+    ///
+    /// This is an internal enum - validation happens in OperatorPropertyReader.get_enum()
+    pub fn parse(s: &str) -> Self {
+        match s.to_lowercase().as_str() {
+            "wrap" => Self::Wrap,
+            _ => Self::Truncate, // "truncate" or any other value
+        }
+    }
+}
+
+/// Which axes each facet panel scales independently
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FacetScaleMode {
+    /// All panels share X and Y ranges
+    Fixed,
+    /// Each column has its own X range
+    FreeX,
+    /// Each row has its own Y range
+    #[default]
+    FreeY,
+    /// Free X per column and free Y per row
+    Free,
+}
+
+impl FacetScaleMode {
+    /// Parse from string value
+    ///
+    /// This is an internal enum - validation happens in OperatorPropertyReader.get_enum()
+    pub fn parse(s: &str) -> Self {
+        match s.to_lowercase().as_str() {
+            "fixed" => Self::Fixed,
+            "free_x" => Self::FreeX,
+            "free" => Self::Free,
+            _ => Self::FreeY, // "free_y" or any other value
+        }
+    }
+}
+
+/// Accessible palette forced over the crosstab palettes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PaletteOverride {
+    /// Keep the crosstab palettes
+    #[default]
+    None,
+    /// Okabe-Ito for categorical colors (Viridis for continuous, if enabled)
+    OkabeIto,
+}
+
+impl PaletteOverride {
+    /// Parse from string value
+    ///
+    /// This is an internal enum - validation happens in OperatorPropertyReader.get_enum()
+    pub fn parse(s: &str) -> Self {
+        match s.to_lowercase().as_str() {
+            "okabe-ito" => Self::OkabeIto,
+            _ => Self::None, // "none" or any other value
+        }
+    }
+}
+
+/// Order of categorical legend entries
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LegendSort {
+    /// Factor-level order from the color table (e.g. Low, Medium, High)
+    #[default]
+    Data,
+    /// Alphabetical by label
+    Alpha,
+}
+
+impl LegendSort {
+    /// Parse from string value
+    ///
+    /// This is an internal enum - validation happens in OperatorPropertyReader.get_enum()
+    pub fn parse(s: &str) -> Self {
+        match s.to_lowercase().as_str() {
+            "alpha" => Self::Alpha,
+            _ => Self::Data, // "data" or any other value
+        }
+    }
+}
+
+/// Legend title overrides
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LegendTitles {
+    /// Title of a single-section legend, whatever its source
+    pub title: Option<String>,
+    /// Display title per color factor name
+    pub by_factor: HashMap<String, String>,
+}
+
+impl LegendTitles {
+    /// Display title for a legend (section) titled `name`
+    ///
+    /// Joined titles of categorical factors ("a, b") are mapped factor by factor.
+    pub fn display(&self, name: &str) -> String {
+        if let Some(title) = self.by_factor.get(name) {
+            return title.clone();
+        }
+        name.split(", ")
+            .map(|factor| self.by_factor.get(factor).map_or(factor, String::as_str))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Where the continuous legend's min/max comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LegendRange {
+    /// Use the palette's color stop range
+    #[default]
+    Palette,
+    /// Use the range of the displayed (aggregated heatmap) values
+    Data,
+}
+
+impl LegendRange {
+    /// Parse from string value
+    ///
+    /// This is an internal enum - validation happens in OperatorPropertyReader.get_enum()
+    pub fn parse(s: &str) -> Self {
+        match s.to_lowercase().as_str() {
+            "data" => Self::Data,
+            _ => Self::Palette, // "palette" or any other value
+        }
+    }
+}
+
+/// How negative values are handled on a sqrt-transformed axis
+///
+/// The inverse of sqrt (squaring) loses the sign, so negative bounds from
+/// background-subtracted data would otherwise produce invalid axis ranges.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SqrtNegativePolicy {
+    /// Start the displayed axis at 0 (values below 0 are clipped)
+    #[default]
+    Clamp,
+    /// Shift the range (and every dequantized value) so the minimum is 0
+    Shift,
+    /// Keep negative values and show the axis in sqrt units (no inversion)
+    SignedSqrt,
+}
+
+impl SqrtNegativePolicy {
+    /// Parse from string value
+    ///
+    /// This is an internal enum - validation happens in OperatorPropertyReader.get_enum()
+    /// (or in the transform spec parser for `sqrt:negative=...`)
+    pub fn parse(s: &str) -> Self {
+        match s.to_lowercase().as_str() {
+            "shift" => Self::Shift,
+            "signed" => Self::SignedSqrt,
+            _ => Self::Clamp, // "clamp" or any other value
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct OperatorConfig {
     /// Number of rows per chunk (default: 10000, not in operator.json)
     pub chunk_size: usize,
 
+    /// Target size of one streamed TSON message in bytes
+    /// Chunks are reduced below chunk_size for wide column sets
+    pub message_budget_bytes: usize,
+
+    /// Time limit for heatmap aggregation in seconds (None = no limit)
+    pub heatmap_aggregation_timeout_secs: Option<u64>,
+
+    /// Row chunks streamed concurrently during heatmap aggregation (1-16)
+    pub max_concurrent_chunks: usize,
+
     /// Theme name: "gray", "bw", "minimal"
     /// Matches ggplot2's theme_gray(), theme_bw(), theme_minimal()
     pub theme: String,
@@ -106,10 +329,10 @@ pub struct OperatorConfig {
     /// Plot title justification (anchor point): (x, y) where x,y ∈ [0,1]
     pub plot_title_justification: Option<(f64, f64)>,
 
-    /// X-axis label (optional)
+    /// X-axis label (None = the X factor name)
     pub x_axis_label: Option<String>,
 
-    /// Y-axis label (optional)
+    /// Y-axis label (None = the Y factor name(s))
     pub y_axis_label: Option<String>,
 
     /// X-axis tick label rotation in degrees (0 = horizontal, 90 = vertical)
@@ -121,17 +344,104 @@ pub struct OperatorConfig {
     /// How to aggregate multiple data points in the same heatmap cell
     pub heatmap_cell_aggregation: HeatmapCellAggregation,
 
+    /// Bar chart layout for multiple layers: "stack" or "dodge"
+    pub bar_mode: BarMode,
+
+    /// Bar charts: draw a histogram of the Y values per facet instead of the values
+    pub histogram: bool,
+
+    /// Number of histogram bins per facet (2-1000)
+    pub histogram_bins: usize,
+
+    /// Continuous legend range: "palette" (default) or "data" (aggregated heatmap values)
+    pub legend_range: LegendRange,
+
+    /// Categorical legend entry order: "data" (default, color table order) or "alpha"
+    pub legend_sort: LegendSort,
+
+    /// Omit categorical legend entries that never occur in the data (default true)
+    pub legend_drop_unused: bool,
+
+    /// Legend title replacing the factor name(s) of a single-section legend
+    pub legend_title: Option<String>,
+
+    /// Legend section titles per color factor name (JSON object property)
+    pub legend_titles: HashMap<String, String>,
+
     /// Point shapes per layer (ggplot2 pch values 0-25)
     /// Cycles through layers based on .axisIndex.
     /// Common shapes: 19=filled circle, 15=filled square, 17=filled triangle
     pub layer_shapes: Vec<i32>,
 
+    /// Point sizes per layer in pixels (UI scale 1-10 * multiplier)
+    /// Cycles through layers like `layer_shapes`; empty = `point_size` for all layers.
+    pub layer_point_sizes: Vec<f64>,
+
+    /// Custom layer palette (RGB), overriding the crosstab palette when non-empty
+    /// Cycles through layers based on .axisIndex.
+    pub categorical_palette: Vec<[u8; 3]>,
+
+    /// Color for rows whose continuous color value is NaN/Inf
+    /// None = drop those rows
+    pub nan_color: Option<[u8; 3]>,
+
+    /// Fixed value range for continuous color palettes, shared by all pages
+    /// None = each palette keeps its own range
+    pub color_range: Option<(f64, f64)>,
+
+    /// Percentiles (low, high) of the color values continuous palettes are clamped to
+    /// None = palette range; ignored when color_range is set
+    pub color_clip_percentiles: Option<(f64, f64)>,
+
+    /// Quantile bands continuous colors are binned into (2-20, None = smooth ramp)
+    pub color_bins: Option<usize>,
+
+    /// Which axes are free per facet panel
+    pub facet_scales: FacetScaleMode,
+
+    /// Facet column whose values order the facet panels (None = table order)
+    pub facet_order: Option<String>,
+
+    /// Preview: render only the first N facet columns and rows (None = all)
+    pub max_facets: Option<usize>,
+
+    /// Drop facet columns/rows whose cells all have fewer points (None = keep all)
+    pub min_points_per_facet: Option<usize>,
+
+    /// Downsample facet cells to at most this many points (None = all points)
+    pub max_points_per_facet: Option<usize>,
+
+    /// Render each facet cell to its own image (facet_{ci}_{ri}) instead of a grid
+    pub split_facets: bool,
+
+    /// Stack all pages into one tall image instead of one image per page
+    pub combine_pages: bool,
+
+    /// Maximum facet label length in characters (None = full labels)
+    pub facet_label_max_len: Option<usize>,
+
+    /// How longer facet labels are shortened: "truncate" or "wrap"
+    pub facet_label_overflow: FacetLabelOverflow,
+
+    /// Accessible palette forced over all categorical colors
+    pub palette_override: PaletteOverride,
+
+    /// With a palette override, also map continuous palettes to Viridis
+    pub palette_override_continuous: bool,
+
     /// Global opacity for data geoms (0.0 = transparent, 1.0 = opaque)
     pub opacity: f64,
 
-    /// Output format: "png", "svg", or "hsvg" (hybrid: vector chrome + rasterized data)
+    /// Horizontal jitter for categorical-X points, as a fraction of the category
+    /// band width (None = no jitter)
+    pub jitter_width: Option<f64>,
+
+    /// Output format: "png", "webp", "svg", or "hsvg" (hybrid: vector chrome + rasterized data)
     pub output_format: String,
 
+    /// WebP quality (0-100, 100 = lossless), used when output_format is "webp"
+    pub webp_quality: f32,
+
     /// Disable major grid lines
     pub grid_major_disable: bool,
 
@@ -144,6 +454,9 @@ pub struct OperatorConfig {
     /// Disable all text labels (axis titles, tick labels, plot title)
     pub text_disable: bool,
 
+    /// Also output the plotted data (.ci, .ri, .x, .y, .color) as a CSV per image
+    pub export_data: bool,
+
     /// Plot title font size in points (None = use theme default)
     pub title_font_size: Option<f64>,
 
@@ -165,6 +478,30 @@ pub struct OperatorConfig {
 
     /// X-axis transform override
     pub x_transform_override: Option<String>,
+
+    /// Pinned Y-axis range "min,max", overriding the crosstab's (None = crosstab or auto)
+    pub y_axis_range: Option<(f64, f64)>,
+
+    /// Pinned X-axis range "min,max", overriding the crosstab's (None = crosstab or auto)
+    pub x_axis_range: Option<(f64, f64)>,
+
+    /// Reverse the Y axis (high values at the bottom)
+    pub flip_y: bool,
+
+    /// Y transform of each layer's values, in layer order (empty = axis transform)
+    pub layer_y_transforms: Vec<String>,
+
+    /// Negative value handling for sqrt-transformed axes
+    /// Overridden per axis by `sqrt:negative=...` in the transform spec
+    pub sqrt_negative_policy: SqrtNegativePolicy,
+
+    /// Log verbosity: "error", "info" (default), or "debug"
+    /// DEBUG diagnostics are only printed at "debug"
+    pub log_level: LogLevel,
+
+    /// Panic when a facet cell has no axis range (debugging aid)
+    /// When false, a range is synthesized from neighboring cells with a warning
+    pub strict_axis_ranges: bool,
 }
 
 impl OperatorConfig {
@@ -176,9 +513,11 @@ impl OperatorConfig {
     /// # Arguments
     /// * `operator_settings` - Operator settings from Tercen
     /// * `ui_point_size` - Point size from crosstab model (UI scale 1-10), None = use default (4)
+    /// * `ui_opacity` - Point opacity from crosstab chart settings (0-1), None = opaque (1.0)
     pub fn from_properties(
         operator_settings: Option<&OperatorSettings>,
         ui_point_size: Option<i32>,
+        ui_opacity: Option<f64>,
     ) -> Result<Self, String> {
         let props = OperatorPropertyReader::new(operator_settings);
 
@@ -209,6 +548,11 @@ impl OperatorConfig {
         // Chunk size (not in operator.json, internal setting)
         let chunk_size = 10_000usize;
 
+        // TSON message budget (MB), caps chunk size for wide column sets
+        let message_budget_bytes =
+            (props.get_f64_in_range("stream.message.budget.mb", 0.1, 64.0)? * 1024.0 * 1024.0)
+                as usize;
+
         // PNG compression: validated enum
         let png_compression = props.get_enum("png.compression")?;
 
@@ -230,29 +574,186 @@ impl OperatorConfig {
         let y_tick_rotation = props.get_f64("axis.y.tick.rotation")?;
 
         // Heatmap cell aggregation: validated enum
-        let heatmap_cell_aggregation =
-            HeatmapCellAggregation::parse(&props.get_enum("heatmap.cell.aggregation")?);
+        let heatmap_cell_aggregation = HeatmapCellAggregation::parse(
+            &props.get_enum("heatmap.cell.aggregation")?,
+            props.get_optional_string("heatmap.cell.weight"),
+        );
+
+        // Bar layout: validated enum
+        let bar_mode = BarMode::parse(&props.get_enum("bar.mode")?);
+
+        // Histogram of the Y values (bar charts)
+        let histogram = props.get_bool("bar.histogram")?;
+        let histogram_bins = props.get_f64_in_range("bar.histogram.bins", 2.0, 1000.0)? as usize;
+
+        // Continuous legend range source: validated enum
+        let legend_range = LegendRange::parse(&props.get_enum("legend.range")?);
+
+        // Categorical legend entry order: validated enum
+        let legend_sort = LegendSort::parse(&props.get_enum("legend.sort")?);
+
+        // Hide categories listed by the color table but absent from the data
+        let legend_drop_unused = props.get_bool("legend.drop.unused")?;
+
+        // Legend title overrides (empty = factor names)
+        let legend_title = props.get_optional_string("legend.title");
+        let legend_titles = props.get_string_map("legend.titles")?;
 
         // Point shapes per layer
         let layer_shapes = props.get_shape_list("point.shapes")?;
 
+        // Custom layer palette (hex colors validated here)
+        let categorical_palette = props.get_color_list("color.palette")?;
+
+        // Color for NaN/Inf continuous color values (empty = drop rows)
+        let nan_color = props.get_optional_color("color.nan")?;
+
+        // Fixed continuous color range (empty = palette range)
+        let color_range = props.get_range("color.range")?;
+
+        // Percentile clipping of continuous colors (empty = palette range)
+        let color_clip_percentiles = match props.get_range("color.clip.percentiles")? {
+            Some((low, high)) if low < 0.0 || high > 100.0 => {
+                return Err(format!(
+                    "Invalid value '{},{}' for property 'color.clip.percentiles'. Expected percentiles between 0 and 100.",
+                    low, high
+                ))
+            }
+            percentiles => percentiles,
+        };
+
+        // Binned continuous colors (empty = smooth ramp)
+        let color_bins = match props.get_optional_f64("color.bins")? {
+            Some(bins) if (2.0..=20.0).contains(&bins) && bins.fract() == 0.0 => {
+                Some(bins as usize)
+            }
+            Some(bins) => {
+                return Err(format!(
+                    "Invalid value '{}' for property 'color.bins'. Expected a whole number from 2 to 20.",
+                    bins
+                ))
+            }
+            None => None,
+        };
+
+        // Facet scales: validated enum
+        let facet_scales = FacetScaleMode::parse(&props.get_enum("facet.scales")?);
+
+        // Facet panel order (empty = facet table order)
+        let facet_order = props.get_optional_string("facet.order");
+
+        // Facet preview subset (empty = all facets)
+        let max_facets = match props.get_optional_f64("facet.preview.max")? {
+            Some(n) if n >= 1.0 && n.fract() == 0.0 => Some(n as usize),
+            Some(n) => {
+                return Err(format!(
+                "Invalid value '{}' for property 'facet.preview.max'. Expected a positive integer.",
+                n
+            ))
+            }
+            None => None,
+        };
+
+        // Sparse facet suppression (empty = keep all facets)
+        let min_points_per_facet = match props.get_optional_f64("facet.min.points")? {
+            Some(n) if n >= 1.0 && n.fract() == 0.0 => Some(n as usize),
+            Some(n) => {
+                return Err(format!(
+                "Invalid value '{}' for property 'facet.min.points'. Expected a positive integer.",
+                n
+            ))
+            }
+            None => None,
+        };
+
+        // Categorical-X jitter (empty = none); below 1 so points stay in their band
+        let jitter_width = match props.get_optional_f64("jitter.width")? {
+            Some(w) if w > 0.0 && w < 1.0 => Some(w),
+            Some(w) => {
+                return Err(format!(
+                    "Invalid value '{}' for property 'jitter.width'. Expected a number in (0, 1).",
+                    w
+                ))
+            }
+            None => None,
+        };
+
+        // Dense facet downsampling (empty = all points)
+        let max_points_per_facet = match props.get_optional_f64("facet.max.points")? {
+            Some(n) if n >= 1.0 && n.fract() == 0.0 => Some(n as usize),
+            Some(n) => {
+                return Err(format!(
+                "Invalid value '{}' for property 'facet.max.points'. Expected a positive integer.",
+                n
+            ))
+            }
+            None => None,
+        };
+
+        // One image per facet cell
+        let split_facets = props.get_bool("facet.split")?;
+
+        // All pages in one image
+        let combine_pages = props.get_bool("pages.combine")?;
+
+        // Facet label length limit (empty = full labels)
+        let facet_label_max_len = match props.get_optional_f64("facet.label.max.length")? {
+            Some(n) if n >= 2.0 && n.fract() == 0.0 => Some(n as usize),
+            Some(n) => {
+                return Err(format!(
+                "Invalid value '{}' for property 'facet.label.max.length'. Expected an integer >= 2.",
+                n
+            ))
+            }
+            None => None,
+        };
+        let facet_label_overflow =
+            FacetLabelOverflow::parse(&props.get_enum("facet.label.overflow")?);
+
+        // Accessible palette override: validated enum
+        let palette_override = PaletteOverride::parse(&props.get_enum("color.palette.override")?);
+        let palette_override_continuous = props.get_bool("color.palette.override.continuous")?;
+
         // Point size: UI value (1-10) * multiplier
         // Default UI value is 4 (from crosstab model, not operator.json)
         let point_size_multiplier = props.get_f64_in_range("point.size.multiplier", 0.01, 100.0)?;
         let ui_size = ui_point_size.unwrap_or(4).clamp(1, 10);
         let point_size = (ui_size as f64) * point_size_multiplier;
 
-        // Opacity for data geoms (0.0 = transparent, 1.0 = opaque)
-        let opacity = props.get_f64_in_range("opacity", 0.0, 1.0)?;
+        // Point sizes per layer, on the same UI scale as the crosstab size
+        let layer_point_sizes = props.get_f64_list("point.sizes")?;
+        if let Some(size) = layer_point_sizes
+            .iter()
+            .find(|s| !(1.0..=10.0).contains(*s))
+        {
+            return Err(format!(
+                "Point size {} in property 'point.sizes' out of range. Must be 1-10.",
+                size
+            ));
+        }
+        let layer_point_sizes: Vec<f64> = layer_point_sizes
+            .iter()
+            .map(|s| s * point_size_multiplier)
+            .collect();
 
-        // Output format: "png", "svg", or "hsvg"
+        // Opacity for data geoms (0.0 = transparent, 1.0 = opaque)
+        // Crosstab chart opacity (default 1.0) scaled by the operator property
+        let ui_opacity = ui_opacity
+            .filter(|a| a.is_finite())
+            .unwrap_or(1.0)
+            .clamp(0.0, 1.0);
+        let opacity = ui_opacity * props.get_f64_in_range("opacity", 0.0, 1.0)?;
+
+        // Output format: "png", "webp", "svg", or "hsvg"
         let output_format = props.get_enum("output.format")?;
+        let webp_quality = props.get_f64_in_range("webp.quality", 0.0, 100.0)? as f32;
 
         // Disable toggles
         let grid_major_disable = props.get_bool("grid.major.disable")?;
         let grid_minor_disable = props.get_bool("grid.minor.disable")?;
         let axis_lines_disable = props.get_bool("axis.lines.disable")?;
         let text_disable = props.get_bool("text.disable")?;
+        let export_data = props.get_bool("data.export")?;
 
         // Font size overrides (None = use theme default)
         let title_font_size = props.get_optional_f64("plot.title.font.size")?;
@@ -276,8 +777,52 @@ impl OperatorConfig {
         let y_transform_override = props.get_optional_string("axis.y.transform");
         let x_transform_override = props.get_optional_string("axis.x.transform");
 
+        // Pinned axis ranges (display only, dequantization keeps the table range)
+        let y_axis_range = props.get_range("axis.y.range")?;
+        let x_axis_range = props.get_range("axis.x.range")?;
+
+        // Reversed Y axis (display only, dequantization unchanged)
+        let flip_y = props.get_bool("axis.y.flip")?;
+
+        // Per-layer Y transforms, validated here so a typo fails before streaming
+        let layer_y_transforms: Vec<String> = props
+            .get_string("axis.y.layer.transforms")
+            .split(';')
+            .map(|s| s.trim().to_string())
+            .collect();
+        for spec in &layer_y_transforms {
+            crate::ggrs_integration::transform::parse_transform(spec).map_err(|e| {
+                format!(
+                    "Invalid value for property 'axis.y.layer.transforms': {}",
+                    e
+                )
+            })?;
+        }
+
+        // Negative handling for sqrt axes: validated enum
+        let sqrt_negative_policy =
+            SqrtNegativePolicy::parse(&props.get_enum("axis.sqrt.negative")?);
+
+        // Log verbosity: validated enum
+        let log_level = LogLevel::parse(&props.get_enum("log.level")?);
+
+        // Heatmap aggregation time limit (0 = no limit)
+        let heatmap_aggregation_timeout_secs =
+            Some(props.get_f64_in_range("heatmap.aggregation.timeout", 0.0, 86400.0)? as u64)
+                .filter(|&secs| secs > 0);
+
+        // Concurrent chunk streams for heatmap aggregation
+        let max_concurrent_chunks =
+            props.get_f64_in_range("heatmap.concurrent.chunks", 1.0, 16.0)? as usize;
+
+        // Strict axis ranges: fail hard on missing cell ranges
+        let strict_axis_ranges = props.get_bool("axis.strict.ranges")?;
+
         Ok(Self {
             chunk_size,
+            message_budget_bytes,
+            heatmap_aggregation_timeout_secs,
+            max_concurrent_chunks,
             theme,
             plot_width,
             plot_height,
@@ -295,13 +840,41 @@ impl OperatorConfig {
             x_tick_rotation,
             y_tick_rotation,
             heatmap_cell_aggregation,
+            bar_mode,
+            histogram,
+            histogram_bins,
+            legend_range,
+            legend_sort,
+            legend_drop_unused,
+            legend_title,
+            legend_titles,
             layer_shapes,
+            layer_point_sizes,
+            categorical_palette,
+            nan_color,
+            color_range,
+            color_clip_percentiles,
+            color_bins,
+            facet_scales,
+            facet_order,
+            max_facets,
+            min_points_per_facet,
+            max_points_per_facet,
+            split_facets,
+            combine_pages,
+            jitter_width,
+            facet_label_max_len,
+            facet_label_overflow,
+            palette_override,
+            palette_override_continuous,
             opacity,
             output_format,
+            webp_quality,
             grid_major_disable,
             grid_minor_disable,
             axis_lines_disable,
             text_disable,
+            export_data,
             title_font_size,
             axis_label_font_size,
             tick_label_font_size,
@@ -309,6 +882,13 @@ impl OperatorConfig {
             filename,
             y_transform_override,
             x_transform_override,
+            y_axis_range,
+            x_axis_range,
+            flip_y,
+            layer_y_transforms,
+            sqrt_negative_policy,
+            log_level,
+            strict_axis_ranges,
         })
     }
 
@@ -363,6 +943,19 @@ impl OperatorConfig {
         self.resolve_dimensions_with_crosstab(None, n_col_facets, n_row_facets)
     }
 
+    /// Point size of each of `n_layers` layers, in `.axisIndex` order
+    ///
+    /// Cycles through `layer_point_sizes`, or repeats `point_size` when no
+    /// per-layer sizes are set.
+    pub fn layer_point_sizes_for(&self, n_layers: usize) -> Vec<f64> {
+        if self.layer_point_sizes.is_empty() {
+            return vec![self.point_size; n_layers];
+        }
+        (0..n_layers)
+            .map(|i| self.layer_point_sizes[i % self.layer_point_sizes.len()])
+            .collect()
+    }
+
     /// Convert legend config to GGRS LegendPosition enum
     ///
     /// Matches ggplot2 semantics exactly. Note that legend.justification is stored
@@ -33,34 +33,63 @@ struct CachedChunk {
 
 /// Shared cache for streamed data chunks
 ///
-/// This is wrapped in Arc<Mutex<>> so multiple FilteredStreamGenerator
-/// instances can share the same cache.
+/// This is wrapped in Arc<Mutex<>> so the page generators of a multi-page plot
+/// (or multiple FilteredStreamGenerator instances) can share the same cache.
+/// Every lookup is counted as a hit or a miss.
+#[derive(Default)]
 pub struct DataCache {
     /// Cached chunks, keyed by offset
     chunks: HashMap<usize, CachedChunk>,
-    /// Total rows in the dataset
-    #[allow(dead_code)]
-    total_rows: usize,
+    /// Lookups served from the cache
+    hits: usize,
+    /// Lookups that found no chunk
+    misses: usize,
 }
 
 impl DataCache {
-    #[allow(dead_code)]
-    fn new(total_rows: usize) -> Self {
-        Self {
-            chunks: HashMap::new(),
-            total_rows,
+    /// Create an empty cache
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the cached chunk at `offset`, counting a hit or a miss
+    pub fn get(&mut self, offset: usize) -> Option<DataFrame> {
+        let data = self.chunks.get(&offset).map(|chunk| chunk.data.clone());
+        if data.is_some() {
+            self.hits += 1;
+        } else {
+            self.misses += 1;
         }
+        data
     }
 
-    /// Get cached chunk or return None if not cached
-    fn get(&self, offset: usize) -> Option<&CachedChunk> {
-        self.chunks.get(&offset)
+    /// True when the chunk at `offset` is cached (not counted as a lookup)
+    pub fn contains(&self, offset: usize) -> bool {
+        self.chunks.contains_key(&offset)
     }
 
     /// Store a chunk in the cache
-    fn insert(&mut self, offset: usize, data: DataFrame) {
+    pub fn insert(&mut self, offset: usize, data: DataFrame) {
         self.chunks.insert(offset, CachedChunk { offset, data });
     }
+
+    /// Number of lookups served from the cache
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+
+    /// Number of lookups that found no chunk
+    pub fn misses(&self) -> usize {
+        self.misses
+    }
+
+    /// Estimated memory held by the cached chunks, in bytes
+    pub fn size_bytes(&self) -> usize {
+        self.chunks
+            .values()
+            .map(|chunk| chunk.data.inner().estimated_size())
+            .sum()
+    }
 }
 
 /// Wrapper around TercenStreamGenerator that filters data for a specific page
@@ -223,20 +252,15 @@ impl StreamGenerator for FilteredStreamGenerator {
         let end = data_range.end;
 
         // Try to get from cache first
-        {
-            let cache = self.cache.lock().unwrap();
-            if let Some(cached_chunk) = cache.get(offset) {
-                eprintln!("DEBUG: Cache HIT for offset {}", offset);
-                // Filter and return cached data
-                return self.filter_dataframe(&cached_chunk.data);
-            }
+        let cached = self.cache.lock().unwrap().get(offset);
+        if let Some(data) = cached {
+            crate::debug_log!("Cache HIT for offset {}", offset);
+            // Filter and return cached data
+            return self.filter_dataframe(&data);
         }
 
         // Cache miss - stream from underlying generator
-        eprintln!(
-            "DEBUG: Cache MISS for offset {} - streaming from Tercen",
-            offset
-        );
+        crate::debug_log!("Cache MISS for offset {} - streaming from Tercen", offset);
         let chunk = self.inner.query_data_multi_facet(Range::new(offset, end));
 
         // Store in cache
@@ -0,0 +1,131 @@
+//! Local TSON fixtures for offline testing
+//!
+//! `FixtureStreamer` serves tables from `<FIXTURE_DIR>/<table_id>.tson` files through
+//! the same `get_schema` / `stream_tson` calls as `TableStreamer`, so rendering can
+//! be debugged and tested against captured data without a Tercen connection.
+//! `DataStreamer` picks the fixture source when `TERCEN_URI` is unset and
+//! `FIXTURE_DIR` is set.
+//!
+//! A fixture is a whole table as returned by `stream_tson`. It is served in one
+//! piece: the request at offset 0 returns the full table and later offsets return
+//! no data, which every caller treats as the end of the table. Column selection is
+//! not applied, so fixtures should be captured with the columns the chart uses.
+
+use std::error::Error;
+use std::path::PathBuf;
+use tercen_rs::client::proto::{e_column_schema, e_schema, ColumnSchema, EColumnSchema, ESchema};
+use tercen_rs::{tson_to_dataframe, TableStreamer};
+
+/// Table source backed by `.tson` files in a directory, keyed by table ID
+#[derive(Debug, Clone)]
+pub struct FixtureStreamer {
+    dir: PathBuf,
+}
+
+impl FixtureStreamer {
+    /// Create a streamer reading fixtures from `dir`
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Fixture streamer from `FIXTURE_DIR`, only when `TERCEN_URI` is unset
+    pub fn from_env() -> Option<Self> {
+        if std::env::var("TERCEN_URI").is_ok() {
+            return None;
+        }
+        std::env::var("FIXTURE_DIR").ok().map(Self::new)
+    }
+
+    /// Read the raw TSON of a table
+    fn read(&self, table_id: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+        let path = self.dir.join(format!("{}.tson", table_id));
+        std::fs::read(&path)
+            .map_err(|e| format!("Failed to read fixture {}: {}", path.display(), e).into())
+    }
+
+    /// Table schema (row count, column names and types) derived from the fixture
+    pub async fn get_schema(&self, table_id: &str) -> Result<ESchema, Box<dyn Error>> {
+        use polars::prelude::DataType;
+
+        let df = tson_to_dataframe(&self.read(table_id)?)?;
+        let columns = df
+            .get_columns()
+            .iter()
+            .map(|column| {
+                let column_type = match column.dtype() {
+                    DataType::Float32 | DataType::Float64 => "double",
+                    DataType::Int32 | DataType::UInt32 => "int32",
+                    DataType::Int64 | DataType::UInt64 => "int64",
+                    _ => "string",
+                };
+                EColumnSchema {
+                    object: Some(e_column_schema::Object::Columnschema(ColumnSchema {
+                        name: column.name().to_string(),
+                        r#type: column_type.to_string(),
+                        n_rows: df.height() as _,
+                        ..Default::default()
+                    })),
+                }
+            })
+            .collect();
+
+        Ok(ESchema {
+            object: Some(e_schema::Object::Tableschema(
+                tercen_rs::client::proto::TableSchema {
+                    id: table_id.to_string(),
+                    n_rows: df.height() as _,
+                    columns,
+                    ..Default::default()
+                },
+            )),
+        })
+    }
+
+    /// Table data as TSON: the whole fixture at offset 0, nothing after
+    pub async fn stream_tson(
+        &self,
+        table_id: &str,
+        _columns: Option<Vec<String>>,
+        offset: i64,
+        _limit: i64,
+    ) -> Result<Vec<u8>, Box<dyn Error>> {
+        if offset > 0 {
+            return Ok(Vec::new());
+        }
+        self.read(table_id)
+    }
+}
+
+/// Table source used by the stream generator: Tercen gRPC or local fixtures
+pub enum DataStreamer<'a> {
+    /// Tables streamed from Tercen
+    Tercen(TableStreamer<'a>),
+    /// Tables read from local `.tson` fixtures
+    Fixture(FixtureStreamer),
+}
+
+impl DataStreamer<'_> {
+    /// Fetch a table schema
+    pub async fn get_schema(&self, table_id: &str) -> Result<ESchema, Box<dyn Error>> {
+        match self {
+            Self::Tercen(streamer) => Ok(streamer.get_schema(table_id).await?),
+            Self::Fixture(streamer) => streamer.get_schema(table_id).await,
+        }
+    }
+
+    /// Fetch `limit` rows of `columns` starting at `offset`, as TSON
+    pub async fn stream_tson(
+        &self,
+        table_id: &str,
+        columns: Option<Vec<String>>,
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<u8>, Box<dyn Error>> {
+        match self {
+            Self::Tercen(streamer) => Ok(streamer
+                .stream_tson(table_id, columns, offset, limit)
+                .await?),
+            Self::Fixture(streamer) => streamer.stream_tson(table_id, columns, offset, limit).await,
+        }
+    }
+}
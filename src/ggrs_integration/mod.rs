@@ -5,13 +5,23 @@
 //! Structure:
 //! - `stream_generator.rs`: TercenStreamGenerator implementing GGRS StreamGenerator trait
 //! - `cached_stream_generator.rs`: Caching wrapper for pagination optimization
+//! - `transform.rs`: Axis transform specification parsing (e.g. `asinh:cofactor=150`)
+//! - `palette.rs`: Colorblind-safe palette override (Okabe-Ito, Viridis)
+//! - `fixture_streamer.rs`: Local `.tson` table fixtures for offline testing
 //! - `plot_builder.rs`: Helper to build GGRS plot specs from operator properties
 //! - `renderer.rs`: Wrapper around GGRS ImageRenderer
 
 // Module declarations
 pub mod cached_stream_generator;
+pub mod fixture_streamer;
+pub mod palette;
 pub mod stream_generator;
+pub mod transform;
 
 // Re-exports
-pub use cached_stream_generator::FilteredStreamGenerator;
-pub use stream_generator::{TercenStreamConfig, TercenStreamGenerator};
+pub use cached_stream_generator::{DataCache, FilteredStreamGenerator};
+pub use fixture_streamer::{DataStreamer, FixtureStreamer};
+pub use stream_generator::{
+    new_range_cache, CachedXRanges, DataExport, RangeCache, TercenStreamConfig,
+    TercenStreamGenerator,
+};
@@ -0,0 +1,319 @@
+//! Accessible palette override
+//!
+//! When `color.palette.override` is set, crosstab palettes are replaced at
+//! initialization: categorical colors (factor levels and layer colors) use the
+//! Okabe-Ito palette and, optionally, continuous palettes use Viridis over the
+//! original value range.
+//!
+//! `color.range` pins continuous palettes to a fixed value range so colors are
+//! comparable across pages (see `rescale_stops`); `color.clip.percentiles` clamps
+//! them to data percentiles instead (see `percentile_range`). `color.bins` turns
+//! them into constant-color quantile bands (see `quantile_breaks`, `bin_stops`).
+
+use crate::config::PaletteOverride;
+
+/// Okabe-Ito colorblind-safe palette (black last so it is used least)
+pub const OKABE_ITO: [[u8; 3]; 8] = [
+    [0xE6, 0x9F, 0x00], // orange
+    [0x56, 0xB4, 0xE9], // sky blue
+    [0x00, 0x9E, 0x73], // bluish green
+    [0xF0, 0xE4, 0x42], // yellow
+    [0x00, 0x72, 0xB2], // blue
+    [0xD5, 0x5E, 0x00], // vermillion
+    [0xCC, 0x79, 0xA7], // reddish purple
+    [0x00, 0x00, 0x00], // black
+];
+
+/// Viridis ramp, evenly spaced from low to high
+pub const VIRIDIS: [[u8; 3]; 9] = [
+    [0x44, 0x01, 0x54],
+    [0x47, 0x2D, 0x7B],
+    [0x3B, 0x52, 0x8B],
+    [0x2C, 0x72, 0x8E],
+    [0x21, 0x91, 0x8C],
+    [0x28, 0xAE, 0x80],
+    [0x5E, 0xC9, 0x62],
+    [0xAD, 0xDC, 0x30],
+    [0xFD, 0xE7, 0x25],
+];
+
+/// Categorical colors for an override (None = keep the crosstab palette)
+pub fn categorical_colors(mode: PaletteOverride) -> Option<&'static [[u8; 3]]> {
+    match mode {
+        PaletteOverride::None => None,
+        PaletteOverride::OkabeIto => Some(&OKABE_ITO),
+    }
+}
+
+/// Color of categorical level `level` in `palette`
+pub fn level_color(palette: &[[u8; 3]], level: i64) -> [u8; 3] {
+    palette[level.max(0) as usize % palette.len()]
+}
+
+/// Recolor a categorical color factor
+///
+/// Labels are assigned palette colors in sorted order, matching the level order
+/// of `.colorLevels` and the legend.
+pub fn recolor_categorical(mapping: &mut tercen_rs::ColorMapping, palette: &[[u8; 3]]) {
+    if let tercen_rs::ColorMapping::Categorical(color_map) = mapping {
+        let mut labels: Vec<String> = color_map
+            .mappings
+            .iter()
+            .map(|(label, _)| label.clone())
+            .collect();
+        labels.sort();
+        for (label, color) in color_map.mappings.iter_mut() {
+            let level = labels.iter().position(|l| l == &*label).unwrap_or(0);
+            *color = level_color(palette, level as i64);
+        }
+    }
+}
+
+/// Replace continuous color stops with the Viridis ramp over the same value range
+///
+/// Stops are rebuilt from the first stop so any other stop fields are kept.
+pub fn resample_viridis<S: Clone>(
+    stops: &mut Vec<S>,
+    value: impl Fn(&mut S) -> &mut f64,
+    color: impl Fn(&mut S) -> &mut [u8; 3],
+) {
+    let Some(mut template) = stops.first().cloned() else {
+        return;
+    };
+    let values: Vec<f64> = stops.iter_mut().map(|s| *value(s)).collect();
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    if !min.is_finite() || !max.is_finite() {
+        return;
+    }
+
+    let last = (VIRIDIS.len() - 1) as f64;
+    *stops = VIRIDIS
+        .iter()
+        .enumerate()
+        .map(|(i, rgb)| {
+            *value(&mut template) = min + (max - min) * i as f64 / last;
+            *color(&mut template) = *rgb;
+            template.clone()
+        })
+        .collect();
+}
+
+/// Map continuous color stops linearly onto a fixed `(min, max)` range
+///
+/// Stops keep their relative positions and colors, so the palette shape is
+/// unchanged while every page interpolates over the same range.
+pub fn rescale_stops<S>(stops: &mut [S], value: impl Fn(&mut S) -> &mut f64, range: (f64, f64)) {
+    let values: Vec<f64> = stops.iter_mut().map(|s| *value(s)).collect();
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    if !min.is_finite() || !max.is_finite() {
+        return;
+    }
+
+    let n = (stops.len().max(2) - 1) as f64;
+    for (i, stop) in stops.iter_mut().enumerate() {
+        // Degenerate palettes (all stops equal) are spread evenly
+        let t = if max > min {
+            (*value(stop) - min) / (max - min)
+        } else {
+            i as f64 / n
+        };
+        *value(stop) = range.0 + (range.1 - range.0) * t;
+    }
+}
+
+/// Values at percentiles `(low, high)` (0-100), interpolating between ranks
+///
+/// Sorts `values` in place. None when there are no values or the range is empty.
+pub fn percentile_range(values: &mut [f64], (low, high): (f64, f64)) -> Option<(f64, f64)> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_unstable_by(f64::total_cmp);
+    let (min, max) = (
+        quantile(values, low / 100.0),
+        quantile(values, high / 100.0),
+    );
+    (max > min).then_some((min, max))
+}
+
+/// Value at quantile `q` (0-1) of non-empty sorted `values`, interpolating between ranks
+fn quantile(values: &[f64], q: f64) -> f64 {
+    let rank = q.clamp(0.0, 1.0) * (values.len() - 1) as f64;
+    let (lower, upper) = (rank.floor() as usize, rank.ceil() as usize);
+    values[lower] + (values[upper] - values[lower]) * (rank - lower as f64)
+}
+
+/// Breaks of `bins` equal-count (quantile) bands over `values`
+///
+/// Sorts `values` in place and returns ascending breaks from the minimum to the
+/// maximum. Repeated breaks (ties in the data) are merged, so fewer bands may
+/// result. Empty when there are fewer than two distinct values.
+pub fn quantile_breaks(values: &mut [f64], bins: usize) -> Vec<f64> {
+    if values.is_empty() || bins == 0 {
+        return Vec::new();
+    }
+    values.sort_unstable_by(f64::total_cmp);
+    let mut breaks: Vec<f64> = (0..=bins)
+        .map(|k| quantile(values, k as f64 / bins as f64))
+        .collect();
+    breaks.dedup();
+    if breaks.len() < 2 {
+        breaks.clear();
+    }
+    breaks
+}
+
+/// Color of a continuous ramp at `v`, interpolating linearly between stops
+///
+/// Values beyond the end stops take the end colors.
+fn ramp_color(stops: &[(f64, [u8; 3])], v: f64) -> [u8; 3] {
+    let mut sorted = stops.to_vec();
+    sorted.sort_by(|a, b| a.0.total_cmp(&b.0));
+    match sorted.iter().position(|&(value, _)| value >= v) {
+        None => sorted.last().map_or([0, 0, 0], |&(_, rgb)| rgb),
+        Some(0) => sorted[0].1,
+        Some(i) => {
+            let ((a, from), (b, to)) = (sorted[i - 1], sorted[i]);
+            let t = if b > a { (v - a) / (b - a) } else { 0.0 };
+            std::array::from_fn(|c| {
+                (from[c] as f64 + (to[c] as f64 - from[c] as f64) * t).round() as u8
+            })
+        }
+    }
+}
+
+/// Replace continuous color stops with constant-color bands between `breaks`
+///
+/// Band colors are spread evenly along the original palette (bands are ordinal, so
+/// every band stays distinguishable). Each band is two stops of its color, the
+/// second just below the next break, so interpolation is flat inside a band.
+/// Returns the legend entries ("low–high" label and color), lowest band first.
+pub fn bin_stops<S: Clone>(
+    stops: &mut Vec<S>,
+    value: impl Fn(&mut S) -> &mut f64,
+    color: impl Fn(&mut S) -> &mut [u8; 3],
+    breaks: &[f64],
+) -> Vec<(String, [u8; 3])> {
+    let Some(mut template) = stops.first().cloned() else {
+        return Vec::new();
+    };
+    if breaks.len() < 2 {
+        return Vec::new();
+    }
+    let ramp: Vec<(f64, [u8; 3])> = stops.iter_mut().map(|s| (*value(s), *color(s))).collect();
+    let min = ramp.iter().map(|s| s.0).fold(f64::INFINITY, f64::min);
+    let max = ramp.iter().map(|s| s.0).fold(f64::NEG_INFINITY, f64::max);
+
+    let n = breaks.len() - 1;
+    let epsilon = (breaks[n] - breaks[0]) * 1e-9;
+    let narrowest = breaks
+        .windows(2)
+        .map(|w| w[1] - w[0])
+        .fold(f64::INFINITY, f64::min);
+    let decimals = (1.0 - narrowest.log10().floor()).clamp(0.0, 6.0) as usize;
+    let label = |v: f64| {
+        let text = format!("{:.*}", decimals, v);
+        if text.contains('.') {
+            text.trim_end_matches('0').trim_end_matches('.').to_string()
+        } else {
+            text
+        }
+    };
+
+    let mut binned = Vec::with_capacity(2 * n);
+    let mut entries = Vec::with_capacity(n);
+    for (i, band) in breaks.windows(2).enumerate() {
+        let rgb = ramp_color(&ramp, min + (max - min) * (i as f64 + 0.5) / n as f64);
+        let end = if i + 1 == n {
+            band[1]
+        } else {
+            band[1] - epsilon
+        };
+        for v in [band[0], end] {
+            *value(&mut template) = v;
+            *color(&mut template) = rgb;
+            binned.push(template.clone());
+        }
+        entries.push((format!("{}–{}", label(band[0]), label(band[1])), rgb));
+    }
+    *stops = binned;
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resample_viridis_keeps_range() {
+        let mut stops = vec![(-2.0, [0u8, 0, 0]), (6.0, [255u8, 255, 255])];
+        resample_viridis(&mut stops, |s| &mut s.0, |s| &mut s.1);
+
+        assert_eq!(stops.len(), VIRIDIS.len());
+        assert_eq!(stops[0], (-2.0, VIRIDIS[0]));
+        assert_eq!(stops[4].0, 2.0);
+        assert_eq!(stops[8], (6.0, VIRIDIS[8]));
+    }
+
+    #[test]
+    fn test_rescale_stops_keeps_positions() {
+        let mut stops = vec![(2.0, [0u8, 0, 0]), (3.0, [1u8, 1, 1]), (6.0, [2u8, 2, 2])];
+        rescale_stops(&mut stops, |s| &mut s.0, (0.0, 100.0));
+
+        assert_eq!(stops[0], (0.0, [0, 0, 0]));
+        assert_eq!(stops[1], (25.0, [1, 1, 1]));
+        assert_eq!(stops[2], (100.0, [2, 2, 2]));
+    }
+
+    #[test]
+    fn test_percentile_range_interpolates() {
+        let mut values: Vec<f64> = (0..=100).rev().map(|v| v as f64).collect();
+        values.push(10_000.0);
+        assert_eq!(
+            percentile_range(&mut values, (0.0, 100.0)),
+            Some((0.0, 10_000.0))
+        );
+
+        let mut values = vec![4.0, 0.0, 2.0];
+        assert_eq!(
+            percentile_range(&mut values, (25.0, 75.0)),
+            Some((1.0, 3.0))
+        );
+        assert_eq!(percentile_range(&mut [5.0, 5.0], (2.0, 98.0)), None);
+        assert_eq!(percentile_range(&mut [], (2.0, 98.0)), None);
+    }
+
+    #[test]
+    fn test_quantile_breaks_merge_ties() {
+        let mut values: Vec<f64> = (0..=20).rev().map(|v| v as f64).collect();
+        assert_eq!(
+            quantile_breaks(&mut values, 4),
+            vec![0.0, 5.0, 10.0, 15.0, 20.0]
+        );
+
+        let mut values = vec![1.0, 1.0, 1.0, 1.0, 9.0];
+        assert_eq!(quantile_breaks(&mut values, 4), vec![1.0, 9.0]);
+        assert!(quantile_breaks(&mut [3.0, 3.0], 5).is_empty());
+    }
+
+    #[test]
+    fn test_bin_stops_flat_bands() {
+        let mut stops = vec![(0.0, [0u8, 0, 0]), (1.0, [200u8, 200, 200])];
+        let entries = bin_stops(&mut stops, |s| &mut s.0, |s| &mut s.1, &[0.0, 10.0, 20.0]);
+
+        assert_eq!(
+            entries,
+            vec![
+                ("0–10".to_string(), [50, 50, 50]),
+                ("10–20".to_string(), [150, 150, 150])
+            ]
+        );
+        assert_eq!(stops.len(), 4);
+        assert_eq!(stops[0], (0.0, [50, 50, 50]));
+        assert!(stops[1].0 < 10.0 && stops[1].1 == [50, 50, 50]);
+        assert_eq!(stops[2], (10.0, [150, 150, 150]));
+        assert_eq!(stops[3], (20.0, [150, 150, 150]));
+    }
+}
@@ -3,7 +3,17 @@
 //! This module implements the GGRS `StreamGenerator` trait for Tercen,
 //! enabling lazy loading of data directly from Tercen's gRPC API.
 
-use crate::config::HeatmapCellAggregation;
+use crate::config::{
+    BarMode, FacetLabelOverflow, FacetScaleMode, HeatmapCellAggregation, LegendRange, LegendSort,
+    LegendTitles, PaletteOverride, SqrtNegativePolicy,
+};
+use crate::ggrs_integration::cached_stream_generator::DataCache;
+use crate::ggrs_integration::fixture_streamer::{DataStreamer, FixtureStreamer};
+use crate::ggrs_integration::palette;
+use crate::ggrs_integration::transform::{
+    apply_sqrt_policy, parse_transform, sqrt_policy, ValueTransform,
+};
+use crate::retry::with_retry_from_env;
 use ggrs_core::{
     aes::Aes,
     data::DataFrame,
@@ -14,17 +24,54 @@ use ggrs_core::{
     },
 };
 use polars::prelude::IntoColumn;
-use std::collections::HashMap;
-use std::sync::{Arc, RwLock};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
 use tercen_rs::{
     extract_column_names_from_schema, tson_to_dataframe, ChartKind, FacetInfo, SchemaCache,
     TableStreamer, TercenClient,
 };
 
+/// Parsed X-axis ranges from a single X-axis table
+///
+/// Keyed by the table's own `.ci` value (before mapping to `original_index`),
+/// so the same entry can be reused by every page of a multi-page plot.
+#[derive(Debug, Clone)]
+pub struct CachedXRanges {
+    /// True when the table has a `.ci` column (one range per column facet)
+    pub per_column: bool,
+    /// `.ci` → (min_x, max_x); a global range is stored under key 0
+    pub ranges: HashMap<usize, (f64, f64)>,
+}
+
+/// CSV rows (`.ci,.ri,.x,.y,.color`, no header) of the data served to GGRS,
+/// shared with the pipeline, which drains it after each rendered image
+pub type DataExport = Arc<Mutex<String>>;
+
+/// Shared cache of parsed X-axis ranges, keyed by X-axis table ID
+///
+/// Axis tables are immutable for the lifetime of a task, so entries are never invalidated.
+pub type RangeCache = Arc<RwLock<HashMap<String, CachedXRanges>>>;
+
+/// Create a new empty range cache
+pub fn new_range_cache() -> RangeCache {
+    Arc::new(RwLock::new(HashMap::new()))
+}
+
 /// Default number of categorical color levels in Tercen's built-in palette.
 /// When no actual category names are available, generic labels "Level 0" through "Level 7" are used.
 const DEFAULT_PALETTE_LEVELS: usize = 8;
 
+/// Default target size of one streamed TSON message (3 MB, below gRPC's 4 MB limit)
+pub const DEFAULT_MESSAGE_BUDGET_BYTES: usize = 3 * 1024 * 1024;
+
+/// Row chunk size bounds for heatmap aggregation
+const HEATMAP_CHUNK_ROWS: (usize, usize) = (10_000, 50_000);
+
+/// Rows of all chunks streamed at once during heatmap aggregation
+const HEATMAP_MAX_IN_FLIGHT_ROWS: usize = 200_000;
+
 /// Configuration for creating a TercenStreamGenerator
 ///
 /// Groups all the parameters needed to initialize a stream generator,
@@ -43,6 +90,9 @@ pub struct TercenStreamConfig {
     pub x_axis_table_id: Option<String>,
     /// Chunk size for streaming data
     pub chunk_size: usize,
+    /// Target size of one streamed TSON message in bytes
+    /// Chunks are reduced for wide column sets to stay within it
+    pub message_budget_bytes: usize,
     /// Color factor configurations (legacy - used when all layers share same colors)
     pub color_infos: Vec<tercen_rs::ColorInfo>,
     /// Per-layer color configuration (for mixed-layer scenarios)
@@ -51,22 +101,91 @@ pub struct TercenStreamConfig {
     pub page_factors: Vec<String>,
     /// Optional schema cache for multi-page plots
     pub schema_cache: Option<SchemaCache>,
+    /// Optional parsed X-axis range cache for multi-page plots
+    pub range_cache: Option<RangeCache>,
+    /// Category labels in color level order shared by all pages (see `global_color_levels`)
+    pub color_levels: Option<Vec<String>>,
     /// How to aggregate multiple data points in the same heatmap cell
     pub heatmap_cell_aggregation: HeatmapCellAggregation,
-    /// Y-axis transform type (e.g., "log", "ln", "log10")
+    /// Bar layout for multiple layers (stack or dodge)
+    pub bar_mode: BarMode,
+    /// Bar charts: histogram bins per facet of the Y values (None = plot the values)
+    pub histogram_bins: Option<usize>,
+    /// Continuous legend range source (palette stops or aggregated heatmap values)
+    pub legend_range: LegendRange,
+    /// Categorical legend entry order (color table order or alphabetical)
+    pub legend_sort: LegendSort,
+    /// Omit categorical legend entries whose level never occurs in the data
+    pub legend_drop_unused: bool,
+    /// Legend title overrides (single-section title, titles per factor)
+    pub legend_titles: LegendTitles,
+    /// Y-axis transform type (e.g., "log", "ln", "log10", "log2")
     /// When set, indicates data is pre-transformed and GGRS should invert it
     pub y_transform: Option<String>,
     /// X-axis transform type
     pub x_transform: Option<String>,
+    /// Pinned Y-axis limits (min, max) overriding the displayed range
+    /// Dequantization still uses the Y-axis table range
+    pub y_axis_limits: Option<(f64, f64)>,
+    /// Pinned X-axis limits (min, max) overriding the displayed range
+    pub x_axis_limits: Option<(f64, f64)>,
+    /// Reverse the displayed Y axis (swap `min_axis`/`max_axis`)
+    pub flip_y: bool,
+    /// Y transform specs per layer (by `.axisIndex`); empty specs use the axis transform
+    pub layer_y_transforms: Vec<String>,
+    /// Default negative value policy for sqrt-transformed axes
+    pub sqrt_negative_policy: SqrtNegativePolicy,
     /// Number of layers (axis_queries) - used for layer-based coloring
     pub n_layers: usize,
     /// Palette name for layer-based coloring (from crosstab)
     pub layer_palette_name: Option<String>,
+    /// Custom layer palette (RGB) overriding the crosstab palette when non-empty
+    pub categorical_palette: Vec<[u8; 3]>,
+    /// Color for NaN/Inf continuous color values (None = drop those rows)
+    pub nan_color: Option<[u8; 3]>,
+    /// Fixed value range for continuous palettes (None = palette range)
+    pub color_range: Option<(f64, f64)>,
+    /// Clamp continuous palettes to these percentiles of the data (None = palette range)
+    pub color_clip_percentiles: Option<(f64, f64)>,
+    /// Bin continuous colors into this many quantile bands (None = smooth ramp)
+    pub color_bins: Option<usize>,
+    /// Accessible palette forced over the crosstab palettes
+    pub palette_override: PaletteOverride,
+    /// Map continuous palettes to Viridis under the palette override
+    pub palette_override_continuous: bool,
     /// Y-axis factor names per layer (from axis_queries[i].yAxis.name)
     /// Used for legend entries when layers don't have explicit color factors
     pub layer_y_factor_names: Vec<String>,
+    /// X factor name when it is categorical (string), for non-heatmap charts
+    pub x_categorical_factor: Option<String>,
+    /// Horizontal jitter of categorical-X points (fraction of the category band)
+    pub jitter_width: Option<f64>,
     /// Chart kind - determines data columns needed (e.g., bar charts need .y0s baseline)
     pub chart_kind: ChartKind,
+    /// Panic when a facet cell has no axis range instead of synthesizing a fallback
+    pub strict_axis_ranges: bool,
+    /// Optional chunk cache shared by the pages of a multi-page plot
+    pub data_cache: Option<Arc<Mutex<DataCache>>>,
+    /// Record the plotted data as CSV rows (None = no export)
+    pub data_export: Option<DataExport>,
+    /// Which axes are free per facet panel (default: free Y)
+    pub facet_scales: FacetScaleMode,
+    /// Facet column whose values order the facet panels (None = table order)
+    pub facet_order: Option<String>,
+    /// Preview: render only the first N columns and N rows of facets
+    pub max_facets: Option<usize>,
+    /// Drop facet columns/rows whose cells all have fewer points than this
+    pub min_points_per_facet: Option<usize>,
+    /// Downsample facet cells to at most this many points (None = all points)
+    pub max_points_per_facet: Option<usize>,
+    /// Maximum facet label length in characters (None = full labels)
+    pub facet_label_max_len: Option<usize>,
+    /// How longer facet labels are shortened (truncate or wrap)
+    pub facet_label_overflow: FacetLabelOverflow,
+    /// Time limit for heatmap aggregation (None = no limit)
+    pub aggregation_timeout: Option<Duration>,
+    /// Row chunks streamed concurrently during heatmap aggregation
+    pub max_concurrent_chunks: usize,
 }
 
 impl TercenStreamConfig {
@@ -84,17 +203,52 @@ impl TercenStreamConfig {
             y_axis_table_id: None,
             x_axis_table_id: None,
             chunk_size,
+            message_budget_bytes: DEFAULT_MESSAGE_BUDGET_BYTES,
             color_infos: Vec::new(),
             per_layer_colors: None,
             page_factors: Vec::new(),
             schema_cache: None,
+            range_cache: None,
+            color_levels: None,
             heatmap_cell_aggregation: HeatmapCellAggregation::Last,
+            bar_mode: BarMode::Stack,
+            histogram_bins: None,
+            legend_range: LegendRange::Palette,
+            legend_sort: LegendSort::Data,
+            legend_drop_unused: true,
+            legend_titles: LegendTitles::default(),
             y_transform: None,
             x_transform: None,
+            y_axis_limits: None,
+            x_axis_limits: None,
+            flip_y: false,
+            layer_y_transforms: Vec::new(),
+            sqrt_negative_policy: SqrtNegativePolicy::Clamp,
             n_layers: 1,
             layer_palette_name: None,
+            categorical_palette: Vec::new(),
+            nan_color: None,
+            palette_override: PaletteOverride::None,
+            palette_override_continuous: false,
+            color_range: None,
+            color_clip_percentiles: None,
+            color_bins: None,
             layer_y_factor_names: Vec::new(),
+            x_categorical_factor: None,
+            jitter_width: None,
             chart_kind: ChartKind::Point,
+            strict_axis_ranges: false,
+            data_cache: None,
+            data_export: None,
+            aggregation_timeout: None,
+            max_concurrent_chunks: 1,
+            facet_scales: FacetScaleMode::FreeY,
+            facet_order: None,
+            max_facets: None,
+            min_points_per_facet: None,
+            max_points_per_facet: None,
+            facet_label_max_len: None,
+            facet_label_overflow: FacetLabelOverflow::Truncate,
         }
     }
 
@@ -104,6 +258,12 @@ impl TercenStreamConfig {
         self
     }
 
+    /// Set TSON message size budget in bytes
+    pub fn message_budget_bytes(mut self, bytes: usize) -> Self {
+        self.message_budget_bytes = bytes;
+        self
+    }
+
     /// Set Y-axis table ID
     pub fn y_axis_table(mut self, table_id: Option<String>) -> Self {
         self.y_axis_table_id = table_id;
@@ -140,12 +300,60 @@ impl TercenStreamConfig {
         self
     }
 
+    /// Set X-axis range cache
+    pub fn range_cache(mut self, cache: Option<RangeCache>) -> Self {
+        self.range_cache = cache;
+        self
+    }
+
+    /// Set the category → color level order shared by all pages
+    pub fn color_levels(mut self, levels: Option<Vec<String>>) -> Self {
+        self.color_levels = levels;
+        self
+    }
+
     /// Set heatmap cell aggregation method
     pub fn heatmap_cell_aggregation(mut self, method: HeatmapCellAggregation) -> Self {
         self.heatmap_cell_aggregation = method;
         self
     }
 
+    /// Set bar layout for multiple layers
+    pub fn bar_mode(mut self, mode: BarMode) -> Self {
+        self.bar_mode = mode;
+        self
+    }
+
+    /// Set the histogram bins per facet for bar charts (None = plot the values)
+    pub fn histogram_bins(mut self, bins: Option<usize>) -> Self {
+        self.histogram_bins = bins;
+        self
+    }
+
+    /// Set continuous legend range source
+    pub fn legend_range(mut self, range: LegendRange) -> Self {
+        self.legend_range = range;
+        self
+    }
+
+    /// Set categorical legend entry order
+    pub fn legend_sort(mut self, sort: LegendSort) -> Self {
+        self.legend_sort = sort;
+        self
+    }
+
+    /// Set whether categorical legend entries absent from the data are omitted
+    pub fn legend_drop_unused(mut self, drop: bool) -> Self {
+        self.legend_drop_unused = drop;
+        self
+    }
+
+    /// Set legend title overrides
+    pub fn legend_titles(mut self, titles: LegendTitles) -> Self {
+        self.legend_titles = titles;
+        self
+    }
+
     /// Set Y-axis transform type
     ///
     /// When set, indicates that Y-axis data is pre-transformed (e.g., already in log space).
@@ -161,6 +369,44 @@ impl TercenStreamConfig {
         self
     }
 
+    /// Set pinned Y-axis limits (min, max)
+    ///
+    /// Overrides the displayed axis range (`min_axis`/`max_axis`) while keeping
+    /// `min_value`/`max_value` from the Y-axis table for dequantization.
+    pub fn y_axis_limits(mut self, limits: Option<(f64, f64)>) -> Self {
+        self.y_axis_limits = limits;
+        self
+    }
+
+    /// Set pinned X-axis limits (min, max)
+    pub fn x_axis_limits(mut self, limits: Option<(f64, f64)>) -> Self {
+        self.x_axis_limits = limits;
+        self
+    }
+
+    /// Set reversed Y axis
+    ///
+    /// Swaps the displayed range (`min_axis`/`max_axis`) so high values are at the
+    /// bottom. `min_value`/`max_value` are unchanged, so dequantization is unaffected.
+    pub fn flip_y(mut self, flip: bool) -> Self {
+        self.flip_y = flip;
+        self
+    }
+
+    /// Set the Y transform of each layer's values (empty spec = the axis transform)
+    pub fn layer_y_transforms(mut self, transforms: Vec<String>) -> Self {
+        self.layer_y_transforms = transforms;
+        self
+    }
+
+    /// Set default negative value policy for sqrt-transformed axes
+    ///
+    /// A `sqrt:negative=...` parameter in the transform spec takes precedence.
+    pub fn sqrt_negative_policy(mut self, policy: SqrtNegativePolicy) -> Self {
+        self.sqrt_negative_policy = policy;
+        self
+    }
+
     /// Set number of layers (for layer-based coloring)
     pub fn n_layers(mut self, n: usize) -> Self {
         self.n_layers = n;
@@ -173,11 +419,575 @@ impl TercenStreamConfig {
         self
     }
 
+    /// Set custom layer palette
+    ///
+    /// When non-empty, layer colors cycle through this list instead of the
+    /// crosstab palette.
+    pub fn categorical_palette(mut self, palette: Vec<[u8; 3]>) -> Self {
+        self.categorical_palette = palette;
+        self
+    }
+
+    /// Set color for NaN/Inf continuous color values
+    ///
+    /// When None, rows with non-finite color values are dropped.
+    pub fn nan_color(mut self, color: Option<[u8; 3]>) -> Self {
+        self.nan_color = color;
+        self
+    }
+
+    /// Set accessible palette override
+    ///
+    /// Categorical colors (factor levels and layers) use the override palette;
+    /// continuous palettes use Viridis when `continuous` is set.
+    pub fn palette_override(mut self, mode: PaletteOverride, continuous: bool) -> Self {
+        self.palette_override = mode;
+        self.palette_override_continuous = continuous;
+        self
+    }
+
+    /// Set fixed value range for continuous palettes
+    pub fn color_range(mut self, range: Option<(f64, f64)>) -> Self {
+        self.color_range = range;
+        self
+    }
+
+    /// Set the data percentiles (low, high) continuous palettes are clamped to
+    pub fn color_clip_percentiles(mut self, percentiles: Option<(f64, f64)>) -> Self {
+        self.color_clip_percentiles = percentiles;
+        self
+    }
+
+    /// Set the number of quantile bands continuous colors are binned into
+    pub fn color_bins(mut self, bins: Option<usize>) -> Self {
+        self.color_bins = bins;
+        self
+    }
+
     /// Set Y-axis factor names per layer (for legend entries)
     pub fn layer_y_factor_names(mut self, names: Vec<String>) -> Self {
         self.layer_y_factor_names = names;
         self
     }
+
+    /// Set categorical X factor name (None = numeric X axis)
+    pub fn x_categorical_factor(mut self, factor: Option<String>) -> Self {
+        self.x_categorical_factor = factor;
+        self
+    }
+
+    /// Set categorical-X jitter width (fraction of the category band, None = no jitter)
+    pub fn jitter_width(mut self, width: Option<f64>) -> Self {
+        self.jitter_width = width;
+        self
+    }
+
+    /// Set the shared chunk cache
+    ///
+    /// Data chunks GGRS requests are looked up in the cache first; chunks fetched
+    /// from Tercen are stored in it for the following pages.
+    pub fn data_cache(mut self, cache: Option<Arc<Mutex<DataCache>>>) -> Self {
+        self.data_cache = cache;
+        self
+    }
+
+    /// Set the buffer the served data is exported to as CSV rows
+    pub fn data_export(mut self, export: Option<DataExport>) -> Self {
+        self.data_export = export;
+        self
+    }
+
+    /// Set facet scales (which axes are free per panel)
+    pub fn facet_scales(mut self, scales: FacetScaleMode) -> Self {
+        self.facet_scales = scales;
+        self
+    }
+
+    /// Set the facet column that orders the facet panels (None = table order)
+    pub fn facet_order(mut self, column: Option<String>) -> Self {
+        self.facet_order = column;
+        self
+    }
+
+    /// Set facet preview limit (first N columns × N rows, None = all facets)
+    pub fn max_facets(mut self, max: Option<usize>) -> Self {
+        self.max_facets = max;
+        self
+    }
+
+    /// Set the maximum point count of a facet cell (None = all points)
+    pub fn max_points_per_facet(mut self, max: Option<usize>) -> Self {
+        self.max_points_per_facet = max;
+        self
+    }
+
+    /// Set the minimum point count of a facet (None = keep all facets)
+    pub fn min_points_per_facet(mut self, min: Option<usize>) -> Self {
+        self.min_points_per_facet = min;
+        self
+    }
+
+    /// Set facet label length limit (None = full labels)
+    pub fn facet_label_max_len(mut self, max_len: Option<usize>) -> Self {
+        self.facet_label_max_len = max_len;
+        self
+    }
+
+    /// Set how longer facet labels are shortened
+    pub fn facet_label_overflow(mut self, overflow: FacetLabelOverflow) -> Self {
+        self.facet_label_overflow = overflow;
+        self
+    }
+
+    /// Set heatmap aggregation time limit
+    ///
+    /// The aggregation blocks rendering; when it exceeds the limit the render
+    /// fails with a timeout error instead of hanging.
+    pub fn aggregation_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.aggregation_timeout = timeout;
+        self
+    }
+
+    /// Set how many row chunks heatmap aggregation streams at once (at least 1)
+    pub fn max_concurrent_chunks(mut self, max: usize) -> Self {
+        self.max_concurrent_chunks = max.max(1);
+        self
+    }
+
+    /// Set strict axis range mode
+    ///
+    /// When enabled, a facet cell without an axis range panics (debugging aid).
+    /// Otherwise a range is synthesized from neighboring cells and a warning is logged.
+    pub fn strict_axis_ranges(mut self, strict: bool) -> Self {
+        self.strict_axis_ranges = strict;
+        self
+    }
+}
+
+/// Deterministic 64-bit hash of `seed` (SplitMix64 finalizer)
+fn mix64(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Deterministic pseudo-random value in [0, 1) for `seed`
+fn unit_hash(seed: u64) -> f64 {
+    (mix64(seed) >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Seed for downsampling, so sampled rows don't correlate with the jitter
+const SAMPLE_SEED: u64 = 0x5EED_DA7A_5A3F_1E00;
+
+/// Sampling hash of a main-table row (see `Prescan::add_point`)
+fn sample_hash(row: usize) -> u64 {
+    mix64(SAMPLE_SEED ^ row as u64)
+}
+
+/// Continuous color values kept per factor for percentiles and quantile bins
+///
+/// Quantiles of a uniform sample this size are within about 0.2 percentile ranks of
+/// the exact ones, well below what a color scale can show.
+const COLOR_SAMPLE_SIZE: usize = 100_000;
+
+/// Bounded uniform sample of a factor's finite values, with its exact extremes
+#[derive(Debug, Default)]
+struct ValueSample {
+    /// Finite values offered so far
+    seen: usize,
+    /// Uniform sample of the offered values, at most the sample capacity
+    values: Vec<f64>,
+    /// Exact (min, max) of the offered values
+    range: Option<(f64, f64)>,
+}
+
+impl ValueSample {
+    /// Offer the value of main-table row `row`, keeping at most `capacity` values
+    ///
+    /// Reservoir sampling with the same row hash as `Prescan::add_point`.
+    fn add(&mut self, capacity: usize, row: usize, v: f64) {
+        self.seen += 1;
+        self.range = Some(match self.range {
+            Some((min, max)) => (min.min(v), max.max(v)),
+            None => (v, v),
+        });
+        if self.values.len() < capacity {
+            self.values.push(v);
+        } else {
+            let slot = (sample_hash(row) % self.seen as u64) as usize;
+            if slot < capacity {
+                self.values[slot] = v;
+            }
+        }
+    }
+
+    /// The sampled values; when values were dropped, the sorted sample's ends are
+    /// replaced by the exact extremes so the 0th and 100th percentiles stay exact
+    fn into_values(self) -> Vec<f64> {
+        let mut values = self.values;
+        if let (true, Some((min, max))) = (self.seen > values.len(), self.range) {
+            values.sort_unstable_by(f64::total_cmp);
+            if let Some(first) = values.first_mut() {
+                *first = min;
+            }
+            if let Some(last) = values.last_mut() {
+                *last = max;
+            }
+        }
+        values
+    }
+}
+
+/// What the shared main-table pre-pass collects besides the per-cell point counts
+#[derive(Debug, Default)]
+struct PrescanRequest {
+    /// Reservoir size per cell for downsampling (None = no sampling)
+    sample_size: Option<usize>,
+    /// Distinct `.colorLevels` values
+    color_levels: bool,
+    /// Continuous color factors whose finite values are kept
+    color_factors: Vec<String>,
+}
+
+/// Results of the shared main-table pre-pass (see `TercenStreamGenerator::prescan`)
+///
+/// Cells are keyed by original (.ci, .ri); a missing index column counts as 0.
+#[derive(Debug, Default)]
+struct Prescan {
+    /// Data points per cell
+    counts: HashMap<(usize, usize), usize>,
+    /// Uniform sample of each cell's main-table rows, at most `sample_size` rows
+    reservoirs: HashMap<(usize, usize), Vec<usize>>,
+    /// Distinct `.colorLevels` values (None when not requested or no such column)
+    color_levels: Option<BTreeSet<i64>>,
+    /// Sampled finite values of each requested color factor found in the table
+    color_values: HashMap<String, ValueSample>,
+}
+
+impl Prescan {
+    /// Columns to stream for `request`; only those in `main_columns` when it is known
+    fn columns(request: &PrescanRequest, main_columns: &[String]) -> Vec<String> {
+        let mut columns = vec![".ci", ".ri"];
+        if request.color_levels {
+            columns.push(".colorLevels");
+        }
+        columns.extend(request.color_factors.iter().map(String::as_str));
+        let mut columns: Vec<String> = columns
+            .into_iter()
+            .filter(|c| main_columns.is_empty() || main_columns.iter().any(|m| m == c))
+            .map(str::to_string)
+            .collect();
+        dedup_columns(&mut columns);
+        columns
+    }
+
+    /// Count main-table row `row` in `cell` and offer it to the cell's reservoir
+    ///
+    /// Reservoir sampling (algorithm R): the n-th row of a cell replaces a random
+    /// slot when a draw from [0, n) falls inside the reservoir. The draw is a
+    /// seeded hash of the row index, so the sample is the same on every render.
+    fn add_point(&mut self, sample_size: Option<usize>, row: usize, cell: (usize, usize)) {
+        let n = self.counts.entry(cell).or_insert(0);
+        *n += 1;
+        let Some(size) = sample_size else {
+            return;
+        };
+        let reservoir = self.reservoirs.entry(cell).or_default();
+        if reservoir.len() < size {
+            reservoir.push(row);
+        } else {
+            let slot = (sample_hash(row) % *n as u64) as usize;
+            if slot < size {
+                reservoir[slot] = row;
+            }
+        }
+    }
+
+    /// Accumulate a chunk of the main table whose first row is row `offset`
+    ///
+    /// Requested columns the chunk lacks are skipped.
+    fn visit(
+        &mut self,
+        request: &PrescanRequest,
+        chunk: &polars::frame::DataFrame,
+        offset: usize,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        use polars::prelude::*;
+
+        let cells = facet_cells(chunk)?;
+        for (i, &cell) in cells.iter().enumerate() {
+            self.add_point(request.sample_size, offset + i, cell);
+        }
+
+        if request.color_levels {
+            if let Ok(levels) = chunk.column(".colorLevels") {
+                let levels = levels.cast(&DataType::Int64)?;
+                self.color_levels
+                    .get_or_insert_with(BTreeSet::new)
+                    .extend(levels.i64()?.into_iter().flatten());
+            }
+        }
+
+        for factor in &request.color_factors {
+            let Ok(column) = chunk.column(factor) else {
+                continue;
+            };
+            let column = column.cast(&DataType::Float64)?;
+            let sample = self.color_values.entry(factor.clone()).or_default();
+            for (i, v) in column.f64()?.into_iter().enumerate() {
+                if let Some(v) = v.filter(|v| v.is_finite()) {
+                    sample.add(COLOR_SAMPLE_SIZE, offset + i, v);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Sampled main-table rows of the cells with more than `size` points
+    fn sampled_rows(&self, size: usize) -> HashMap<(usize, usize), HashSet<usize>> {
+        self.reservoirs
+            .iter()
+            .filter(|(cell, _)| self.counts.get(cell).is_some_and(|&n| n > size))
+            .map(|(&cell, rows)| (cell, rows.iter().copied().collect()))
+            .collect()
+    }
+}
+
+/// Axis ranges and main-table facts read by `TercenStreamGenerator::load_ranges`
+struct LoadedRanges {
+    /// (X, Y) axis of each cell, keyed by original (.ci, .ri)
+    axis_ranges: HashMap<(usize, usize), (AxisData, AxisData)>,
+    /// Rows of the main table
+    total_rows: usize,
+    /// Main table column names (empty when unknown)
+    main_columns: Vec<String>,
+    /// Level labels of a categorical X axis (empty for a numeric X axis)
+    x_categories: Vec<String>,
+}
+
+/// X-axis ranges of a table from the range cache, fetched and cached on a miss
+///
+/// With a shared cache, the pages of a multi-page plot decode the X-axis table once.
+async fn cached_x_ranges<F, Fut>(
+    range_cache: &Option<RangeCache>,
+    x_axis_table_id: &str,
+    fetch: F,
+) -> Result<CachedXRanges, Box<dyn std::error::Error>>
+where
+    F: FnOnce() -> Fut,
+    Fut: std::future::Future<Output = Result<CachedXRanges, Box<dyn std::error::Error>>>,
+{
+    let cached = range_cache
+        .as_ref()
+        .and_then(|c| c.read().unwrap().get(x_axis_table_id).cloned());
+    if let Some(x_ranges) = cached {
+        crate::info_log!(
+            "  Using cached X-axis ranges for table {} ({} rows)",
+            x_axis_table_id,
+            x_ranges.ranges.len()
+        );
+        return Ok(x_ranges);
+    }
+
+    let x_ranges = fetch().await?;
+    if let Some(cache) = range_cache {
+        cache
+            .write()
+            .unwrap()
+            .insert(x_axis_table_id.to_string(), x_ranges.clone());
+    }
+    Ok(x_ranges)
+}
+
+/// Pad a zero-width range (a single unique value) to one centered on the value
+///
+/// The padding is 5% of the value, or 1 around zero. Other ranges, including
+/// non-finite placeholders, are returned unchanged.
+fn pad_zero_width(min: f64, max: f64) -> (f64, f64) {
+    if min == max && min.is_finite() {
+        let pad = if min == 0.0 { 1.0 } else { min.abs() * 0.05 };
+        (min - pad, max + pad)
+    } else {
+        (min, max)
+    }
+}
+
+/// Quantize a Y value over (min, max) into the 0-65535 `.ys` scale
+///
+/// Values outside the range are clamped; an empty range maps to the middle.
+fn quantize_y(y: f64, (min, max): (f64, f64)) -> f64 {
+    if max > min {
+        ((y - min) / (max - min) * 65535.0)
+            .round()
+            .clamp(0.0, 65535.0)
+    } else {
+        32767.0
+    }
+}
+
+/// Re-express a `.y` value of a layer transformed with `layer` on an axis transformed
+/// with `axis`, quantized over the axis `range` (None for non-finite values)
+fn retransform_y(
+    y: f64,
+    layer: ValueTransform,
+    axis: ValueTransform,
+    range: (f64, f64),
+) -> Option<f64> {
+    Some(axis.apply(layer.inverse(y)))
+        .filter(|v| v.is_finite())
+        .map(|v| quantize_y(v, range))
+}
+
+/// Index column `name` of `df` (`.ci`, `.ri`, `.axisIndex`) as non-negative indices
+///
+/// Nulls and negative values count as 0, and so does every row when the column is
+/// missing (an unfaceted or single-layer table).
+fn index_column(
+    df: &polars::frame::DataFrame,
+    name: &str,
+) -> Result<Vec<usize>, Box<dyn std::error::Error>> {
+    use polars::prelude::*;
+
+    match df.column(name) {
+        Ok(c) => Ok(c
+            .cast(&DataType::Int64)?
+            .i64()?
+            .into_iter()
+            .map(|v| v.unwrap_or(0).max(0) as usize)
+            .collect()),
+        Err(_) => Ok(vec![0; df.height()]),
+    }
+}
+
+/// Original facet cell (.ci, .ri) of each row of `df` (see `index_column`)
+fn facet_cells(
+    df: &polars::frame::DataFrame,
+) -> Result<Vec<(usize, usize)>, Box<dyn std::error::Error>> {
+    Ok(index_column(df, ".ci")?
+        .into_iter()
+        .zip(index_column(df, ".ri")?)
+        .collect())
+}
+
+/// Integer column `name` of `df` (e.g. quantized `.xs`/`.ys`), nulls as 0
+fn int_column(
+    df: &polars::frame::DataFrame,
+    name: &str,
+) -> Result<Vec<i64>, Box<dyn std::error::Error>> {
+    use polars::prelude::*;
+
+    Ok(df
+        .column(name)?
+        .cast(&DataType::Int64)?
+        .i64()?
+        .into_iter()
+        .map(|v| v.unwrap_or(0))
+        .collect())
+}
+
+/// Drop repeated column names, keeping the first occurrence of each
+///
+/// A continuous color factor can share its name with another requested column
+/// (e.g. an error or label factor); requesting it twice would give the streamed
+/// table duplicate columns.
+fn dedup_columns(columns: &mut Vec<String>) {
+    let mut seen = HashSet::new();
+    columns.retain(|c| seen.insert(c.clone()));
+}
+
+/// Display order of facet groups by their sort keys
+///
+/// Numeric when every key parses as a number, otherwise alphabetical. The sort is
+/// stable, so equal keys keep their table order. Returns positions into `keys`.
+fn facet_sort_order(keys: &[&str]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..keys.len()).collect();
+    let numbers: Option<Vec<f64>> = keys.iter().map(|k| k.trim().parse().ok()).collect();
+    match numbers {
+        Some(numbers) => order.sort_by(|&a, &b| numbers[a].total_cmp(&numbers[b])),
+        None => order.sort_by(|&a, &b| keys[a].cmp(keys[b])),
+    }
+    order
+}
+
+/// Keep the facet cells whose column and row are both kept
+///
+/// Cells are keyed by original (.ci, .ri). An empty kept list means the axis has
+/// no facet groups, so every index on it is kept.
+fn retain_facet_cells<V>(
+    cells: &mut HashMap<(usize, usize), V>,
+    kept_cols: &[usize],
+    kept_rows: &[usize],
+) {
+    cells.retain(|(col, row), _| {
+        (kept_cols.is_empty() || kept_cols.contains(col))
+            && (kept_rows.is_empty() || kept_rows.contains(row))
+    });
+}
+
+/// Facet columns and rows (original indices) in which every cell has fewer than `min` points
+///
+/// An axis without facet groups counts as a single index 0. None when nothing is
+/// sparse, or when dropping the sparse facets would leave no column or no row.
+fn sparse_facets(
+    col_indices: &[usize],
+    row_indices: &[usize],
+    counts: &HashMap<(usize, usize), usize>,
+    min: usize,
+) -> Option<(Vec<usize>, Vec<usize>)> {
+    let count = |col: usize, row: usize| counts.get(&(col, row)).copied().unwrap_or(0);
+    let or_zero = |indices: &[usize]| {
+        if indices.is_empty() {
+            vec![0]
+        } else {
+            indices.to_vec()
+        }
+    };
+    let (cols_or_zero, rows_or_zero) = (or_zero(col_indices), or_zero(row_indices));
+
+    let sparse_cols: Vec<usize> = col_indices
+        .iter()
+        .copied()
+        .filter(|&col| rows_or_zero.iter().all(|&row| count(col, row) < min))
+        .collect();
+    let sparse_rows: Vec<usize> = row_indices
+        .iter()
+        .copied()
+        .filter(|&row| cols_or_zero.iter().all(|&col| count(col, row) < min))
+        .collect();
+    if sparse_cols.is_empty() && sparse_rows.is_empty() {
+        return None;
+    }
+    if (!col_indices.is_empty() && sparse_cols.len() == col_indices.len())
+        || (!row_indices.is_empty() && sparse_rows.len() == row_indices.len())
+    {
+        crate::warn_log!(
+            "every facet has fewer than {} points - keeping all facets",
+            min
+        );
+        return None;
+    }
+    Some((sparse_cols, sparse_rows))
+}
+
+/// Color level of each facet group whose label is a color category
+///
+/// `groups` are (original index, label). Labels are looked up in `levels`; labels
+/// not found there get the levels after the known ones, in group order.
+fn facet_color_levels<'a>(
+    groups: impl Iterator<Item = (usize, &'a str)>,
+    levels: &[String],
+) -> HashMap<usize, i32> {
+    let mut extra = levels.len();
+    groups
+        .map(|(original_index, label)| {
+            let level = levels.iter().position(|l| l == label).unwrap_or_else(|| {
+                extra += 1;
+                extra - 1
+            });
+            (original_index, level as i32)
+        })
+        .collect()
 }
 
 /// Extract row count from schema
@@ -186,12 +996,13 @@ fn extract_row_count_from_schema(
 ) -> Result<i64, Box<dyn std::error::Error>> {
     use tercen_rs::client::proto::e_schema;
 
-    // All schema types (TableSchema, ComputedTableSchema, CubeQueryTableSchema) have nRows field
+    // All schema types (Schema, TableSchema, ComputedTableSchema, CubeQueryTableSchema)
+    // have nRows field
     match &schema.object {
         Some(e_schema::Object::Tableschema(ts)) => Ok(ts.n_rows as i64),
         Some(e_schema::Object::Computedtableschema(cts)) => Ok(cts.n_rows as i64),
         Some(e_schema::Object::Cubequerytableschema(cqts)) => Ok(cqts.n_rows as i64),
-        Some(e_schema::Object::Schema(_)) => Err("Schema variant not supported".into()),
+        Some(e_schema::Object::Schema(s)) => Ok(s.n_rows as i64),
         None => Err("Schema object is None".into()),
     }
 }
@@ -222,6 +1033,10 @@ pub struct TercenStreamGenerator {
     /// Total row count across ALL facets
     total_rows: usize,
 
+    /// Column names of the main table (empty = unknown, request everything)
+    /// Bulk requests are limited to these (see bulk_columns)
+    main_columns: Vec<String>,
+
     /// GGRS aesthetic mappings - uses quantized coordinates
     aes: Aes,
 
@@ -231,6 +1046,9 @@ pub struct TercenStreamGenerator {
     /// Chunk size for streaming
     chunk_size: usize,
 
+    /// Target size of one streamed TSON message in bytes
+    message_budget_bytes: usize,
+
     /// Color information (factors and palettes) - legacy field
     color_infos: Vec<tercen_rs::ColorInfo>,
 
@@ -240,6 +1058,9 @@ pub struct TercenStreamGenerator {
     /// Cached legend scale (loaded during initialization)
     cached_legend_scale: LegendScale,
 
+    /// Legend title overrides, applied when the legend is queried
+    legend_titles: LegendTitles,
+
     /// Page factor names (kept for metadata/debugging)
     /// Not used for filtering - GGRS handles everything via original_index
     #[allow(dead_code)]
@@ -258,19 +1079,46 @@ pub struct TercenStreamGenerator {
     /// This is necessary because GGRS streams in chunks, but aggregation requires all data.
     heatmap_cached_data: RwLock<Option<DataFrame>>,
 
+    /// Histogram bars (.ci, .ri, .xs, .ys, .y0s) served instead of the main table
+    /// when the bar histogram is on
+    histogram_data: Option<DataFrame>,
+
+    /// Chunks fetched ahead of rendering, keyed by range start (see `preload`)
+    preloaded_chunks: Mutex<HashMap<usize, DataFrame>>,
+
     /// How to aggregate multiple data points in the same heatmap cell
     heatmap_cell_aggregation: HeatmapCellAggregation,
 
-    /// Y-axis transform type (e.g., "log", "ln", "log10")
+    /// Bar layout for multiple layers (stack or dodge)
+    bar_mode: BarMode,
+
+    /// Category labels in color level order shared by all pages (None = this page's)
+    color_levels: Option<Vec<String>>,
+
+    /// Continuous legend range source (palette stops or aggregated heatmap values)
+    legend_range: LegendRange,
+
+    /// Min/max of each continuous color factor after heatmap aggregation
+    /// Filled by aggregate_heatmap_data(); used when legend_range is Data
+    heatmap_value_ranges: RwLock<HashMap<String, (f64, f64)>>,
+
+    /// Y-axis transform type (e.g., "log", "ln", "log10", "log2")
     /// When set, indicates Y data is pre-transformed and GGRS should invert it
-    /// Note: Transform is applied to axis_ranges, this field kept for debugging
-    #[allow(dead_code)]
+    /// Note: Transform is applied to axis_ranges
     y_transform: Option<Transform>,
 
+    /// Forward/inverse functions of the Y transform, computed by the operator to
+    /// re-express layers with their own transform (see retransform_layer_y) and to
+    /// export data values (None = a transform without local functions, e.g. logicle)
+    y_value_transform: Option<ValueTransform>,
+
     /// X-axis transform type
     #[allow(dead_code)]
     x_transform: Option<Transform>,
 
+    /// Forward/inverse functions of the X transform, for the data export
+    x_value_transform: Option<ValueTransform>,
+
     /// Number of layers (axis_queries) - used for layer-based coloring
     /// When > 1 and color_infos is empty, we color points by their .axisIndex
     n_layers: usize,
@@ -278,18 +1126,80 @@ pub struct TercenStreamGenerator {
     /// Palette name for layer-based coloring (from crosstab)
     layer_palette_name: Option<String>,
 
+    /// Custom layer palette (RGB) overriding the crosstab palette when non-empty
+    categorical_palette: Vec<[u8; 3]>,
+
+    /// Color for NaN/Inf continuous color values (None = drop those rows)
+    nan_color: Option<[u8; 3]>,
+
+    /// Accessible palette forced over categorical color levels (see palette.rs)
+    palette_override: PaletteOverride,
+
     /// Y-axis factor names per layer (from axis_queries[i].yAxis.name)
     /// Used for legend entries when layers don't have explicit color factors
     /// Note: Used at initialization in load_legend_scale(), not read later
     #[allow(dead_code)]
     layer_y_factor_names: Vec<String>,
 
+    /// Categorical X factor name and its sorted levels (empty = numeric X axis)
+    /// Points are placed at the level index (see position_x_categories)
+    x_categorical_factor: Option<String>,
+    x_categories: Vec<String>,
+
+    /// Horizontal jitter of categorical-X points (fraction of the category band)
+    /// Only set for point charts
+    jitter_width: Option<f64>,
+
     /// Chart kind - determines data columns needed (e.g., bar charts need .y0s baseline)
     chart_kind: ChartKind,
-}
 
-impl TercenStreamGenerator {
-    /// Create a new stream generator with configuration struct
+    /// Union of all cell ranges (X, Y), computed at init
+    /// Used as the last-resort fallback when a cell has no axis range
+    global_axis_range: (AxisData, AxisData),
+
+    /// Panic on missing axis ranges instead of falling back
+    strict_axis_ranges: bool,
+
+    /// Chunk cache shared with the other pages of the plot
+    data_cache: Option<Arc<Mutex<DataCache>>>,
+
+    /// Buffer receiving the served data as CSV rows (None = no export)
+    data_export: Option<DataExport>,
+
+    /// Time limit for heatmap aggregation (None = no limit)
+    aggregation_timeout: Option<Duration>,
+
+    /// Row chunks streamed concurrently during heatmap aggregation
+    max_concurrent_chunks: usize,
+
+    /// Rows streamed from the main table so far (progress reporting)
+    rows_streamed: AtomicUsize,
+
+    /// Maximum displayed facet label length (None = full labels)
+    /// facet_info keeps the full labels (see full_facet_labels)
+    facet_label_max_len: Option<usize>,
+
+    /// How longer facet labels are shortened
+    facet_label_overflow: FacetLabelOverflow,
+
+    /// Single facet cell (original .ci, .ri) whose rows are kept (split rendering)
+    cell_filter: Option<(usize, usize)>,
+
+    /// Transform of each layer (by `.axisIndex`) whose values differ from the axis
+    /// transform (Identity = untransformed values). Empty = all layers use the axis's
+    layer_y_transforms: HashMap<i64, ValueTransform>,
+
+    /// Continuous color factor that is also the Y factor, derived from `.ys`
+    /// instead of being streamed (see add_y_color_column)
+    y_color_factor: Option<String>,
+
+    /// Downsampling: sampled main-table rows of the cells above max_points_per_facet
+    /// (reservoirs of the pre-pass, see `Prescan::add_point`)
+    sampled_rows: HashMap<(usize, usize), HashSet<usize>>,
+}
+
+impl TercenStreamGenerator {
+    /// Create a new stream generator with configuration struct
     ///
     /// This loads facet metadata and axis ranges from pre-computed tables.
     /// Note: page_filter is used to load only the facets for this page (e.g., female or male),
@@ -313,381 +1223,1837 @@ impl TercenStreamGenerator {
             y_axis_table_id,
             x_axis_table_id,
             chunk_size,
+            message_budget_bytes,
             color_infos,
             per_layer_colors,
             page_factors,
             schema_cache,
+            range_cache,
+            color_levels,
             heatmap_cell_aggregation,
+            bar_mode,
+            histogram_bins,
+            legend_range,
+            legend_sort,
+            legend_drop_unused,
+            legend_titles,
             y_transform,
             x_transform,
+            y_axis_limits,
+            x_axis_limits,
+            flip_y,
+            layer_y_transforms,
+            sqrt_negative_policy,
             n_layers,
             layer_palette_name,
+            categorical_palette,
+            nan_color,
+            palette_override,
+            palette_override_continuous,
+            color_range,
+            color_clip_percentiles,
+            color_bins,
             layer_y_factor_names,
+            x_categorical_factor,
+            jitter_width,
             chart_kind,
+            strict_axis_ranges,
+            data_cache,
+            data_export,
+            aggregation_timeout,
+            max_concurrent_chunks,
+            facet_scales,
+            facet_order,
+            max_facets,
+            min_points_per_facet,
+            max_points_per_facet,
+            facet_label_max_len,
+            facet_label_overflow,
         } = config;
 
-        // Convert transform strings to Transform structs
-        let y_transform = y_transform.and_then(|t| Transform::parse(&t));
-        let x_transform = x_transform.and_then(|t| Transform::parse(&t));
+        // Histogram: bars are the bin counts of all Y values in a cell, so colors,
+        // layers and axis transforms don't apply
+        let histogram_bins = histogram_bins.filter(|_| matches!(chart_kind, ChartKind::Bar));
+        let (color_infos, per_layer_colors, n_layers, x_transform, y_transform) = if histogram_bins
+            .is_some()
+        {
+            if !color_infos.is_empty()
+                || per_layer_colors.is_some()
+                || n_layers > 1
+                || x_transform.is_some()
+                || y_transform.is_some()
+            {
+                crate::warn_log!("bar.histogram ignores color factors, layers and axis transforms");
+            }
+            (Vec::new(), None, 1, None, None)
+        } else {
+            (
+                color_infos,
+                per_layer_colors,
+                n_layers,
+                x_transform,
+                y_transform,
+            )
+        };
+
+        // Continuous color factor that is also the Y factor of every layer: its values
+        // are the plotted Y values, so derive them from .ys instead of streaming the
+        // column again (not for transformed Y, where .ys is not the raw factor value)
+        let y_color_factor = color_infos
+            .iter()
+            .find(|ci| {
+                matches!(ci.mapping, tercen_rs::ColorMapping::Continuous(_))
+                    && !layer_y_factor_names.is_empty()
+                    && layer_y_factor_names.iter().all(|y| *y == ci.factor_name)
+            })
+            .map(|ci| ci.factor_name.clone())
+            .filter(|_| {
+                per_layer_colors.is_none()
+                    && y_transform.is_none()
+                    && !matches!(chart_kind, ChartKind::Heatmap)
+            });
+        if let Some(ref name) = y_color_factor {
+            crate::debug_log!("Color factor '{}' is the Y factor - reusing .ys", name);
+        }
+
+        // Accessible palette override: recolor the crosstab palettes before the
+        // legend is built, and use the override for the layer colors too
+        let override_colors = palette::categorical_colors(palette_override);
+        let (color_infos, per_layer_colors, categorical_palette) = match override_colors {
+            Some(colors) => {
+                crate::info_log!(
+                    "  Palette override: {:?} (continuous: {})",
+                    palette_override,
+                    palette_override_continuous
+                );
+                let (color_infos, per_layer_colors) = Self::apply_palette_override(
+                    color_infos,
+                    per_layer_colors,
+                    colors,
+                    palette_override_continuous,
+                );
+                (color_infos, per_layer_colors, colors.to_vec())
+            }
+            None => (color_infos, per_layer_colors, categorical_palette),
+        };
+
+        // Fixed color range: stretch continuous palettes onto it before the legend is
+        // built, so legend and point colors use the same bounds on every page
+        let (color_infos, per_layer_colors) = match color_range {
+            Some(range) => {
+                crate::info_log!("  Continuous color range: [{}, {}]", range.0, range.1);
+                Self::apply_color_range(color_infos, per_layer_colors, range)
+            }
+            None => (color_infos, per_layer_colors),
+        };
+
+        // Custom palette overrides the constant layer colors (data and legend)
+        let per_layer_colors = if categorical_palette.is_empty() {
+            per_layer_colors
+        } else {
+            crate::info_log!(
+                "  Custom layer palette: {} colors",
+                categorical_palette.len()
+            );
+            per_layer_colors.map(|plc| Self::apply_layer_palette(plc, &categorical_palette))
+        };
+
+        // Negative value policies for sqrt axes (None for other transforms)
+        let y_sqrt_policy = y_transform
+            .as_deref()
+            .map(|t| sqrt_policy(t, sqrt_negative_policy))
+            .transpose()?
+            .flatten();
+        let x_sqrt_policy = x_transform
+            .as_deref()
+            .map(|t| sqrt_policy(t, sqrt_negative_policy))
+            .transpose()?
+            .flatten();
+
+        // Local functions of the axis transforms, for values the operator re-expresses
+        let y_value_transform = Self::value_transform(y_transform.as_deref())?;
+        let x_value_transform = Self::value_transform(x_transform.as_deref())?;
+
+        // Convert transform strings (including parameters like asinh:cofactor=150)
+        // to Transform structs
+        let y_transform = y_transform
+            .map(|t| parse_transform(&t))
+            .transpose()?
+            .flatten();
+        let x_transform = x_transform
+            .map(|t| parse_transform(&t))
+            .transpose()?
+            .flatten();
 
         if y_transform.is_some() {
-            println!("  Y-axis transform: {:?}", y_transform);
+            crate::info_log!("  Y-axis transform: {:?}", y_transform);
         }
         if x_transform.is_some() {
-            println!("  X-axis transform: {:?}", x_transform);
+            crate::info_log!("  X-axis transform: {:?}", x_transform);
         }
 
         // Load facets with optional filtering for pagination
         // Each page should only show its own facet panels
-        let facet_info = if let Some(filter) = page_filter {
-            eprintln!("DEBUG: Loading facets with page filter: {:?}", filter);
+        let mut facet_info = if let Some(filter) = page_filter {
+            crate::debug_log!("Loading facets with page filter: {:?}", filter);
             FacetInfo::load_with_filter(&client, &col_facet_table_id, &row_facet_table_id, filter)
                 .await?
         } else {
-            eprintln!("DEBUG: Loading all facets (no pagination)");
+            crate::debug_log!("Loading all facets (no pagination)");
             FacetInfo::load(&client, &col_facet_table_id, &row_facet_table_id).await?
         };
 
-        println!(
+        crate::info_log!(
             "Loaded facets: {} columns × {} rows = {} cells",
             facet_info.n_col_facets(),
             facet_info.n_row_facets(),
             facet_info.total_facets()
         );
+        crate::progress_log!("facets loaded ({} cells)", facet_info.total_facets());
 
         // NO FILTERING! Operator is dumb - GGRS handles everything via original_index.
         // We just keep the facet_info which has both index and original_index for each facet.
 
-        // Load axis ranges from pre-computed Y-axis table (required)
-        let y_table_id = y_axis_table_id.ok_or(
-            "Y-axis table is required but was not found. \
-             This usually means schema_ids is empty in the task. \
-             Ensure the crosstab has a Y-axis factor defined.",
-        )?;
-
-        println!("Loading axis ranges from Y-axis table: {}", y_table_id);
-        let (mut axis_ranges, total_rows) = Self::load_axis_ranges_from_table(
+        // Categorical X (string factor): heatmaps take their X categories from the
+        // column facets instead
+        let x_categorical_factor =
+            x_categorical_factor.filter(|_| !matches!(chart_kind, ChartKind::Heatmap));
+        let LoadedRanges {
+            mut axis_ranges,
+            total_rows,
+            main_columns,
+            x_categories,
+        } = Self::load_ranges(
             &client,
-            &y_table_id,
             &main_table_id,
+            y_axis_table_id,
+            x_axis_table_id.as_deref(),
             &facet_info,
-            &schema_cache,
+            (&schema_cache, &range_cache),
+            x_categorical_factor.as_deref(),
         )
         .await?;
 
-        eprintln!(
-            "DEBUG: axis_ranges has {} entries (before X range computation), total_rows: {}",
-            axis_ranges.len(),
-            total_rows
+        // Per-layer Y transforms: layers whose values were transformed differently
+        // from the axis are requantized into the axis transform's space per row
+        let layered =
+            n_layers > 1 && histogram_bins.is_none() && !matches!(chart_kind, ChartKind::Heatmap);
+        let layer_transforms = Self::layer_value_transforms(
+            &layer_y_transforms,
+            layered,
+            &main_columns,
+            y_value_transform,
+        )?;
+
+        // Shared pre-pass over the main table: a single scan of the facet indices, plus
+        // the value columns the enabled options need, gives the point counts, downsampling
+        // reservoirs, observed color levels and color factor values
+        let not_heatmap = !matches!(chart_kind, ChartKind::Heatmap);
+        let (prescan_request, clip_factors, bin_factors) = Self::prescan_request(
+            &color_infos,
+            per_layer_colors.as_ref(),
+            &main_columns,
+            max_points_per_facet.filter(|_| not_heatmap),
+            legend_drop_unused,
+            color_clip_percentiles.is_some() && color_range.is_none(),
+            color_bins.is_some(),
         );
+        let needs_counts =
+            not_heatmap && (min_points_per_facet.is_some() || max_points_per_facet.is_some());
+        let mut prescan = if total_rows > 0
+            && (needs_counts
+                || prescan_request.color_levels
+                || !prescan_request.color_factors.is_empty())
+        {
+            Self::prescan(
+                &client,
+                &schema_cache,
+                &main_table_id,
+                &main_columns,
+                total_rows,
+                &prescan_request,
+            )
+            .await?
+        } else {
+            Prescan::default()
+        };
 
-        // Check if X ranges need to be loaded (Y-axis table may not have .minX/.maxX columns)
-        let needs_x_range = axis_ranges.values().any(|(x_axis, _)| {
-            if let AxisData::Numeric(ref num) = x_axis {
-                num.min_value.is_nan() || num.max_value.is_nan()
-            } else {
-                false
+        // Histogram: count Y values per bin and replace the axes (X = Y value range,
+        // Y = counts) before limits and flipping apply
+        let mut histogram_data = None;
+        if let Some(bins) = histogram_bins {
+            if x_axis_table_id.is_some() || !x_categories.is_empty() {
+                crate::warn_log!("bar.histogram bins the Y factor - the X factor is ignored");
             }
-        });
-
-        if needs_x_range {
-            // First, try to load X ranges from X-axis table (if available)
-            if let Some(ref x_table_id) = x_axis_table_id {
-                println!("Loading X-axis ranges from X-axis table: {}", x_table_id);
-                Self::load_x_ranges_from_table(
+            let counts = if total_rows > 0 {
+                Self::histogram_counts(
                     &client,
-                    x_table_id,
-                    &facet_info,
-                    &mut axis_ranges,
                     &schema_cache,
+                    &main_table_id,
+                    &main_columns,
+                    total_rows,
+                    &axis_ranges,
+                    bins,
                 )
-                .await?;
+                .await?
             } else {
-                // No X-axis table means X is sequential (1..n_rows)
-                // No need to scan data - just use the row count
-                println!(
-                    "No X-axis table - using sequential X range: 1 to {}",
-                    total_rows
-                );
-                Self::set_sequential_x_ranges(total_rows as f64, &mut axis_ranges);
-            }
+                HashMap::new()
+            };
+            let bars = Self::histogram_bars(&counts, bins, &mut axis_ranges)?;
+            crate::info_log!(
+                "  Histogram: {} bins, {} bars in {} cells",
+                bins,
+                bars.nrow(),
+                counts.len()
+            );
+            histogram_data = Some(bars);
         }
 
-        // NOTE: axis_ranges now keyed by original_index (not filtered index)
-        // load_axis_ranges_from_table() already maps table's .ri (0-11) → original_index (12-23)
-        // This ensures data[.ri=12] can look up y_ranges[12] correctly
-        eprintln!("DEBUG: axis_ranges keyed by original_index for data matching");
+        // Transforms, sqrt policies, pinned limits and Y reversal of the axes
+        Self::finish_axis_ranges(
+            &mut axis_ranges,
+            (&x_transform, &y_transform),
+            (x_sqrt_policy, y_sqrt_policy),
+            (x_axis_limits, y_axis_limits),
+            flip_y,
+        );
 
-        // Apply transform info to axis ranges
-        // This tells GGRS that the data is pre-transformed and needs inversion
-        if y_transform.is_some() || x_transform.is_some() {
-            for (x_axis, y_axis) in axis_ranges.values_mut() {
-                if let AxisData::Numeric(ref mut num) = y_axis {
-                    num.transform = y_transform.clone();
-                }
-                if let AxisData::Numeric(ref mut num) = x_axis {
-                    num.transform = x_transform.clone();
+        // Dense facets: keep a reproducible uniform sample of at most N points per cell
+        // (heatmaps already aggregate per cell)
+        let sampled_rows = match prescan_request.sample_size {
+            Some(max) if total_rows > 0 => {
+                let sampled = prescan.sampled_rows(max);
+                if !sampled.is_empty() {
+                    let kept: usize = prescan.counts.values().map(|&n| n.min(max)).sum();
+                    crate::info_log!(
+                        "  Downsampling {} facet cell(s) to {} points: keeping {} of {} rows ({:.1}%)",
+                        sampled.len(),
+                        max,
+                        kept,
+                        total_rows,
+                        100.0 * kept as f64 / total_rows as f64
+                    );
                 }
+                sampled
             }
-            eprintln!(
-                "DEBUG: Applied transforms to axis_ranges - Y: {:?}, X: {:?}",
-                y_transform, x_transform
-            );
-        }
+            _ => HashMap::new(),
+        };
 
-        eprintln!(
-            "DEBUG: TercenStreamGenerator initialized with total_rows = {}",
+        // Sparse facets, facet order and preview subset
+        Self::arrange_facets(
+            &mut facet_info,
+            &mut axis_ranges,
+            &prescan.counts,
+            min_points_per_facet.filter(|_| total_rows > 0),
+            facet_order.as_deref(),
+            max_facets,
+            not_heatmap,
+        );
+
+        crate::debug_log!(
+            "TercenStreamGenerator initialized with total_rows = {}",
             total_rows
         );
 
+        let mut color_values: HashMap<String, Vec<f64>> = prescan
+            .color_values
+            .drain()
+            .map(|(factor, sample)| (factor, sample.into_values()))
+            .collect();
+
+        // Percentile clipping: stretch continuous palettes onto the data percentiles so
+        // outliers don't flatten the scale (a fixed color range takes precedence)
+        let (color_infos, per_layer_colors) = match color_clip_percentiles {
+            Some(_) if color_range.is_some() => {
+                crate::warn_log!("color.range is set - ignoring color.clip.percentiles");
+                (color_infos, per_layer_colors)
+            }
+            Some(percentiles) if total_rows > 0 => {
+                let ranges =
+                    Self::color_percentile_ranges(&mut color_values, &clip_factors, percentiles);
+                for (factor, (min, max)) in &ranges {
+                    crate::info_log!(
+                        "  Color '{}' clipped to percentiles {}-{}: [{}, {}]",
+                        factor,
+                        percentiles.0,
+                        percentiles.1,
+                        min,
+                        max
+                    );
+                }
+                Self::apply_color_ranges(color_infos, per_layer_colors, &ranges)
+            }
+            _ => (color_infos, per_layer_colors),
+        };
+
+        // Binned colors: continuous palettes become constant-color quantile bands,
+        // shown as a discrete legend of band ranges
+        let mut color_infos = color_infos;
+        let color_bands = match color_bins {
+            Some(bins) if total_rows > 0 => {
+                Self::bin_colors(&mut color_infos, &bin_factors, &mut color_values, bins)
+            }
+            _ => HashMap::new(),
+        };
+
         // Load legend scale data
         // Load legend scale from color info (n_levels from schema)
         // For mixed scenarios, combine sections from layers with colors and layers without
-        println!("Loading legend scale data...");
+        crate::info_log!("Loading legend scale data...");
         let cached_legend_scale = Self::load_legend_scale(
             &color_infos,
             per_layer_colors.as_ref(),
             &layer_y_factor_names,
         )?;
-        eprintln!("DEBUG: Cached legend scale: {:?}", cached_legend_scale);
-
-        // Create default aesthetics
-        // Dequantization happens in GGRS render.rs using axis ranges
-        // After dequantization, columns are .x and .y (actual data values)
-        // Add color aesthetic if colors are defined (either legacy or per-layer)
-        let mut aes = Aes::new().x(".x").y(".y");
-
-        // Determine if we have any colors to display
-        // With the new LayerColorConfig, every layer has a config (explicit or constant)
-        let has_colors = if let Some(ref plc) = per_layer_colors {
-            plc.has_explicit_colors() || plc.has_constant_colors()
+        let cached_legend_scale = match override_colors {
+            Some(colors) => Self::recolor_legend_levels(cached_legend_scale, colors),
+            None => cached_legend_scale,
+        };
+        let cached_legend_scale = if prescan_request.color_levels && total_rows > 0 {
+            match prescan.color_levels.take() {
+                Some(observed) => Self::drop_unused_legend_levels(cached_legend_scale, &observed),
+                None => cached_legend_scale,
+            }
         } else {
-            !color_infos.is_empty()
+            cached_legend_scale
         };
-
-        eprintln!("DEBUG: color_infos.len() = {}", color_infos.len());
-        eprintln!(
-            "DEBUG: per_layer_colors = {:?}",
-            per_layer_colors.as_ref().map(|p| format!(
-                "n_layers={}, has_explicit={}, is_mixed={}",
-                p.n_layers,
-                p.has_explicit_colors(),
-                p.is_mixed()
-            ))
+        let cached_legend_scale = Self::order_legend_entries(
+            cached_legend_scale,
+            legend_sort,
+            &Self::legend_data_order(&color_infos),
         );
+        // Band ranges keep their value order, so this comes after sorting
+        let cached_legend_scale = Self::band_legend(cached_legend_scale, &color_bands);
+        crate::debug_log!("Cached legend scale: {:?}", cached_legend_scale);
 
-        if has_colors {
-            eprintln!("DEBUG: Adding .color aesthetic to Aes");
-            if !color_infos.is_empty() {
-                eprintln!("DEBUG: Color factor: '{}'", color_infos[0].factor_name);
-                match &color_infos[0].mapping {
-                    tercen_rs::ColorMapping::Continuous(palette) => {
-                        eprintln!(
-                            "DEBUG: Continuous palette with {} color stops",
-                            palette.stops.len()
-                        );
-                        for (i, stop) in palette.stops.iter().enumerate() {
-                            eprintln!(
-                                "  Stop {}: value={:.2}, color=RGB({}, {}, {})",
-                                i, stop.value, stop.color[0], stop.color[1], stop.color[2]
-                            );
-                        }
-                    }
-                    tercen_rs::ColorMapping::Categorical(color_map) => {
-                        eprintln!(
-                            "DEBUG: Categorical palette with {} categories",
-                            color_map.mappings.len()
-                        );
-                    }
-                }
-            }
-            aes = aes.color(".color");
-        } else {
-            eprintln!("DEBUG: No colors configured, NOT adding .color aesthetic");
-        }
+        let aes = Self::data_aes(&color_infos, per_layer_colors.as_ref());
 
-        // Create facet spec based on facet metadata
-        // Use actual column names from facet tables for labels
-        // Data filtering still uses .ri/.ci indices (handled in query_data_chunk)
-        let facet_spec = if !facet_info.row_facets.is_empty() && !facet_info.col_facets.is_empty() {
-            // Grid faceting: rows × columns
-            use ggrs_core::stream::FacetScales;
-            let row_vars = facet_info
-                .row_facets
-                .column_names
-                .iter()
-                .filter(|n| !n.is_empty())
-                .cloned()
-                .collect::<Vec<_>>();
-            let col_vars = facet_info
-                .col_facets
-                .column_names
-                .iter()
-                .filter(|n| !n.is_empty())
-                .cloned()
-                .collect::<Vec<_>>();
-            let row_var = row_vars.first().unwrap_or(&".ri".to_string()).clone();
-            let col_var = col_vars.first().unwrap_or(&".ci".to_string()).clone();
-            FacetSpec::grid(row_var, col_var).scales(FacetScales::FreeY)
-        } else if !facet_info.row_facets.is_empty() {
-            // Row faceting only (each row has its own Y range)
-            use ggrs_core::stream::FacetScales;
-            let row_vars = facet_info
-                .row_facets
-                .column_names
-                .iter()
-                .filter(|n| !n.is_empty())
-                .cloned()
-                .collect::<Vec<_>>();
-            let row_var = row_vars.first().unwrap_or(&".ri".to_string()).clone();
-            FacetSpec::row(row_var).scales(FacetScales::FreeY)
-        } else if !facet_info.col_facets.is_empty() {
-            // Column faceting only
-            let col_vars = facet_info
-                .col_facets
-                .column_names
-                .iter()
-                .filter(|n| !n.is_empty())
-                .cloned()
-                .collect::<Vec<_>>();
-            let col_var = col_vars.first().unwrap_or(&".ci".to_string()).clone();
-            FacetSpec::col(col_var)
-        } else {
-            // No faceting
-            FacetSpec::none()
-        };
+        let facet_spec = Self::build_facet_spec(&facet_info, facet_scales);
 
-        Ok(Self {
+        let global_axis_range = Self::global_axis_range(&axis_ranges);
+
+        let generator = Self {
             client,
             main_table_id,
             facet_info,
             axis_ranges,
             total_rows,
+            main_columns,
             aes,
             facet_spec,
             chunk_size,
+            message_budget_bytes,
             color_infos,
             per_layer_colors,
             cached_legend_scale,
+            legend_titles,
             page_factors,
             heatmap_mode: None,
             schema_cache,
             heatmap_cached_data: RwLock::new(None),
+            histogram_data,
+            preloaded_chunks: Mutex::new(HashMap::new()),
             heatmap_cell_aggregation,
+            bar_mode,
+            color_levels,
+            legend_range,
+            heatmap_value_ranges: RwLock::new(HashMap::new()),
             y_transform,
+            y_value_transform,
             x_transform,
+            x_value_transform,
             n_layers,
             layer_palette_name,
+            categorical_palette,
+            nan_color,
+            palette_override,
             layer_y_factor_names,
+            x_categorical_factor,
+            x_categories,
+            jitter_width: jitter_width.filter(|_| matches!(chart_kind, ChartKind::Point)),
             chart_kind,
-        })
-    }
+            global_axis_range,
+            strict_axis_ranges,
+            data_cache,
+            data_export,
+            aggregation_timeout,
+            max_concurrent_chunks,
+            rows_streamed: AtomicUsize::new(0),
+            facet_label_max_len,
+            facet_label_overflow,
+            cell_filter: None,
+            layer_y_transforms: layer_transforms,
+            y_color_factor,
+            sampled_rows,
+        };
 
-    /// Create a TableStreamer, using the schema cache if available
-    fn create_streamer<'a>(
-        client: &'a TercenClient,
-        cache: &Option<SchemaCache>,
-    ) -> TableStreamer<'a> {
-        match cache {
-            Some(c) => TableStreamer::with_cache(client, c.clone()),
-            None => TableStreamer::new(client),
+        // Auxiliary columns the chart expects but the main table lacks are skipped
+        let missing = generator.missing_bulk_columns();
+        if !missing.is_empty() && !matches!(generator.chart_kind, ChartKind::Heatmap) {
+            crate::warn_log!(
+                "Main table has no column(s) {:?} - not requesting them",
+                missing
+            );
         }
+
+        Ok(generator)
     }
 
-    /// Create a stream generator with pre-computed axis ranges
+    /// Axis ranges of every facet cell, with the main table's row count and columns
     ///
-    /// This is used when axis ranges are provided externally.
-    #[allow(clippy::too_many_arguments)]
-    pub fn new_with_ranges(
-        client: Arc<TercenClient>,
-        main_table_id: String,
-        facet_info: FacetInfo,
-        axis_ranges: HashMap<(usize, usize), (AxisData, AxisData)>,
-        total_rows: usize,
-        chunk_size: usize,
-        color_infos: Vec<tercen_rs::ColorInfo>,
-        page_factors: Vec<String>,
-    ) -> Self {
-        // Aesthetics use dequantized coordinates: .x and .y (actual data values)
-        // Dequantization happens in stream_facet_data() before data reaches renderers
-        // Add color aesthetic if colors are defined
-        let mut aes = Aes::new().x(".x").y(".y");
-        if !color_infos.is_empty() {
-            aes = aes.color(".color");
-        }
-
-        // Create facet spec based on facet metadata
-        // Use .ri/.ci as faceting variables since our data uses indices
-        // GGRS will use these to determine panel layout
-        let facet_spec = if !facet_info.row_facets.is_empty() && !facet_info.col_facets.is_empty() {
-            // Grid faceting: rows × columns
-            use ggrs_core::stream::FacetScales;
-            FacetSpec::grid(".ri", ".ci").scales(FacetScales::FreeY)
-        } else if !facet_info.row_facets.is_empty() {
-            // Row faceting only (each row has its own Y range)
-            use ggrs_core::stream::FacetScales;
-            FacetSpec::row(".ri").scales(FacetScales::FreeY)
-        } else if !facet_info.col_facets.is_empty() {
-            // Column faceting only
-            FacetSpec::col(".ci")
-        } else {
-            // No faceting
-            FacetSpec::none()
-        };
-
-        // NO FILTERING! Operator is dumb - GGRS handles everything via original_index.
+    /// Ranges come from the Y-axis table (required); X ranges it lacks come from the
+    /// X-axis table, or are 1..n rows when there is none. A categorical X factor
+    /// replaces the X ranges with its level labels. An empty main table gets
+    /// placeholder ranges so its panels still render.
+    async fn load_ranges(
+        client: &TercenClient,
+        main_table_id: &str,
+        y_axis_table_id: Option<String>,
+        x_axis_table_id: Option<&str>,
+        facet_info: &FacetInfo,
+        (schema_cache, range_cache): (&Option<SchemaCache>, &Option<RangeCache>),
+        x_categorical_factor: Option<&str>,
+    ) -> Result<LoadedRanges, Box<dyn std::error::Error>> {
+        // Load axis ranges from pre-computed Y-axis table (required)
+        // (generate_plots reports the task's schema_ids before getting here)
+        let y_table_id = y_axis_table_id.ok_or(
+            "Y-axis table is required but was not found. \
+             This usually means schema_ids is empty in the task, or has no table of type 'y'. \
+             Ensure the crosstab has a Y-axis factor defined.",
+        )?;
 
-        Self {
+        crate::info_log!("Loading axis ranges from Y-axis table: {}", y_table_id);
+        let (mut axis_ranges, total_rows, main_columns) = Self::load_axis_ranges_from_table(
             client,
+            &y_table_id,
             main_table_id,
             facet_info,
-            axis_ranges,
-            total_rows,
-            aes,
-            facet_spec,
-            chunk_size,
-            color_infos,
-            per_layer_colors: None, // Sync constructor doesn't support per-layer colors
-            cached_legend_scale: LegendScale::None, // TODO: Load async if needed
-            page_factors,
-            heatmap_mode: None,
-            schema_cache: None, // sync method - no caching
-            heatmap_cached_data: RwLock::new(None),
-            heatmap_cell_aggregation: HeatmapCellAggregation::Last, // Default for sync constructor
-            y_transform: None, // Sync constructor doesn't support transforms
-            x_transform: None,
-            n_layers: 1, // Sync constructor defaults to single layer
-            layer_palette_name: None,
-            layer_y_factor_names: Vec::new(), // Sync constructor defaults to empty
-            chart_kind: ChartKind::Point,     // Sync constructor defaults to Point
-        }
-    }
+            schema_cache,
+        )
+        .await?;
 
-    /// Enable heatmap mode with the given grid dimensions
-    ///
-    /// In heatmap mode:
-    /// - Facet counts are overridden to 1x1 (single panel)
-    /// - Axis ranges use grid dimensions: X = (-0.5, n_cols-0.5), Y = (-0.5, n_rows-0.5)
-    /// - Data coordinates use .ci for X and .ri for Y (no quantization)
-    ///
-    /// # Arguments
-    /// * `n_cols` - Number of columns in the heatmap grid (max .ci + 1)
-    /// * `n_rows` - Number of rows in the heatmap grid (max .ri + 1)
-    pub fn set_heatmap_mode(&mut self, n_cols: usize, n_rows: usize) {
-        eprintln!(
-            "DEBUG: Enabling heatmap mode with grid {}×{}",
-            n_cols, n_rows
+        crate::debug_log!(
+            "axis_ranges has {} entries (before X range computation), total_rows: {}",
+            axis_ranges.len(),
+            total_rows
         );
-        self.heatmap_mode = Some((n_cols, n_rows));
-    }
 
-    /// Get the heatmap grid dimensions if in heatmap mode
-    pub fn heatmap_dims(&self) -> Option<(usize, usize)> {
-        self.heatmap_mode
-    }
+        // Check if X ranges need to be loaded (Y-axis table may not have .minX/.maxX columns)
+        let needs_x_range = axis_ranges.values().any(|(x_axis, _)| {
+            if let AxisData::Numeric(ref num) = x_axis {
+                num.min_value.is_nan() || num.max_value.is_nan()
+            } else {
+                false
+            }
+        });
 
-    /// Get the original facet grid dimensions (before heatmap mode override)
-    /// Returns (n_col_facets, n_row_facets) from the underlying facet_info
+        if needs_x_range {
+            // First, try to load X ranges from X-axis table (if available)
+            if let Some(x_table_id) = x_axis_table_id {
+                crate::info_log!("Loading X-axis ranges from X-axis table: {}", x_table_id);
+                Self::load_x_ranges_from_table(
+                    client,
+                    x_table_id,
+                    facet_info,
+                    &mut axis_ranges,
+                    schema_cache,
+                    range_cache,
+                )
+                .await?;
+            } else {
+                // No X-axis table means X is sequential (1..n_rows)
+                // No need to scan data - just use the row count
+                crate::info_log!(
+                    "No X-axis table - using sequential X range: 1 to {}",
+                    total_rows
+                );
+                Self::set_sequential_x_ranges(total_rows as f64, &mut axis_ranges);
+            }
+        }
+
+        // Categorical X (string factor): replace the numeric X range with the level labels
+        let x_categories = match x_categorical_factor {
+            Some(factor) if total_rows > 0 => {
+                let categories = Self::load_x_categories(
+                    client,
+                    schema_cache,
+                    main_table_id,
+                    factor,
+                    total_rows,
+                )
+                .await?;
+                crate::info_log!(
+                    "Categorical X axis '{}' with {} levels",
+                    factor,
+                    categories.len()
+                );
+                Self::set_categorical_x_ranges(&categories, &mut axis_ranges);
+                categories
+            }
+            _ => Vec::new(),
+        };
+
+        // Empty main table: give every facet cell a placeholder range so panels,
+        // axes and facet labels still render (pipeline adds a "No data" title)
+        if total_rows == 0 {
+            crate::warn_log!("Main table has no rows - rendering empty panels");
+            Self::fill_empty_axis_ranges(facet_info, &mut axis_ranges);
+        }
+        crate::progress_log!(
+            "axis ranges loaded ({} cells, {} rows expected)",
+            axis_ranges.len(),
+            total_rows
+        );
+
+        // NOTE: axis_ranges now keyed by original_index (not filtered index)
+        // load_axis_ranges_from_table() already maps table's .ri (0-11) → original_index (12-23)
+        // This ensures data[.ri=12] can look up y_ranges[12] correctly
+        crate::debug_log!("axis_ranges keyed by original_index for data matching");
+
+        Ok(LoadedRanges {
+            axis_ranges,
+            total_rows,
+            main_columns,
+            x_categories,
+        })
+    }
+
+    /// Bin the continuous palettes of `factors` into `bins` constant-color quantile bands
+    ///
+    /// Breaks are the quantiles of each factor's sampled values (taken from
+    /// `color_values`). Returns the (label, color) band entries of each binned factor.
+    fn bin_colors(
+        color_infos: &mut [tercen_rs::ColorInfo],
+        factors: &[String],
+        color_values: &mut HashMap<String, Vec<f64>>,
+        bins: usize,
+    ) -> HashMap<String, Vec<(String, [u8; 3])>> {
+        let mut bands: HashMap<String, Vec<(String, [u8; 3])>> = HashMap::new();
+        for factor in factors {
+            if bands.contains_key(factor) {
+                continue;
+            }
+            let mut values = color_values.remove(factor).unwrap_or_default();
+            let breaks = palette::quantile_breaks(&mut values, bins);
+            for color_info in color_infos.iter_mut() {
+                if &color_info.factor_name != factor {
+                    continue;
+                }
+                if let tercen_rs::ColorMapping::Continuous(ref mut p) = color_info.mapping {
+                    let entries = palette::bin_stops(
+                        &mut p.stops,
+                        |s| &mut s.value,
+                        |s| &mut s.color,
+                        &breaks,
+                    );
+                    if !entries.is_empty() {
+                        crate::info_log!(
+                            "  Color '{}' binned into {} quantile bands",
+                            factor,
+                            entries.len()
+                        );
+                        bands.insert(factor.clone(), entries);
+                    }
+                }
+            }
+        }
+        bands
+    }
+
+    /// Transforms of the layers whose Y values were transformed differently from the axis
+    ///
+    /// Keyed by layer (`.axisIndex`); layers with an empty spec are left out. Needs
+    /// `layered` data (two or more plotted layers) with `.y` and `.axisIndex` columns
+    /// and a Y axis transform with local functions, otherwise the specs are ignored
+    /// with a warning.
+    fn layer_value_transforms(
+        specs: &[String],
+        layered: bool,
+        main_columns: &[String],
+        y_value_transform: Option<ValueTransform>,
+    ) -> Result<HashMap<i64, ValueTransform>, String> {
+        let mut layer_transforms = HashMap::new();
+        if specs.iter().all(|t| t.trim().is_empty()) {
+            return Ok(layer_transforms);
+        }
+        let has_columns = main_columns.is_empty()
+            || [".y", ".axisIndex"]
+                .iter()
+                .all(|c| main_columns.iter().any(|m| m == c));
+        if !layered {
+            crate::warn_log!(
+                "Per-layer Y transforms need two or more plotted layers - ignoring them"
+            );
+        } else if !has_columns {
+            crate::warn_log!(
+                "Per-layer Y transforms need .y and .axisIndex columns - ignoring them"
+            );
+        } else if y_value_transform.is_none() {
+            crate::warn_log!(
+                "Per-layer Y transforms can't be re-expressed on this Y axis transform - ignoring them"
+            );
+        } else {
+            for (layer, spec) in specs.iter().enumerate() {
+                if spec.trim().is_empty() {
+                    continue;
+                }
+                match ValueTransform::parse(spec)? {
+                    Some(t) => {
+                        layer_transforms.insert(layer as i64, t);
+                    }
+                    None => crate::warn_log!(
+                        "Layer {} Y transform '{}' is not supported per layer - ignoring it",
+                        layer + 1,
+                        spec.trim()
+                    ),
+                }
+            }
+            crate::info_log!("  Per-layer Y transforms: {:?}", layer_transforms);
+        }
+        Ok(layer_transforms)
+    }
+
+    /// What the main-table pre-pass collects for the enabled options
+    ///
+    /// Returns the request with the continuous color factors that percentile clipping
+    /// (`clip`) and color binning (`bin`) apply to; binned factors are limited to the
+    /// main table's columns when those are known.
+    fn prescan_request(
+        color_infos: &[tercen_rs::ColorInfo],
+        per_layer_colors: Option<&tercen_rs::PerLayerColorConfig>,
+        main_columns: &[String],
+        sample_size: Option<usize>,
+        drop_unused_levels: bool,
+        clip: bool,
+        bin: bool,
+    ) -> (PrescanRequest, Vec<String>, Vec<String>) {
+        let has_categorical = color_infos
+            .iter()
+            .any(|ci| matches!(ci.mapping, tercen_rs::ColorMapping::Categorical(_)));
+        let continuous_factors: Vec<String> = color_infos
+            .iter()
+            .filter(|ci| matches!(ci.mapping, tercen_rs::ColorMapping::Continuous(_)))
+            .map(|ci| ci.factor_name.clone())
+            .collect();
+        let mut clip_factors = continuous_factors.clone();
+        if let Some(plc) = per_layer_colors {
+            for config in &plc.layer_configs {
+                if let tercen_rs::LayerColorConfig::Continuous { factor_name, .. } = config {
+                    if !clip_factors.contains(factor_name) {
+                        clip_factors.push(factor_name.clone());
+                    }
+                }
+            }
+        }
+        let bin_factors: Vec<String> = continuous_factors
+            .into_iter()
+            .filter(|f| main_columns.is_empty() || main_columns.contains(f))
+            .collect();
+        let mut color_factors = Vec::new();
+        if clip {
+            color_factors.extend(clip_factors.iter().cloned());
+        }
+        if bin {
+            color_factors.extend(bin_factors.iter().cloned());
+        }
+        dedup_columns(&mut color_factors);
+        let request = PrescanRequest {
+            sample_size,
+            color_levels: drop_unused_levels && has_categorical,
+            color_factors,
+        };
+        (request, clip_factors, bin_factors)
+    }
+
+    /// Apply the axis transforms, sqrt negative policies, pinned limits and Y reversal
+    ///
+    /// Pairs are (X, Y). The Y axis is reversed last, so pinned limits are flipped too.
+    fn finish_axis_ranges(
+        axis_ranges: &mut HashMap<(usize, usize), (AxisData, AxisData)>,
+        (x_transform, y_transform): (&Option<Transform>, &Option<Transform>),
+        (x_sqrt_policy, y_sqrt_policy): (Option<SqrtNegativePolicy>, Option<SqrtNegativePolicy>),
+        (x_axis_limits, y_axis_limits): (Option<(f64, f64)>, Option<(f64, f64)>),
+        flip_y: bool,
+    ) {
+        // Apply transform info to axis ranges
+        // This tells GGRS that the data is pre-transformed and needs inversion
+        if y_transform.is_some() || x_transform.is_some() {
+            Self::apply_axis_transforms(axis_ranges, x_transform, y_transform);
+            crate::debug_log!(
+                "Applied transforms to axis_ranges - Y: {:?}, X: {:?}",
+                y_transform,
+                x_transform
+            );
+        }
+
+        // Sqrt axes can't invert negative values: apply the configured policy
+        if y_sqrt_policy.is_some() || x_sqrt_policy.is_some() {
+            for (x_axis, y_axis) in axis_ranges.values_mut() {
+                if let (Some(policy), AxisData::Numeric(ref mut num)) = (y_sqrt_policy, y_axis) {
+                    apply_sqrt_policy(num, policy);
+                }
+                if let (Some(policy), AxisData::Numeric(ref mut num)) = (x_sqrt_policy, x_axis) {
+                    apply_sqrt_policy(num, policy);
+                }
+            }
+            crate::debug_log!(
+                "Applied sqrt negative policies - Y: {:?}, X: {:?}",
+                y_sqrt_policy,
+                x_sqrt_policy
+            );
+        }
+
+        // Apply pinned axis limits (display range only, dequantization range untouched)
+        if y_axis_limits.is_some() || x_axis_limits.is_some() {
+            Self::apply_axis_limits(axis_ranges, x_axis_limits, y_axis_limits);
+            crate::info_log!(
+                "  Pinned axis limits - X: {:?}, Y: {:?}",
+                x_axis_limits,
+                y_axis_limits
+            );
+        }
+
+        // Reverse the Y axis
+        if flip_y {
+            for (_, y_axis) in axis_ranges.values_mut() {
+                if let AxisData::Numeric(ref mut num) = y_axis {
+                    std::mem::swap(&mut num.min_axis, &mut num.max_axis);
+                }
+            }
+            crate::info_log!("  Y axis reversed");
+        }
+    }
+
+    /// Drop sparse facets, order the facets and keep the preview subset
+    ///
+    /// Sparse facets are the columns/rows where every cell has fewer than
+    /// `min_points` points (heatmaps keep the full grid, it is the plot itself).
+    /// Ordering comes before the preview so it keeps the first panels in that order
+    /// (heatmap tiles stay at their .ci/.ri positions); the preview is applied after
+    /// the axis tables are read, since they reference every facet index.
+    fn arrange_facets(
+        facet_info: &mut FacetInfo,
+        axis_ranges: &mut HashMap<(usize, usize), (AxisData, AxisData)>,
+        counts: &HashMap<(usize, usize), usize>,
+        min_points: Option<usize>,
+        facet_order: Option<&str>,
+        max_facets: Option<usize>,
+        not_heatmap: bool,
+    ) {
+        if let Some(min) = min_points.filter(|_| not_heatmap) {
+            Self::suppress_sparse_facets(facet_info, axis_ranges, counts, min);
+        }
+        if let Some(column) = facet_order.filter(|_| not_heatmap) {
+            Self::sort_facets(facet_info, column);
+        }
+        if let Some(max) = max_facets {
+            Self::truncate_facets(facet_info, axis_ranges, max);
+        }
+    }
+
+    /// `.x`/`.y` aesthetics, plus `.color` when any layer is colored
+    ///
+    /// Dequantization happens in GGRS using the axis ranges, after which the data
+    /// columns are `.x` and `.y` (actual data values).
+    fn data_aes(
+        color_infos: &[tercen_rs::ColorInfo],
+        per_layer_colors: Option<&tercen_rs::PerLayerColorConfig>,
+    ) -> Aes {
+        let aes = Aes::new().x(".x").y(".y");
+
+        // With LayerColorConfig, every layer has a config (explicit or constant)
+        let has_colors = match per_layer_colors {
+            Some(plc) => plc.has_explicit_colors() || plc.has_constant_colors(),
+            None => !color_infos.is_empty(),
+        };
+
+        crate::debug_log!("color_infos.len() = {}", color_infos.len());
+        crate::debug_log!(
+            "per_layer_colors = {:?}",
+            per_layer_colors.map(|p| format!(
+                "n_layers={}, has_explicit={}, is_mixed={}",
+                p.n_layers,
+                p.has_explicit_colors(),
+                p.is_mixed()
+            ))
+        );
+
+        if !has_colors {
+            crate::debug_log!("No colors configured, NOT adding .color aesthetic");
+            return aes;
+        }
+        crate::debug_log!("Adding .color aesthetic to Aes");
+        if let Some(color_info) = color_infos.first() {
+            crate::debug_log!("Color factor: '{}'", color_info.factor_name);
+            match &color_info.mapping {
+                tercen_rs::ColorMapping::Continuous(palette) => {
+                    crate::debug_log!(
+                        "Continuous palette with {} color stops",
+                        palette.stops.len()
+                    );
+                    for (i, stop) in palette.stops.iter().enumerate() {
+                        crate::debug_log!(
+                            "  Stop {}: value={:.2}, color=RGB({}, {}, {})",
+                            i,
+                            stop.value,
+                            stop.color[0],
+                            stop.color[1],
+                            stop.color[2]
+                        );
+                    }
+                }
+                tercen_rs::ColorMapping::Categorical(color_map) => {
+                    crate::debug_log!(
+                        "Categorical palette with {} categories",
+                        color_map.mappings.len()
+                    );
+                }
+            }
+        }
+        aes.color(".color")
+    }
+
+    /// Facet spec of the facet metadata
+    ///
+    /// Labels use the facet tables' column names, or "column"/"row" when they have
+    /// none. Data filtering still uses the .ri/.ci indices.
+    fn build_facet_spec(facet_info: &FacetInfo, facet_scales: FacetScaleMode) -> FacetSpec {
+        let row_var = Self::facet_variable(&facet_info.row_facets.column_names, "row");
+        let col_var = Self::facet_variable(&facet_info.col_facets.column_names, "column");
+        let scales = Self::ggrs_facet_scales(facet_scales);
+        match (
+            facet_info.row_facets.is_empty(),
+            facet_info.col_facets.is_empty(),
+        ) {
+            // Grid faceting: rows × columns
+            (false, false) => FacetSpec::grid(row_var, col_var).scales(scales),
+            (false, true) => FacetSpec::row(row_var).scales(scales),
+            (true, false) => FacetSpec::col(col_var).scales(scales),
+            (true, true) => FacetSpec::none(),
+        }
+    }
+
+    /// Order the column or row facet groups by the values of facet column `column`
+    ///
+    /// Group labels join the facet column values with ", " (in `column_names` order),
+    /// so the key is that column's part of the label (the whole label when it doesn't
+    /// split into one part per column). Only display positions change: each group
+    /// keeps its `original_index`, which data rows and axis ranges are matched on.
+    fn sort_facets(facet_info: &mut FacetInfo, column: &str) {
+        let mut found = false;
+        for (kind, facets) in [
+            ("column", &mut facet_info.col_facets),
+            ("row", &mut facet_info.row_facets),
+        ] {
+            let Some(position) = facets.column_names.iter().position(|c| c == column) else {
+                continue;
+            };
+            found = true;
+            let n_columns = facets.column_names.len();
+            let keys: Vec<&str> = facets
+                .groups
+                .iter()
+                .map(|g| {
+                    let parts: Vec<&str> = g.label.split(", ").collect();
+                    if parts.len() == n_columns {
+                        parts[position]
+                    } else {
+                        g.label.as_str()
+                    }
+                })
+                .collect();
+            let order = facet_sort_order(&keys);
+            let mut groups: Vec<_> = facets.groups.drain(..).map(Some).collect();
+            facets.groups = order.iter().filter_map(|&i| groups[i].take()).collect();
+            crate::info_log!("  {} facets ordered by '{}'", kind, column);
+        }
+        if !found {
+            crate::warn_log!(
+                "facet.order column '{}' is not a facet column - keeping table order",
+                column
+            );
+        }
+    }
+
+    /// Keep only the first `max` column and row facets (preview mode)
+    ///
+    /// Axis ranges of dropped cells are removed; their data rows are still streamed
+    /// but GGRS ignores rows whose `.ci`/`.ri` has no panel.
+    fn truncate_facets(
+        facet_info: &mut FacetInfo,
+        axis_ranges: &mut HashMap<(usize, usize), (AxisData, AxisData)>,
+        max: usize,
+    ) {
+        let (n_cols, n_rows) = (facet_info.n_col_facets(), facet_info.n_row_facets());
+        if n_cols <= max && n_rows <= max {
+            return;
+        }
+
+        facet_info.col_facets.groups.truncate(max);
+        facet_info.row_facets.groups.truncate(max);
+        let kept_cols: Vec<usize> = facet_info
+            .col_facets
+            .groups
+            .iter()
+            .map(|g| g.original_index)
+            .collect();
+        let kept_rows: Vec<usize> = facet_info
+            .row_facets
+            .groups
+            .iter()
+            .map(|g| g.original_index)
+            .collect();
+        retain_facet_cells(axis_ranges, &kept_cols, &kept_rows);
+
+        crate::info_log!(
+            "PREVIEW: rendering only the first {} × {} of {} × {} facets (facet.preview.max = {})",
+            facet_info.n_col_facets(),
+            facet_info.n_row_facets(),
+            n_cols,
+            n_rows,
+            max
+        );
+    }
+
+    /// Shared pre-pass over the main table (see `Prescan`)
+    ///
+    /// Streams the facet index columns and the value columns `request` needs once,
+    /// instead of one full-table scan per option. Histograms bin against the axis
+    /// ranges this pass can still change, so they keep their own scan.
+    async fn prescan(
+        client: &TercenClient,
+        schema_cache: &Option<SchemaCache>,
+        main_table_id: &str,
+        main_columns: &[String],
+        total_rows: usize,
+        request: &PrescanRequest,
+    ) -> Result<Prescan, Box<dyn std::error::Error>> {
+        const CHUNK_SIZE: usize = 100_000;
+        let mut prescan = Prescan::default();
+        let columns = Prescan::columns(request, main_columns);
+        if columns.is_empty() {
+            (0..total_rows).for_each(|row| prescan.add_point(request.sample_size, row, (0, 0)));
+            return Ok(prescan);
+        }
+        let streamer = Self::create_streamer(client, schema_cache);
+
+        let mut offset = 0usize;
+        while offset < total_rows {
+            let limit = (total_rows - offset).min(CHUNK_SIZE);
+            let tson_data = with_retry_from_env(|| {
+                streamer.stream_tson(
+                    main_table_id,
+                    Some(columns.clone()),
+                    offset as i64,
+                    limit as i64,
+                )
+            })
+            .await?;
+            if tson_data.is_empty() {
+                break;
+            }
+
+            let chunk_df = tson_to_dataframe(&tson_data)?;
+            if chunk_df.height() == 0 {
+                break;
+            }
+            prescan.visit(request, &chunk_df, offset)?;
+            offset += chunk_df.height();
+        }
+        crate::progress_log!(
+            "main table pre-pass done ({} rows, {} cells)",
+            offset,
+            prescan.counts.len()
+        );
+
+        Ok(prescan)
+    }
+
+    /// Stream some columns of the main table in chunks and visit each chunk
+    ///
+    /// Returns false (without visiting) when the table lacks one of the columns.
+    async fn scan_main_columns(
+        client: &TercenClient,
+        schema_cache: &Option<SchemaCache>,
+        main_table_id: &str,
+        main_columns: &[String],
+        total_rows: usize,
+        columns: &[&str],
+        mut visit: impl FnMut(&polars::frame::DataFrame) -> Result<(), Box<dyn std::error::Error>>,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        const CHUNK_SIZE: usize = 100_000;
+        if !main_columns.is_empty()
+            && !columns
+                .iter()
+                .all(|column| main_columns.iter().any(|c| c == column))
+        {
+            return Ok(false);
+        }
+        let streamer = Self::create_streamer(client, schema_cache);
+        let columns: Vec<String> = columns.iter().map(|c| c.to_string()).collect();
+
+        let mut offset = 0usize;
+        while offset < total_rows {
+            let limit = (total_rows - offset).min(CHUNK_SIZE);
+            let tson_data = with_retry_from_env(|| {
+                streamer.stream_tson(
+                    main_table_id,
+                    Some(columns.clone()),
+                    offset as i64,
+                    limit as i64,
+                )
+            })
+            .await?;
+            if tson_data.is_empty() {
+                break;
+            }
+
+            let chunk_df = tson_to_dataframe(&tson_data)?;
+            if chunk_df.height() == 0 {
+                break;
+            }
+            if !columns.iter().all(|c| chunk_df.column(c).is_ok()) {
+                return Ok(false);
+            }
+            visit(&chunk_df)?;
+            offset += chunk_df.height();
+        }
+
+        Ok(true)
+    }
+
+    /// Histogram bin counts of the `.y` values per cell
+    ///
+    /// Each cell's Y data range is split into `bins` equal bins. Every chunk is
+    /// binned and counted with a group_by over (.ci, .ri, bin); values outside the
+    /// range are dropped. Cells are keyed by original index.
+    #[allow(clippy::too_many_arguments)]
+    async fn histogram_counts(
+        client: &TercenClient,
+        schema_cache: &Option<SchemaCache>,
+        main_table_id: &str,
+        main_columns: &[String],
+        total_rows: usize,
+        axis_ranges: &HashMap<(usize, usize), (AxisData, AxisData)>,
+        bins: usize,
+    ) -> Result<HashMap<(usize, usize), Vec<u64>>, Box<dyn std::error::Error>> {
+        use polars::prelude::*;
+
+        let ranges: HashMap<(usize, usize), (f64, f64)> = axis_ranges
+            .iter()
+            .filter_map(|(&cell, (_, y))| match y {
+                AxisData::Numeric(num) => Some((
+                    cell,
+                    (
+                        num.min_value.min(num.max_value),
+                        num.min_value.max(num.max_value),
+                    ),
+                )),
+                _ => None,
+            })
+            .collect();
+        let mut counts: HashMap<(usize, usize), Vec<u64>> = HashMap::new();
+        let index_columns: Vec<&str> = [".ci", ".ri"]
+            .into_iter()
+            .filter(|c| main_columns.is_empty() || main_columns.iter().any(|m| m == c))
+            .collect();
+        let mut columns = vec![".y"];
+        columns.extend(index_columns);
+        let found = Self::scan_main_columns(
+            client,
+            schema_cache,
+            main_table_id,
+            main_columns,
+            total_rows,
+            &columns,
+            |chunk| {
+                let cells = facet_cells(chunk)?;
+                let ys = chunk.column(".y")?.cast(&DataType::Float64)?;
+                let bin: Vec<Option<i64>> = ys
+                    .f64()?
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, y)| {
+                        let (min, max) = *ranges.get(&cells[i])?;
+                        let y = y.filter(|y| y.is_finite() && *y >= min && *y <= max)?;
+                        let t = if max > min {
+                            (y - min) / (max - min)
+                        } else {
+                            0.5
+                        };
+                        Some(((t * bins as f64) as i64).min(bins as i64 - 1))
+                    })
+                    .collect();
+
+                let grouped = polars::frame::DataFrame::new(vec![
+                    Column::new(".ci".into(), ci),
+                    Column::new(".ri".into(), ri),
+                    Column::new(".bin".into(), bin),
+                ])?
+                .lazy()
+                .filter(col(".bin").is_not_null())
+                .group_by([col(".ci"), col(".ri"), col(".bin")])
+                .agg([len().cast(DataType::Int64).alias(".count")])
+                .collect()?;
+
+                let column = |name: &str| grouped.column(name).and_then(|c| c.i64().cloned());
+                let (ci, ri, bin, n) = (
+                    column(".ci")?,
+                    column(".ri")?,
+                    column(".bin")?,
+                    column(".count")?,
+                );
+                for i in 0..grouped.height() {
+                    if let (Some(ci), Some(ri), Some(bin), Some(n)) =
+                        (ci.get(i), ri.get(i), bin.get(i), n.get(i))
+                    {
+                        counts
+                            .entry((ci as usize, ri as usize))
+                            .or_insert_with(|| vec![0; bins])[bin as usize] += n as u64;
+                    }
+                }
+                Ok(())
+            },
+        )
+        .await?;
+        if !found {
+            return Err("bar.histogram needs the .y column in the main table".into());
+        }
+        Ok(counts)
+    }
+
+    /// Histogram bars as bulk rows, replacing the cells' axes
+    ///
+    /// Each numeric cell's X axis becomes its Y data range and its Y axis the count
+    /// range (0 to the cell's largest count, at least 1). Bars are placed at the bin
+    /// centers and start at 0 (`.y0s`); empty bins get no bar.
+    fn histogram_bars(
+        counts: &HashMap<(usize, usize), Vec<u64>>,
+        bins: usize,
+        axis_ranges: &mut HashMap<(usize, usize), (AxisData, AxisData)>,
+    ) -> Result<DataFrame, Box<dyn std::error::Error>> {
+        use polars::prelude::*;
+
+        let (mut ci, mut ri, mut xs, mut ys) = (Vec::new(), Vec::new(), Vec::new(), Vec::new());
+        for (&(col, row), (x_axis, y_axis)) in axis_ranges.iter_mut() {
+            let AxisData::Numeric(y) = &*y_axis else {
+                continue;
+            };
+            let (min, max) = (y.min_value.min(y.max_value), y.min_value.max(y.max_value));
+            let cell_counts = counts.get(&(col, row));
+            let peak = cell_counts
+                .and_then(|c| c.iter().copied().max())
+                .unwrap_or(0)
+                .max(1) as f64;
+
+            *x_axis = AxisData::Numeric(NumericAxisData {
+                min_value: min,
+                max_value: max,
+                min_axis: min,
+                max_axis: max,
+                transform: None,
+            });
+            *y_axis = AxisData::Numeric(NumericAxisData {
+                min_value: 0.0,
+                max_value: peak,
+                min_axis: 0.0,
+                max_axis: peak,
+                transform: None,
+            });
+
+            for (bin, &n) in cell_counts.into_iter().flatten().enumerate() {
+                if n == 0 {
+                    continue;
+                }
+                ci.push(col as i64);
+                ri.push(row as i64);
+                xs.push(((bin as f64 + 0.5) / bins as f64 * 65535.0).round() as i64);
+                ys.push((n as f64 / peak * 65535.0).round() as i64);
+            }
+        }
+
+        let y0s = vec![0i64; ci.len()];
+        let df = polars::frame::DataFrame::new(vec![
+            Column::new(".ci".into(), ci),
+            Column::new(".ri".into(), ri),
+            Column::new(".xs".into(), xs),
+            Column::new(".ys".into(), ys),
+            Column::new(".y0s".into(), y0s),
+        ])?;
+        Ok(ggrs_core::data::DataFrame::from_polars(df))
+    }
+
+    /// Percentile range of each continuous color factor's values
+    ///
+    /// `values` are the factors' sampled finite values from the pre-pass. Factors missing
+    /// from the table or without finite values are left out.
+    fn color_percentile_ranges(
+        values: &mut HashMap<String, Vec<f64>>,
+        factors: &[String],
+        percentiles: (f64, f64),
+    ) -> HashMap<String, (f64, f64)> {
+        factors
+            .iter()
+            .filter_map(|factor| {
+                let range = palette::percentile_range(values.get_mut(factor)?, percentiles)?;
+                Some((factor.clone(), range))
+            })
+            .collect()
+    }
+
+    /// Rescale each continuous palette onto its factor's range in `ranges`
+    ///
+    /// Like apply_color_range, but per factor; palettes without a range are kept.
+    fn apply_color_ranges(
+        mut color_infos: Vec<tercen_rs::ColorInfo>,
+        mut per_layer_colors: Option<tercen_rs::PerLayerColorConfig>,
+        ranges: &HashMap<String, (f64, f64)>,
+    ) -> (
+        Vec<tercen_rs::ColorInfo>,
+        Option<tercen_rs::PerLayerColorConfig>,
+    ) {
+        for color_info in &mut color_infos {
+            if let tercen_rs::ColorMapping::Continuous(ref mut p) = color_info.mapping {
+                if let Some(&range) = ranges.get(&color_info.factor_name) {
+                    palette::rescale_stops(&mut p.stops, |s| &mut s.value, range);
+                }
+            }
+        }
+        if let Some(ref mut plc) = per_layer_colors {
+            for config in plc.layer_configs.iter_mut() {
+                if let tercen_rs::LayerColorConfig::Continuous {
+                    palette: p,
+                    factor_name,
+                    ..
+                } = config
+                {
+                    if let Some(&range) = ranges.get(factor_name.as_str()) {
+                        palette::rescale_stops(&mut p.stops, |s| &mut s.value, range);
+                    }
+                }
+            }
+        }
+        (color_infos, per_layer_colors)
+    }
+
+    /// Drop facet columns and rows in which every cell has fewer than `min` points
+    ///
+    /// Cells are keyed by original index, like `axis_ranges`. A grid cell can't be
+    /// removed on its own, so a column (row) is dropped only when all its cells are
+    /// sparse. Nothing is dropped if it would leave no facets.
+    fn suppress_sparse_facets(
+        facet_info: &mut FacetInfo,
+        axis_ranges: &mut HashMap<(usize, usize), (AxisData, AxisData)>,
+        counts: &HashMap<(usize, usize), usize>,
+        min: usize,
+    ) {
+        let col_indices: Vec<usize> = facet_info
+            .col_facets
+            .groups
+            .iter()
+            .map(|g| g.original_index)
+            .collect();
+        let row_indices: Vec<usize> = facet_info
+            .row_facets
+            .groups
+            .iter()
+            .map(|g| g.original_index)
+            .collect();
+        let Some((sparse_cols, sparse_rows)) =
+            sparse_facets(&col_indices, &row_indices, counts, min)
+        else {
+            return;
+        };
+
+        facet_info
+            .col_facets
+            .groups
+            .retain(|g| !sparse_cols.contains(&g.original_index));
+        facet_info
+            .row_facets
+            .groups
+            .retain(|g| !sparse_rows.contains(&g.original_index));
+        axis_ranges
+            .retain(|(col, row), _| !sparse_cols.contains(col) && !sparse_rows.contains(row));
+
+        crate::info_log!(
+            "  Suppressed {} facet column(s) and {} facet row(s) with fewer than {} points per cell",
+            sparse_cols.len(),
+            sparse_rows.len(),
+            min
+        );
+    }
+
+    /// Keep only the facet cell at grid position (`col`, `row`)
+    ///
+    /// Returns the cell's original (.ci, .ri). An axis without facet groups keeps
+    /// index 0.
+    fn restrict_to_cell(
+        facet_info: &mut FacetInfo,
+        axis_ranges: &mut HashMap<(usize, usize), (AxisData, AxisData)>,
+        col: usize,
+        row: usize,
+    ) -> Result<(usize, usize), Box<dyn std::error::Error>> {
+        let (n_cols, n_rows) = (facet_info.n_col_facets(), facet_info.n_row_facets());
+        if (n_cols > 0 && col >= n_cols) || (n_rows > 0 && row >= n_rows) {
+            return Err(format!(
+                "Facet cell ({}, {}) is outside the {} × {} facet grid",
+                col, row, n_cols, n_rows
+            )
+            .into());
+        }
+
+        let keep = |groups: &mut Vec<_>, idx: usize| {
+            if !groups.is_empty() {
+                let group = groups.swap_remove(idx);
+                groups.clear();
+                groups.push(group);
+            }
+        };
+        keep(&mut facet_info.col_facets.groups, col);
+        keep(&mut facet_info.row_facets.groups, row);
+
+        let original_col = facet_info
+            .col_facets
+            .groups
+            .first()
+            .map_or(0, |g| g.original_index);
+        let original_row = facet_info
+            .row_facets
+            .groups
+            .first()
+            .map_or(0, |g| g.original_index);
+        axis_ranges.retain(|key, _| *key == (original_col, original_row));
+
+        Ok((original_col, original_row))
+    }
+
+    /// Map the configured facet scales onto the GGRS variant
+    fn ggrs_facet_scales(mode: FacetScaleMode) -> ggrs_core::stream::FacetScales {
+        use ggrs_core::stream::FacetScales;
+        match mode {
+            FacetScaleMode::Fixed => FacetScales::Fixed,
+            FacetScaleMode::FreeX => FacetScales::FreeX,
+            FacetScaleMode::FreeY => FacetScales::FreeY,
+            FacetScaleMode::Free => FacetScales::Free,
+        }
+    }
+
+    /// Facet variable name: the first non-empty facet column name, or `fallback`
+    fn facet_variable(column_names: &[String], fallback: &str) -> String {
+        column_names
+            .iter()
+            .find(|name| !name.is_empty())
+            .cloned()
+            .unwrap_or_else(|| fallback.to_string())
+    }
+
+    /// Strip labels for facet groups
+    ///
+    /// Groups without a label (facet tables without named columns) are numbered
+    /// "Column 1", "Row 2", ... rather than left blank.
+    fn facet_group_labels<'a>(
+        &self,
+        labels: impl Iterator<Item = &'a str>,
+        kind: &str,
+    ) -> Vec<String> {
+        labels
+            .enumerate()
+            .map(|(position, label)| {
+                let label = label.trim();
+                if label.is_empty() {
+                    format!("{} {}", kind, position + 1)
+                } else {
+                    self.display_facet_label(label)
+                }
+            })
+            .collect()
+    }
+
+    /// Create a table streamer: local fixtures when FIXTURE_DIR is set (and
+    /// TERCEN_URI is not), otherwise Tercen, using the schema cache if available
+    fn create_streamer<'a>(
+        client: &'a TercenClient,
+        cache: &Option<SchemaCache>,
+    ) -> DataStreamer<'a> {
+        if let Some(fixture) = FixtureStreamer::from_env() {
+            crate::debug_log!("Reading tables from local fixtures (FIXTURE_DIR)");
+            return DataStreamer::Fixture(fixture);
+        }
+        match cache {
+            Some(c) => DataStreamer::Tercen(TableStreamer::with_cache(client, c.clone())),
+            None => DataStreamer::Tercen(TableStreamer::new(client)),
+        }
+    }
+
+    /// Create a stream generator with pre-computed axis ranges
+    ///
+    /// This is used when axis ranges are provided externally.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_with_ranges(
+        client: Arc<TercenClient>,
+        main_table_id: String,
+        facet_info: FacetInfo,
+        axis_ranges: HashMap<(usize, usize), (AxisData, AxisData)>,
+        total_rows: usize,
+        chunk_size: usize,
+        color_infos: Vec<tercen_rs::ColorInfo>,
+        page_factors: Vec<String>,
+        facet_scales: FacetScaleMode,
+    ) -> Self {
+        // Aesthetics use dequantized coordinates: .x and .y (actual data values)
+        // Dequantization happens in stream_facet_data() before data reaches renderers
+        // Add color aesthetic if colors are defined
+        let mut aes = Aes::new().x(".x").y(".y");
+        if !color_infos.is_empty() {
+            aes = aes.color(".color");
+        }
+
+        // Create facet spec based on facet metadata
+        // Same variable names as new(); data filtering still uses .ri/.ci indices
+        let row_var = Self::facet_variable(&facet_info.row_facets.column_names, "row");
+        let col_var = Self::facet_variable(&facet_info.col_facets.column_names, "column");
+        let facet_spec = if !facet_info.row_facets.is_empty() && !facet_info.col_facets.is_empty() {
+            // Grid faceting: rows × columns
+            FacetSpec::grid(row_var, col_var).scales(Self::ggrs_facet_scales(facet_scales))
+        } else if !facet_info.row_facets.is_empty() {
+            // Row faceting only
+            FacetSpec::row(row_var).scales(Self::ggrs_facet_scales(facet_scales))
+        } else if !facet_info.col_facets.is_empty() {
+            // Column faceting only
+            FacetSpec::col(col_var).scales(Self::ggrs_facet_scales(facet_scales))
+        } else {
+            // No faceting
+            FacetSpec::none()
+        };
+
+        // NO FILTERING! Operator is dumb - GGRS handles everything via original_index.
+
+        let global_axis_range = Self::global_axis_range(&axis_ranges);
+
+        Self {
+            client,
+            main_table_id,
+            facet_info,
+            axis_ranges,
+            total_rows,
+            main_columns: Vec::new(),
+            aes,
+            facet_spec,
+            chunk_size,
+            message_budget_bytes: DEFAULT_MESSAGE_BUDGET_BYTES,
+            color_infos,
+            per_layer_colors: None, // Sync constructor doesn't support per-layer colors
+            cached_legend_scale: LegendScale::None, // TODO: Load async if needed
+            legend_titles: LegendTitles::default(),
+            page_factors,
+            heatmap_mode: None,
+            schema_cache: None, // sync method - no caching
+            heatmap_cached_data: RwLock::new(None),
+            histogram_data: None,
+            preloaded_chunks: Mutex::new(HashMap::new()),
+            heatmap_cell_aggregation: HeatmapCellAggregation::Last, // Default for sync constructor
+            bar_mode: BarMode::Stack,
+            color_levels: None,
+            legend_range: LegendRange::Palette,
+            heatmap_value_ranges: RwLock::new(HashMap::new()),
+            y_transform: None, // Sync constructor doesn't support transforms
+            y_value_transform: Some(ValueTransform::Identity),
+            x_transform: None,
+            x_value_transform: Some(ValueTransform::Identity),
+            n_layers: 1, // Sync constructor defaults to single layer
+            layer_palette_name: None,
+            categorical_palette: Vec::new(),
+            nan_color: None,
+            palette_override: PaletteOverride::None,
+            layer_y_factor_names: Vec::new(), // Sync constructor defaults to empty
+            x_categorical_factor: None,
+            x_categories: Vec::new(),
+            jitter_width: None,
+            chart_kind: ChartKind::Point, // Sync constructor defaults to Point
+            global_axis_range,
+            strict_axis_ranges: false,
+            data_cache: None,
+            data_export: None,
+            aggregation_timeout: None,
+            max_concurrent_chunks: 1,
+            rows_streamed: AtomicUsize::new(0),
+            facet_label_max_len: None,
+            facet_label_overflow: FacetLabelOverflow::Truncate,
+            cell_filter: None,
+            layer_y_transforms: HashMap::new(),
+            y_color_factor: None,
+            sampled_rows: HashMap::new(),
+        }
+    }
+
+    /// Create a fully configured stream generator from already-loaded parts
+    ///
+    /// Synchronous and without I/O, so the `StreamGenerator` implementation can be
+    /// tested without gRPC. Options come from `config` (its table IDs other than
+    /// `main_table_id` are not used). `facet_info`, `axis_ranges` and `total_rows`
+    /// are taken as given: none of the loading steps of `new()` run (page filter,
+    /// axis tables, axis limits, palette override, color range, facet
+    /// trimming, sampling, categorical X). Call `set_heatmap_mode` for heatmaps, as
+    /// the pipeline does.
+    pub fn from_parts(
+        client: Arc<TercenClient>,
+        facet_info: FacetInfo,
+        axis_ranges: HashMap<(usize, usize), (AxisData, AxisData)>,
+        total_rows: usize,
+        config: TercenStreamConfig,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let TercenStreamConfig {
+            main_table_id,
+            chunk_size,
+            message_budget_bytes,
+            color_infos,
+            per_layer_colors,
+            page_factors,
+            heatmap_cell_aggregation,
+            bar_mode,
+            legend_range,
+            legend_sort,
+            legend_titles,
+            y_transform,
+            x_transform,
+            n_layers,
+            layer_palette_name,
+            categorical_palette,
+            nan_color,
+            palette_override,
+            layer_y_factor_names,
+            jitter_width,
+            chart_kind,
+            strict_axis_ranges,
+            data_cache,
+            data_export,
+            facet_scales,
+            facet_label_max_len,
+            facet_label_overflow,
+            aggregation_timeout,
+            max_concurrent_chunks,
+            ..
+        } = config;
+
+        // Same aesthetics as new()
+        let has_colors = match per_layer_colors {
+            Some(ref plc) => plc.has_explicit_colors() || plc.has_constant_colors(),
+            None => !color_infos.is_empty(),
+        };
+        let mut aes = Aes::new().x(".x").y(".y");
+        if has_colors {
+            aes = aes.color(".color");
+        }
+
+        let cached_legend_scale = Self::load_legend_scale(
+            &color_infos,
+            per_layer_colors.as_ref(),
+            &layer_y_factor_names,
+        )?;
+        let cached_legend_scale = Self::order_legend_entries(
+            cached_legend_scale,
+            legend_sort,
+            &Self::legend_data_order(&color_infos),
+        );
+        let y_value_transform = Self::value_transform(y_transform.as_deref())?;
+        let x_value_transform = Self::value_transform(x_transform.as_deref())?;
+        let y_transform = y_transform
+            .map(|t| parse_transform(&t))
+            .transpose()?
+            .flatten();
+        let x_transform = x_transform
+            .map(|t| parse_transform(&t))
+            .transpose()?
+            .flatten();
+
+        let mut generator = Self::new_with_ranges(
+            client,
+            main_table_id,
+            facet_info,
+            axis_ranges,
+            total_rows,
+            chunk_size,
+            color_infos,
+            page_factors,
+            facet_scales,
+        );
+        generator.aes = aes;
+        generator.cached_legend_scale = cached_legend_scale;
+        generator.legend_titles = legend_titles;
+        generator.per_layer_colors = per_layer_colors;
+        generator.message_budget_bytes = message_budget_bytes;
+        generator.heatmap_cell_aggregation = heatmap_cell_aggregation;
+        generator.bar_mode = bar_mode;
+        generator.legend_range = legend_range;
+        generator.y_transform = y_transform;
+        generator.y_value_transform = y_value_transform;
+        generator.x_transform = x_transform;
+        generator.x_value_transform = x_value_transform;
+        generator.n_layers = n_layers;
+        generator.layer_palette_name = layer_palette_name;
+        generator.categorical_palette = categorical_palette;
+        generator.nan_color = nan_color;
+        generator.palette_override = palette_override;
+        generator.layer_y_factor_names = layer_y_factor_names;
+        generator.jitter_width = jitter_width.filter(|_| matches!(chart_kind, ChartKind::Point));
+        generator.chart_kind = chart_kind;
+        generator.strict_axis_ranges = strict_axis_ranges;
+        generator.data_cache = data_cache;
+        generator.data_export = data_export;
+        generator.aggregation_timeout = aggregation_timeout;
+        generator.max_concurrent_chunks = max_concurrent_chunks;
+        generator.facet_label_max_len = facet_label_max_len;
+        generator.facet_label_overflow = facet_label_overflow;
+        Ok(generator)
+    }
+
+    /// Full (unshortened) column and row facet labels, for tooltips and metadata
+    pub fn full_facet_labels(&self) -> (Vec<String>, Vec<String>) {
+        let col_labels = self.facet_info.col_facets.groups.iter();
+        let row_labels = self.facet_info.row_facets.groups.iter();
+        (
+            col_labels.map(|group| group.label.clone()).collect(),
+            row_labels.map(|group| group.label.clone()).collect(),
+        )
+    }
+
+    /// Shorten a facet label to `facet_label_max_len` characters
+    ///
+    /// Truncation keeps `max_len - 1` characters and appends an ellipsis. Wrapping
+    /// breaks lines at spaces; a single word longer than the limit is truncated.
+    fn display_facet_label(&self, label: &str) -> String {
+        let Some(max_len) = self.facet_label_max_len else {
+            return label.to_string();
+        };
+        let truncate = |text: &str| -> String {
+            if text.chars().count() <= max_len {
+                text.to_string()
+            } else {
+                let mut cut: String = text.chars().take(max_len - 1).collect();
+                cut.push('…');
+                cut
+            }
+        };
+        match self.facet_label_overflow {
+            FacetLabelOverflow::Truncate => truncate(label),
+            FacetLabelOverflow::Wrap => {
+                let mut lines: Vec<String> = Vec::new();
+                let mut line = String::new();
+                for word in label.split_whitespace() {
+                    let word = truncate(word);
+                    if line.is_empty() {
+                        line = word;
+                    } else if line.chars().count() + 1 + word.chars().count() <= max_len {
+                        line.push(' ');
+                        line.push_str(&word);
+                    } else {
+                        lines.push(std::mem::replace(&mut line, word));
+                    }
+                }
+                if !line.is_empty() {
+                    lines.push(line);
+                }
+                lines.join("\n")
+            }
+        }
+    }
+
+    /// Whether the main table has no rows (plot renders empty panels)
+    pub fn is_empty(&self) -> bool {
+        self.total_rows == 0
+    }
+
+    /// Generator for the single facet cell at grid position (`col`, `row`)
+    ///
+    /// Used for split rendering. The pre-scanned state of this generator is copied,
+    /// so the full-table scans of `new()` run once for all cells; the cell's data
+    /// rows are filtered in bulk streaming.
+    pub fn for_cell(&self, col: usize, row: usize) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut facet_info = self.facet_info.clone();
+        let mut axis_ranges = self.axis_ranges.clone();
+        let cell_filter = Self::restrict_to_cell(&mut facet_info, &mut axis_ranges, col, row)?;
+        let global_axis_range = Self::global_axis_range(&axis_ranges);
+
+        Ok(Self {
+            client: self.client.clone(),
+            main_table_id: self.main_table_id.clone(),
+            facet_info,
+            axis_ranges,
+            total_rows: self.total_rows,
+            main_columns: self.main_columns.clone(),
+            aes: self.aes.clone(),
+            facet_spec: self.facet_spec.clone(),
+            chunk_size: self.chunk_size,
+            message_budget_bytes: self.message_budget_bytes,
+            color_infos: self.color_infos.clone(),
+            per_layer_colors: self.per_layer_colors.clone(),
+            cached_legend_scale: self.cached_legend_scale.clone(),
+            legend_titles: self.legend_titles.clone(),
+            page_factors: self.page_factors.clone(),
+            heatmap_mode: self.heatmap_mode,
+            schema_cache: self.schema_cache.clone(),
+            heatmap_cached_data: RwLock::new(None),
+            histogram_data: self.histogram_data.clone(),
+            preloaded_chunks: Mutex::new(HashMap::new()),
+            heatmap_cell_aggregation: self.heatmap_cell_aggregation.clone(),
+            bar_mode: self.bar_mode,
+            color_levels: self.color_levels.clone(),
+            legend_range: self.legend_range,
+            heatmap_value_ranges: RwLock::new(HashMap::new()),
+            y_transform: self.y_transform.clone(),
+            y_value_transform: self.y_value_transform,
+            x_transform: self.x_transform.clone(),
+            x_value_transform: self.x_value_transform,
+            n_layers: self.n_layers,
+            layer_palette_name: self.layer_palette_name.clone(),
+            categorical_palette: self.categorical_palette.clone(),
+            nan_color: self.nan_color,
+            palette_override: self.palette_override,
+            layer_y_factor_names: self.layer_y_factor_names.clone(),
+            x_categorical_factor: self.x_categorical_factor.clone(),
+            x_categories: self.x_categories.clone(),
+            jitter_width: self.jitter_width,
+            chart_kind: self.chart_kind.clone(),
+            global_axis_range,
+            strict_axis_ranges: self.strict_axis_ranges,
+            // Chunks of a cell generator hold only that cell's rows
+            data_cache: None,
+            data_export: self.data_export.clone(),
+            aggregation_timeout: self.aggregation_timeout,
+            max_concurrent_chunks: self.max_concurrent_chunks,
+            rows_streamed: AtomicUsize::new(0),
+            facet_label_max_len: self.facet_label_max_len,
+            facet_label_overflow: self.facet_label_overflow,
+            cell_filter: Some(cell_filter),
+            layer_y_transforms: self.layer_y_transforms.clone(),
+            y_color_factor: self.y_color_factor.clone(),
+            sampled_rows: self.sampled_rows.clone(),
+        })
+    }
+
+    /// Enable heatmap mode with the given grid dimensions
+    ///
+    /// In heatmap mode:
+    /// - Facet counts are overridden to 1x1 (single panel)
+    /// - Axis ranges use grid dimensions: X = (-0.5, n_cols-0.5), Y = (-0.5, n_rows-0.5)
+    /// - Data coordinates use .ci for X and .ri for Y (no quantization)
+    ///
+    /// # Arguments
+    /// * `n_cols` - Number of columns in the heatmap grid (max .ci + 1)
+    /// * `n_rows` - Number of rows in the heatmap grid (max .ri + 1)
+    pub fn set_heatmap_mode(&mut self, n_cols: usize, n_rows: usize) {
+        crate::debug_log!("Enabling heatmap mode with grid {}×{}", n_cols, n_rows);
+        self.heatmap_mode = Some((n_cols, n_rows));
+    }
+
+    /// Get the heatmap grid dimensions if in heatmap mode
+    pub fn heatmap_dims(&self) -> Option<(usize, usize)> {
+        self.heatmap_mode
+    }
+
+    /// Get the original facet grid dimensions (before heatmap mode override)
+    /// Returns (n_col_facets, n_row_facets) from the underlying facet_info
     pub fn original_grid_dims(&self) -> (usize, usize) {
         (
             self.facet_info.n_col_facets(),
@@ -755,13 +3121,16 @@ impl TercenStreamGenerator {
     /// - `First`: Use the first data point
     /// - `Mean`: Compute the mean of all data points
     /// - `Median`: Compute the median of all data points
+    /// - `Mode`: Most frequent categorical level (continuous colors use the mean)
+    /// - `WeightedMean`: Mean weighted by another factor (plain mean without weights)
     ///
     /// # Returns
     /// DataFrame with one row per unique (ci, ri) cell, with aggregated values
     async fn aggregate_heatmap_data(&self) -> Result<DataFrame, Box<dyn std::error::Error>> {
+        use futures::stream::{self, StreamExt, TryStreamExt};
         use polars::prelude::*;
 
-        eprintln!("DEBUG: Aggregating heatmap data by (.ci, .ri)");
+        crate::debug_log!("Aggregating heatmap data by (.ci, .ri)");
 
         let streamer = Self::create_streamer(&self.client, &self.schema_cache);
 
@@ -783,59 +3152,86 @@ impl TercenStreamGenerator {
                 }
             }
         }
-        eprintln!(
-            "DEBUG: Fetching columns for heatmap aggregation: {:?}",
-            columns
-        );
 
         // Get the actual row count from schema
-        let schema = streamer.get_schema(&self.main_table_id).await?;
+        let schema = with_retry_from_env(|| streamer.get_schema(&self.main_table_id)).await?;
         let actual_total_rows = extract_row_count_from_schema(&schema)? as usize;
-        eprintln!(
-            "DEBUG: Schema says {} actual rows to aggregate",
-            actual_total_rows
-        );
-
-        // Stream data in chunks and accumulate (TSON decoding only handles one chunk at a time)
-        let chunk_size = 50000; // Larger chunks for aggregation efficiency
-        let mut accumulated_dfs: Vec<polars::frame::DataFrame> = Vec::new();
-        let mut offset = 0usize;
-
-        while offset < actual_total_rows {
-            let remaining = actual_total_rows - offset;
-            let limit = remaining.min(chunk_size);
-
-            let tson_data = streamer
-                .stream_tson(
-                    &self.main_table_id,
-                    Some(columns.clone()),
-                    offset as i64,
-                    limit as i64,
-                )
-                .await?;
 
-            if tson_data.is_empty() {
-                break;
+        // Weight column for weighted means, if the main table has it
+        let weight_column = match &self.heatmap_cell_aggregation {
+            HeatmapCellAggregation::WeightedMean { weight_factor } => {
+                if extract_column_names_from_schema(&schema)?.contains(weight_factor) {
+                    Some(weight_factor.clone())
+                } else {
+                    crate::warn_log!(
+                        "Heatmap weight factor '{}' not found in the main table, using mean",
+                        weight_factor
+                    );
+                    None
+                }
             }
-
-            let chunk_df = tson_to_dataframe(&tson_data)?;
-            let chunk_rows = chunk_df.height();
-
-            if chunk_rows == 0 {
-                break;
+            _ => None,
+        };
+        if let Some(weight) = &weight_column {
+            if !columns.contains(weight) {
+                columns.push(weight.clone());
             }
+        }
+        crate::debug_log!("Fetching columns for heatmap aggregation: {:?}", columns);
+        crate::debug_log!("Schema says {} actual rows to aggregate", actual_total_rows);
+
+        // Stream row ranges concurrently and accumulate (TSON decoding only handles one
+        // chunk at a time). `buffered` keeps chunks in table order, which first/last
+        // aggregation depends on. Rows in flight are capped, so chunks shrink as
+        // concurrency grows.
+        let concurrency = self.max_concurrent_chunks.max(1);
+        let chunk_size = (HEATMAP_MAX_IN_FLIGHT_ROWS / concurrency)
+            .clamp(HEATMAP_CHUNK_ROWS.0, HEATMAP_CHUNK_ROWS.1);
+        let ranges: Vec<(usize, usize)> = (0..actual_total_rows)
+            .step_by(chunk_size)
+            .map(|offset| (offset, chunk_size.min(actual_total_rows - offset)))
+            .collect();
+        crate::debug_log!(
+            "Streaming {} aggregation chunks of up to {} rows, {} at a time",
+            ranges.len(),
+            chunk_size,
+            concurrency
+        );
 
-            eprintln!(
-                "DEBUG: Aggregation chunk: offset={}, got {} rows",
-                offset, chunk_rows
-            );
+        let (streamer, columns) = (&streamer, &columns);
+        let chunks: Vec<polars::frame::DataFrame> = stream::iter(ranges)
+            .map(|(offset, limit)| async move {
+                let tson_data = with_retry_from_env(|| {
+                    streamer.stream_tson(
+                        &self.main_table_id,
+                        Some(columns.clone()),
+                        offset as i64,
+                        limit as i64,
+                    )
+                })
+                .await?;
+                let chunk_df = if tson_data.is_empty() {
+                    polars::frame::DataFrame::empty()
+                } else {
+                    tson_to_dataframe(&tson_data)?
+                };
+                crate::debug_log!(
+                    "Aggregation chunk: offset={}, got {} rows",
+                    offset,
+                    chunk_df.height()
+                );
+                Ok::<_, Box<dyn std::error::Error>>(chunk_df)
+            })
+            .buffered(concurrency)
+            .try_collect()
+            .await?;
 
-            accumulated_dfs.push(chunk_df);
-            offset += chunk_rows;
-        }
+        let accumulated_dfs: Vec<polars::frame::DataFrame> =
+            chunks.into_iter().filter(|df| df.height() > 0).collect();
+        let offset: usize = accumulated_dfs.iter().map(|df| df.height()).sum();
 
-        eprintln!(
-            "DEBUG: Accumulated {} chunks with {} total rows",
+        crate::debug_log!(
+            "Accumulated {} chunks with {} total rows",
             accumulated_dfs.len(),
             offset
         );
@@ -854,39 +3250,49 @@ impl TercenStreamGenerator {
             .collect()?
         };
 
-        eprintln!("DEBUG: Combined DataFrame has {} rows", all_data.height());
+        crate::debug_log!("Combined DataFrame has {} rows", all_data.height());
 
         // Group by .ci and .ri, aggregate based on configured method
         let ci_col = col(".ci");
         let ri_col = col(".ri");
 
-        eprintln!(
-            "DEBUG: Using heatmap cell aggregation: {:?}",
+        crate::debug_log!(
+            "Using heatmap cell aggregation: {:?}",
             self.heatmap_cell_aggregation
         );
 
         // Build aggregation expressions for color factors based on configured method
         // .colorLevels is shared by all categorical factors - only aggregate once
+        let use_mode = self.heatmap_cell_aggregation == HeatmapCellAggregation::Mode;
         let mut agg_exprs: Vec<Expr> = Vec::new();
         let mut has_color_levels_agg = false;
         for color_info in &self.color_infos {
             match &color_info.mapping {
                 tercen_rs::ColorMapping::Categorical(_) => {
                     if !has_color_levels_agg {
-                        // Categorical always uses last (mean/median don't make sense)
-                        let expr = col(".colorLevels").last();
-                        agg_exprs.push(expr.alias(".colorLevels"));
+                        // Categorical uses last (mean/median don't make sense),
+                        // except in Mode where it is computed separately below
+                        if !use_mode {
+                            let expr = col(".colorLevels").last();
+                            agg_exprs.push(expr.alias(".colorLevels"));
+                        }
                         has_color_levels_agg = true;
                     }
                 }
                 tercen_rs::ColorMapping::Continuous(_) => {
                     // For continuous colors, use the configured aggregation method
                     let col_name = &color_info.factor_name;
-                    let expr = match self.heatmap_cell_aggregation {
+                    let expr = match &self.heatmap_cell_aggregation {
                         HeatmapCellAggregation::Last => col(col_name).last(),
                         HeatmapCellAggregation::First => col(col_name).first(),
-                        HeatmapCellAggregation::Mean => col(col_name).mean(),
+                        HeatmapCellAggregation::Mean | HeatmapCellAggregation::Mode => {
+                            col(col_name).mean()
+                        }
                         HeatmapCellAggregation::Median => col(col_name).median(),
+                        HeatmapCellAggregation::WeightedMean { .. } => match &weight_column {
+                            Some(weight) => Self::weighted_mean_expr(col_name, weight),
+                            None => col(col_name).mean(),
+                        },
                     };
                     agg_exprs.push(expr.alias(col_name));
                 }
@@ -894,24 +3300,58 @@ impl TercenStreamGenerator {
         }
 
         // Perform the aggregation
-        let aggregated = all_data
+        let mut aggregated = all_data
+            .clone()
             .lazy()
             .group_by([ci_col, ri_col])
             .agg(agg_exprs)
             .collect()?;
 
-        eprintln!(
-            "DEBUG: Aggregated heatmap data: {} rows (from {} raw rows)",
+        // Mode: most frequent .colorLevels per cell, joined back onto the aggregated cells
+        if use_mode && has_color_levels_agg {
+            let modal_levels = Self::modal_color_levels(all_data)?;
+            aggregated = aggregated
+                .lazy()
+                .join(
+                    modal_levels.lazy(),
+                    [col(".ci"), col(".ri")],
+                    [col(".ci"), col(".ri")],
+                    JoinArgs::new(JoinType::Left),
+                )
+                .collect()?;
+        }
+
+        crate::debug_log!(
+            "Aggregated heatmap data: {} rows (from {} raw rows)",
             aggregated.height(),
             offset
         );
 
+        // Record the displayed value range of each continuous factor (for legend.range = data)
+        {
+            let mut ranges = self.heatmap_value_ranges.write().unwrap();
+            for color_info in &self.color_infos {
+                if !matches!(color_info.mapping, tercen_rs::ColorMapping::Continuous(_)) {
+                    continue;
+                }
+                let values = aggregated
+                    .column(&color_info.factor_name)?
+                    .cast(&DataType::Float64)?;
+                let values = values.f64()?;
+                if let (Some(min), Some(max)) = (values.min(), values.max()) {
+                    ranges.insert(color_info.factor_name.clone(), (min, max));
+                }
+            }
+            crate::debug_log!("Aggregated continuous value ranges: {:?}", *ranges);
+        }
+
         // Add color columns to the aggregated data
         let result = if !self.color_infos.is_empty() {
-            eprintln!("DEBUG: Adding color columns to aggregated data");
-            let colored =
-                tercen_rs::color_processor::add_color_columns(aggregated, &self.color_infos)?;
-            eprintln!("DEBUG: Color columns added to aggregated data");
+            crate::debug_log!("Adding color columns to aggregated data");
+            let colored = self.add_color_columns_checked(aggregated)?;
+            let colored = self.check_color_levels(colored)?;
+            let colored = self.recolor_categorical_levels(colored)?;
+            crate::debug_log!("Color columns added to aggregated data");
             colored
         } else {
             aggregated
@@ -920,6 +3360,49 @@ impl TercenStreamGenerator {
         Ok(ggrs_core::data::DataFrame::from_polars(result))
     }
 
+    /// Weighted mean of `value` by `weight` within a group
+    ///
+    /// Rows with a missing value or weight do not count. Groups whose weights sum
+    /// to zero (or less) fall back to the plain mean of `value`.
+    fn weighted_mean_expr(value: &str, weight: &str) -> polars::prelude::Expr {
+        use polars::prelude::*;
+
+        let value_col = col(value).cast(DataType::Float64);
+        let weight_col = when(value_col.clone().is_not_null())
+            .then(col(weight).cast(DataType::Float64).fill_null(lit(0.0)))
+            .otherwise(lit(0.0));
+        let weight_sum = weight_col.clone().sum();
+        when(weight_sum.clone().gt(lit(0.0)))
+            .then((value_col.clone() * weight_col).sum() / weight_sum)
+            .otherwise(value_col.mean())
+    }
+
+    /// Compute the most frequent `.colorLevels` value per (ci, ri) cell
+    ///
+    /// Counts occurrences of each level per cell, then keeps the level with the highest
+    /// count. Ties are broken by the lowest level index so the result does not depend
+    /// on streaming order.
+    fn modal_color_levels(
+        data: polars::frame::DataFrame,
+    ) -> Result<polars::frame::DataFrame, Box<dyn std::error::Error>> {
+        use polars::prelude::*;
+
+        let modal = data
+            .lazy()
+            .group_by([col(".ci"), col(".ri"), col(".colorLevels")])
+            .agg([len().alias(".levelCount")])
+            .sort(
+                [".levelCount", ".colorLevels"],
+                SortMultipleOptions::default().with_order_descending_multi([true, false]),
+            )
+            .group_by([col(".ci"), col(".ri")])
+            .agg([col(".colorLevels").first().alias(".colorLevels")])
+            .collect()?;
+
+        crate::debug_log!("Modal .colorLevels computed for {} cells", modal.height());
+        Ok(modal)
+    }
+
     /// Load axis ranges from pre-computed Y-axis table
     ///
     /// The Y-axis table contains columns: .ri, .minY, .maxY (and optionally .ci)
@@ -933,17 +3416,18 @@ impl TercenStreamGenerator {
     ) -> Result<
         (
             HashMap<(usize, usize), (AxisData, AxisData)>,
-            usize, // total rows across all facets
+            usize,       // total rows across all facets
+            Vec<String>, // main table column names
         ),
         Box<dyn std::error::Error>,
     > {
         let streamer = Self::create_streamer(client, schema_cache);
 
         // First, get the schema to see which columns exist
-        println!("  Fetching Y-axis table schema...");
-        let schema = streamer.get_schema(y_axis_table_id).await?;
+        crate::info_log!("  Fetching Y-axis table schema...");
+        let schema = with_retry_from_env(|| streamer.get_schema(y_axis_table_id)).await?;
         let column_names = extract_column_names_from_schema(&schema)?;
-        println!("  Y-axis table columns: {:?}", column_names);
+        crate::info_log!("  Y-axis table columns: {:?}", column_names);
 
         // Build column list: always need .minY, .maxY
         // Optionally include .ri (for per-row ranges) and .ci (for per-cell ranges)
@@ -969,45 +3453,57 @@ impl TercenStreamGenerator {
 
         // Log what kind of range we're dealing with
         if !has_ri && !has_ci {
-            println!("  Global axis range (single row, applies to all facets)");
+            crate::info_log!("  Global axis range (single row, applies to all facets)");
         } else if !has_ri {
-            println!("  Per-column axis range (no .ri, applies to all rows)");
+            crate::info_log!("  Per-column axis range (no .ri, applies to all rows)");
         } else if !has_ci {
-            println!("  Per-row axis range (no .ci, applies to all columns)");
+            crate::info_log!("  Per-row axis range (no .ci, applies to all columns)");
         } else {
-            println!("  Per-cell axis range (both .ri and .ci)");
+            crate::info_log!("  Per-cell axis range (both .ri and .ci)");
         }
 
         // Fetch all rows from Y-axis table
         // Note: Y-axis table has one row per row facet (indexed by .ri only)
         // Not one row per cell (col × row) because Y ranges are per row
         let expected_rows = facet_info.n_row_facets();
-        println!(
+        crate::info_log!(
             "  Fetching Y-axis ranges (expecting {} rows - one per row facet)...",
             expected_rows
         );
-        let data = streamer
-            .stream_tson(
+        // Fetch every row the table has, so extra rows are validated below rather
+        // than cut off
+        let table_rows = extract_row_count_from_schema(&schema)? as usize;
+        if table_rows != expected_rows {
+            crate::warn_log!(
+                "Y-axis table has {} rows, expected {} (one per row facet)",
+                table_rows,
+                expected_rows
+            );
+        }
+        let data = with_retry_from_env(|| {
+            streamer.stream_tson(
                 y_axis_table_id,
-                Some(columns_to_fetch),
+                Some(columns_to_fetch.clone()),
                 0,
-                expected_rows as i64,
+                table_rows.max(expected_rows) as i64,
             )
-            .await?;
+        })
+        .await?;
 
-        println!("  Parsing {} bytes...", data.len());
+        crate::info_log!("  Parsing {} bytes...", data.len());
         let df = ggrs_core::data::DataFrame::from_polars(tson_to_dataframe(&data)?);
-        println!("  Parsed {} rows", df.nrow());
+        crate::info_log!("  Parsed {} rows", df.nrow());
 
         // Get total row count from main table schema
-        println!("  Getting main table row count...");
-        let main_schema = streamer.get_schema(main_table_id).await?;
+        crate::info_log!("  Getting main table row count...");
+        let main_schema = with_retry_from_env(|| streamer.get_schema(main_table_id)).await?;
         let main_columns = extract_column_names_from_schema(&main_schema)?;
-        eprintln!("DEBUG: Main data table columns: {:?}", main_columns);
+        crate::debug_log!("Main data table columns: {:?}", main_columns);
         let total_rows = extract_row_count_from_schema(&main_schema)? as usize;
-        println!("  Total rows: {}", total_rows);
+        crate::info_log!("  Total rows: {}", total_rows);
 
         let mut axis_ranges = HashMap::new();
+        let mut seen_rows: HashMap<(usize, usize), usize> = HashMap::new();
         let has_ci = df.columns().contains(&".ci".to_string());
         let has_ri = df.columns().contains(&".ri".to_string());
         let has_x_range = df.columns().contains(&".minX".to_string())
@@ -1016,19 +3512,13 @@ impl TercenStreamGenerator {
         // Process each row in Y-axis table
         for i in 0..df.nrow() {
             let col_idx = if has_ci {
-                match df.get_value(i, ".ci")? {
-                    ggrs_core::data::Value::Int(v) => v as usize,
-                    _ => return Err(format!("Invalid .ci at row {}", i).into()),
-                }
+                Self::table_index(&df, i, ".ci")?
             } else {
                 0 // Will replicate to all columns below
             };
 
             let row_idx = if has_ri {
-                let row_idx_from_table = match df.get_value(i, ".ri")? {
-                    ggrs_core::data::Value::Int(v) => v as usize,
-                    _ => return Err(format!("Invalid .ri at row {}", i).into()),
-                };
+                let row_idx_from_table = Self::table_index(&df, i, ".ri")?;
 
                 // Map table's .ri (filtered index 0-11) to original index (12-23 for page 2)
                 // This is necessary because the Y-axis table from Tercen is pre-filtered by page
@@ -1049,36 +3539,34 @@ impl TercenStreamGenerator {
                 0 // Will replicate to all rows below
             };
 
-            let min_y = match df.get_value(i, ".minY")? {
-                ggrs_core::data::Value::Float(v) => v,
-                _ => return Err(format!("Invalid .minY at row {}", i).into()),
-            };
-
-            let max_y = match df.get_value(i, ".maxY")? {
-                ggrs_core::data::Value::Float(v) => v,
-                _ => return Err(format!("Invalid .maxY at row {}", i).into()),
-            };
+            // A single unique value gives an empty range: pad it so the value
+            // renders centered instead of dequantizing to NaN
+            let (min_y, max_y) = pad_zero_width(
+                Self::table_f64(&df, i, ".minY")?,
+                Self::table_f64(&df, i, ".maxY")?,
+            );
 
             // X-axis: use from Y-axis table if available, otherwise will compute from data later
             let (min_x, max_x) = if has_x_range {
-                let min_x = match df.get_value(i, ".minX")? {
-                    ggrs_core::data::Value::Float(v) => v,
-                    _ => return Err(format!("Invalid .minX at row {}", i).into()),
-                };
-                let max_x = match df.get_value(i, ".maxX")? {
-                    ggrs_core::data::Value::Float(v) => v,
-                    _ => return Err(format!("Invalid .maxX at row {}", i).into()),
-                };
-                (min_x, max_x)
+                pad_zero_width(
+                    Self::table_f64(&df, i, ".minX")?,
+                    Self::table_f64(&df, i, ".maxX")?,
+                )
             } else {
                 // X range not in Y-axis table - use placeholder, will compute from data
                 // This matches R plot_operator behavior which computes range from actual .x values
                 (f64::NAN, f64::NAN)
             };
 
-            println!(
+            crate::info_log!(
                 "  Range row {}: ci={}, ri={}, X [{}, {}], Y [{}, {}]",
-                i, col_idx, row_idx, min_x, max_x, min_y, max_y
+                i,
+                col_idx,
+                row_idx,
+                min_x,
+                max_x,
+                min_y,
+                max_y
             );
 
             let x_axis = AxisData::Numeric(NumericAxisData {
@@ -1097,38 +3585,167 @@ impl TercenStreamGenerator {
                 transform: None,
             });
 
+            // Duplicate rows for the same .ci/.ri are merged into the union of their
+            // ranges (each distinct row writes a disjoint set of cells)
+            if let Some(first) = seen_rows.insert((col_idx, row_idx), i) {
+                crate::warn_log!(
+                    "Y-axis table rows {} and {} share ci={}, ri={} - merging their ranges",
+                    first,
+                    i,
+                    col_idx,
+                    row_idx
+                );
+            }
+            let mut insert = |key: (usize, usize)| {
+                Self::insert_range_union(&mut axis_ranges, key, &x_axis, &y_axis);
+            };
+
             // Replicate range based on which index columns are present
             match (has_ci, has_ri) {
                 (true, true) => {
                     // Per-cell range
-                    axis_ranges.insert((col_idx, row_idx), (x_axis.clone(), y_axis.clone()));
+                    insert((col_idx, row_idx));
                 }
                 (false, true) => {
                     // Per-row range: replicate to all columns
-                    for col in 0..facet_info.n_col_facets() {
-                        axis_ranges.insert((col, row_idx), (x_axis.clone(), y_axis.clone()));
+                    for col in 0..facet_info.n_col_facets().max(1) {
+                        insert((col, row_idx));
                     }
                 }
                 (true, false) => {
                     // Per-column range: replicate to all rows
-                    for row in 0..facet_info.n_row_facets() {
-                        axis_ranges.insert((col_idx, row), (x_axis.clone(), y_axis.clone()));
+                    for row in 0..facet_info.n_row_facets().max(1) {
+                        insert((col_idx, row));
                     }
                 }
                 (false, false) => {
-                    // Global range: replicate to all cells
-                    for col in 0..facet_info.n_col_facets() {
-                        for row in 0..facet_info.n_row_facets() {
-                            axis_ranges.insert((col, row), (x_axis.clone(), y_axis.clone()));
+                    // Global range: replicate to all cells (at least one, even
+                    // when only one facet axis exists)
+                    for col in 0..facet_info.n_col_facets().max(1) {
+                        for row in 0..facet_info.n_row_facets().max(1) {
+                            insert((col, row));
+                        }
+                    }
+                }
+            }
+        }
+
+        // Every row facet needs a range: report the missing ones now and give them the
+        // global range, instead of failing when the facet is drawn
+        if has_ri {
+            let covered: std::collections::HashSet<usize> =
+                axis_ranges.keys().map(|&(_, ri)| ri).collect();
+            let missing: Vec<usize> = facet_info
+                .row_facets
+                .groups
+                .iter()
+                .map(|g| g.original_index)
+                .filter(|ri| !covered.contains(ri))
+                .collect();
+            if !missing.is_empty() {
+                crate::warn_log!(
+                    "Y-axis table has ranges for {} of {} row facets - row facets {:?} (original index) use the global range",
+                    expected_rows - missing.len(),
+                    expected_rows,
+                    missing
+                );
+                let global_y = Self::union_numeric_axes(axis_ranges.values().map(|(_, y)| y));
+                // X placeholder (NaN) when the table has no X range, computed from data later
+                let global_x = Self::union_numeric_axes(axis_ranges.values().map(|(x, _)| x))
+                    .unwrap_or_else(|| {
+                        AxisData::Numeric(NumericAxisData {
+                            min_value: f64::NAN,
+                            max_value: f64::NAN,
+                            min_axis: f64::NAN,
+                            max_axis: f64::NAN,
+                            transform: None,
+                        })
+                    });
+                match global_y {
+                    Some(global_y) => {
+                        for &row in &missing {
+                            for col in 0..facet_info.n_col_facets().max(1) {
+                                axis_ranges
+                                    .insert((col, row), (global_x.clone(), global_y.clone()));
+                            }
                         }
                     }
+                    None => crate::warn_log!("No finite Y range to fall back to"),
                 }
             }
         }
 
-        println!("  Loaded {} axis ranges", axis_ranges.len());
-        Ok((axis_ranges, total_rows))
+        crate::info_log!("  Loaded {} axis ranges", axis_ranges.len());
+        Ok((axis_ranges, total_rows, main_columns))
+    }
+    /// Numeric value of an axis table cell as f64
+    ///
+    /// Integer-typed tables (e.g. count data) give `Value::Int`, others `Value::Float`.
+    fn table_f64(
+        df: &ggrs_core::data::DataFrame,
+        row: usize,
+        column: &str,
+    ) -> Result<f64, Box<dyn std::error::Error>> {
+        match df.get_value(row, column)? {
+            ggrs_core::data::Value::Float(v) => Ok(v),
+            ggrs_core::data::Value::Int(v) => Ok(v as f64),
+            _ => Err(format!("Invalid {} at row {}", column, row).into()),
+        }
+    }
+
+    /// Facet index (`.ci`/`.ri`) of an axis table cell, stored as Int or whole Float
+    fn table_index(
+        df: &ggrs_core::data::DataFrame,
+        row: usize,
+        column: &str,
+    ) -> Result<usize, Box<dyn std::error::Error>> {
+        match df.get_value(row, column)? {
+            ggrs_core::data::Value::Int(v) if v >= 0 => Ok(v as usize),
+            ggrs_core::data::Value::Float(v) if v >= 0.0 && v.fract() == 0.0 => Ok(v as usize),
+            _ => Err(format!("Invalid {} at row {}", column, row).into()),
+        }
+    }
+
+    /// Insert a cell range, merging with an existing entry into the union of both
+    ///
+    /// Non-finite (placeholder) ranges do not widen the union.
+    fn insert_range_union(
+        axis_ranges: &mut HashMap<(usize, usize), (AxisData, AxisData)>,
+        key: (usize, usize),
+        x_axis: &AxisData,
+        y_axis: &AxisData,
+    ) {
+        let merged = match axis_ranges.get(&key) {
+            Some((x, y)) => (
+                Self::union_numeric_axes([x, x_axis].into_iter()).unwrap_or_else(|| x_axis.clone()),
+                Self::union_numeric_axes([y, y_axis].into_iter()).unwrap_or_else(|| y_axis.clone()),
+            ),
+            None => (x_axis.clone(), y_axis.clone()),
+        };
+        axis_ranges.insert(key, merged);
+    }
+
+    /// Local functions of an axis transform spec (Identity when there is none)
+    fn value_transform(spec: Option<&str>) -> Result<Option<ValueTransform>, String> {
+        spec.map_or(Ok(Some(ValueTransform::Identity)), ValueTransform::parse)
+    }
+
+    /// Set each numeric axis' transform; X and Y are independent (None = linear)
+    fn apply_axis_transforms(
+        axis_ranges: &mut HashMap<(usize, usize), (AxisData, AxisData)>,
+        x_transform: &Option<Transform>,
+        y_transform: &Option<Transform>,
+    ) {
+        for (x_axis, y_axis) in axis_ranges.values_mut() {
+            if let AxisData::Numeric(ref mut num) = y_axis {
+                num.transform = y_transform.clone();
+            }
+            if let AxisData::Numeric(ref mut num) = x_axis {
+                num.transform = x_transform.clone();
+            }
+        }
     }
+
     /// Compute X-axis ranges by scanning the main data table
     /// Set sequential X ranges when no X-axis table exists
     ///
@@ -1138,9 +3755,8 @@ impl TercenStreamGenerator {
         n_rows: f64,
         axis_ranges: &mut HashMap<(usize, usize), (AxisData, AxisData)>,
     ) {
-        // Sequential X range: 1 to n_rows (1-indexed)
-        let min_x = 1.0;
-        let max_x = n_rows;
+        // Sequential X range: 1 to n_rows (1-indexed), padded for a single row
+        let (min_x, max_x) = pad_zero_width(1.0, n_rows);
 
         // Update all facet cells with the same sequential range
         for (x_axis, _y_axis) in axis_ranges.values_mut() {
@@ -1154,24 +3770,335 @@ impl TercenStreamGenerator {
         }
     }
 
+    /// Collect the sorted distinct levels of a categorical X factor
+    ///
+    /// Scans the factor column of the main table in chunks.
+    async fn load_x_categories(
+        client: &TercenClient,
+        schema_cache: &Option<SchemaCache>,
+        main_table_id: &str,
+        factor: &str,
+        total_rows: usize,
+    ) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        use polars::prelude::*;
+
+        const CHUNK_SIZE: usize = 50_000;
+        let streamer = Self::create_streamer(client, schema_cache);
+        let columns = vec![factor.to_string()];
+        let mut levels = std::collections::BTreeSet::new();
+        let mut offset = 0usize;
+
+        while offset < total_rows {
+            let limit = (total_rows - offset).min(CHUNK_SIZE);
+            let tson_data = with_retry_from_env(|| {
+                streamer.stream_tson(
+                    main_table_id,
+                    Some(columns.clone()),
+                    offset as i64,
+                    limit as i64,
+                )
+            })
+            .await?;
+            if tson_data.is_empty() {
+                break;
+            }
+
+            let chunk_df = tson_to_dataframe(&tson_data)?;
+            if chunk_df.height() == 0 {
+                break;
+            }
+            let values = chunk_df.column(factor)?.cast(&DataType::String)?;
+            levels.extend(values.str()?.into_iter().flatten().map(|v| v.to_string()));
+            offset += chunk_df.height();
+        }
+
+        Ok(levels.into_iter().collect())
+    }
+
+    /// Use a categorical X axis in every facet cell
+    fn set_categorical_x_ranges(
+        categories: &[String],
+        axis_ranges: &mut HashMap<(usize, usize), (AxisData, AxisData)>,
+    ) {
+        for (x_axis, _y_axis) in axis_ranges.values_mut() {
+            *x_axis = AxisData::Categorical(CategoricalAxisData {
+                categories: categories.to_vec(),
+            });
+        }
+    }
+
+    /// Ensure every facet cell has a usable range when the main table is empty
+    ///
+    /// Missing cells and numeric ranges that are non-finite or degenerate
+    /// (e.g. the sequential 1..0 range) are replaced with (0, 1).
+    fn fill_empty_axis_ranges(
+        facet_info: &FacetInfo,
+        axis_ranges: &mut HashMap<(usize, usize), (AxisData, AxisData)>,
+    ) {
+        let unit = || {
+            AxisData::Numeric(NumericAxisData {
+                min_value: 0.0,
+                max_value: 1.0,
+                min_axis: 0.0,
+                max_axis: 1.0,
+                transform: None,
+            })
+        };
+        let is_usable = |axis: &AxisData| match axis {
+            AxisData::Numeric(num) => {
+                num.min_value.is_finite()
+                    && num.max_value.is_finite()
+                    && num.min_value < num.max_value
+            }
+            AxisData::Categorical(_) => true,
+        };
+
+        let col_indices: Vec<usize> = if facet_info.col_facets.groups.is_empty() {
+            vec![0]
+        } else {
+            facet_info
+                .col_facets
+                .groups
+                .iter()
+                .map(|g| g.original_index)
+                .collect()
+        };
+        let row_indices: Vec<usize> = if facet_info.row_facets.groups.is_empty() {
+            vec![0]
+        } else {
+            facet_info
+                .row_facets
+                .groups
+                .iter()
+                .map(|g| g.original_index)
+                .collect()
+        };
+
+        for &ci in &col_indices {
+            for &ri in &row_indices {
+                let (x_axis, y_axis) = axis_ranges
+                    .entry((ci, ri))
+                    .or_insert_with(|| (unit(), unit()));
+                if !is_usable(x_axis) {
+                    *x_axis = unit();
+                }
+                if !is_usable(y_axis) {
+                    *y_axis = unit();
+                }
+            }
+        }
+    }
+
+    /// Union of all numeric cell ranges, used as the fallback for missing cells
+    ///
+    /// Falls back to (0, 1) for an axis with no finite numeric range at all.
+    fn global_axis_range(
+        axis_ranges: &HashMap<(usize, usize), (AxisData, AxisData)>,
+    ) -> (AxisData, AxisData) {
+        let unit = || {
+            AxisData::Numeric(NumericAxisData {
+                min_value: 0.0,
+                max_value: 1.0,
+                min_axis: 0.0,
+                max_axis: 1.0,
+                transform: None,
+            })
+        };
+        let x = Self::union_numeric_axes(axis_ranges.values().map(|(x, _)| x)).unwrap_or_else(unit);
+        let y = Self::union_numeric_axes(axis_ranges.values().map(|(_, y)| y)).unwrap_or_else(unit);
+        (x, y)
+    }
+
+    /// Union of the finite numeric ranges in `axes` (None if there are none)
+    ///
+    /// The transform of the first numeric axis is kept.
+    fn union_numeric_axes<'a>(axes: impl Iterator<Item = &'a AxisData>) -> Option<AxisData> {
+        let mut union: Option<NumericAxisData> = None;
+        for axis in axes {
+            let AxisData::Numeric(num) = axis else {
+                continue;
+            };
+            if !num.min_value.is_finite() || !num.max_value.is_finite() {
+                continue;
+            }
+            match union {
+                Some(ref mut u) => {
+                    u.min_value = u.min_value.min(num.min_value);
+                    u.max_value = u.max_value.max(num.max_value);
+                    u.min_axis = u.min_axis.min(num.min_axis);
+                    u.max_axis = u.max_axis.max(num.max_axis);
+                }
+                None => union = Some(num.clone()),
+            }
+        }
+        union.map(AxisData::Numeric)
+    }
+
+    /// Synthesize an axis range for a facet cell missing from `axis_ranges`
+    ///
+    /// Uses the union of the cells sharing the axis (same column for X, same row
+    /// for Y), then the global range. Panics in strict mode.
+    fn fallback_axis(
+        &self,
+        col_idx: usize,
+        row_idx: usize,
+        original_col_idx: usize,
+        original_row_idx: usize,
+        is_x: bool,
+    ) -> AxisData {
+        let axis_name = if is_x { "X" } else { "Y" };
+        if self.strict_axis_ranges {
+            panic!(
+                "No {}-axis range for cell ({}, {}) [original: ({}, {})]. \
+                axis_ranges has {} entries. This indicates missing axis range data.",
+                axis_name,
+                col_idx,
+                row_idx,
+                original_col_idx,
+                original_row_idx,
+                self.axis_ranges.len()
+            );
+        }
+
+        let neighbors = self.axis_ranges.iter().filter_map(|(&(ci, ri), (x, y))| {
+            if is_x && ci == original_col_idx {
+                Some(x)
+            } else if !is_x && ri == original_row_idx {
+                Some(y)
+            } else {
+                None
+            }
+        });
+        let (axis, source) = match Self::union_numeric_axes(neighbors) {
+            Some(axis) => (axis, if is_x { "same column" } else { "same row" }),
+            None => {
+                let global = if is_x {
+                    &self.global_axis_range.0
+                } else {
+                    &self.global_axis_range.1
+                };
+                (global.clone(), "all cells")
+            }
+        };
+
+        crate::warn_log!(
+            "No {}-axis range for cell ({}, {}) [original: ({}, {})], using range from {}",
+            axis_name,
+            col_idx,
+            row_idx,
+            original_col_idx,
+            original_row_idx,
+            source
+        );
+        axis
+    }
+
+    /// Override the displayed axis range of every facet cell
+    ///
+    /// Only `min_axis`/`max_axis` are changed; `min_value`/`max_value` keep the
+    /// axis table range so quantized coordinates still dequantize correctly.
+    fn apply_axis_limits(
+        axis_ranges: &mut HashMap<(usize, usize), (AxisData, AxisData)>,
+        x_limits: Option<(f64, f64)>,
+        y_limits: Option<(f64, f64)>,
+    ) {
+        for (x_axis, y_axis) in axis_ranges.values_mut() {
+            if let (Some((min, max)), AxisData::Numeric(ref mut num)) = (x_limits, x_axis) {
+                num.min_axis = min;
+                num.max_axis = max;
+            }
+            if let (Some((min, max)), AxisData::Numeric(ref mut num)) = (y_limits, y_axis) {
+                num.min_axis = min;
+                num.max_axis = max;
+            }
+        }
+    }
+
     /// Load X-axis ranges from pre-computed X-axis table
     ///
     /// The X-axis table contains columns: .ci, .ticks, .minX, .maxX
     /// There should be one row per column facet (indexed by .ci)
+    ///
+    /// When a range cache is provided, the parsed rows are stored per table ID so
+    /// later pages reuse them instead of re-streaming and re-decoding the table.
     async fn load_x_ranges_from_table(
         client: &TercenClient,
         x_axis_table_id: &str,
         facet_info: &FacetInfo,
         axis_ranges: &mut HashMap<(usize, usize), (AxisData, AxisData)>,
         schema_cache: &Option<SchemaCache>,
+        range_cache: &Option<RangeCache>,
     ) -> Result<(), Box<dyn std::error::Error>> {
+        let x_ranges = cached_x_ranges(range_cache, x_axis_table_id, || {
+            Self::fetch_x_ranges(client, x_axis_table_id, facet_info, schema_cache)
+        })
+        .await?;
+
+        for (&col_idx_from_table, &(min_x, max_x)) in &x_ranges.ranges {
+            let (min_x, max_x) = pad_zero_width(min_x, max_x);
+            let x_axis = AxisData::Numeric(NumericAxisData {
+                min_value: min_x,
+                max_value: max_x,
+                min_axis: min_x,
+                max_axis: max_x,
+                transform: None,
+            });
+
+            // Update axis_ranges based on whether we have per-column or global range
+            if x_ranges.per_column {
+                // Map table's .ci (filtered index) to original index
+                let col_idx = facet_info
+                    .col_facets
+                    .groups
+                    .get(col_idx_from_table)
+                    .map(|g| g.original_index)
+                    .ok_or_else(|| {
+                        format!(
+                            "X-axis table has .ci={} but FacetInfo only has {} col groups",
+                            col_idx_from_table,
+                            facet_info.col_facets.groups.len()
+                        )
+                    })?;
+
+                // Per-column range: update all rows for this column (row 0 when
+                // there are no row facets)
+                for row_idx in 0..facet_info.n_row_facets().max(1) {
+                    let row_original_idx = facet_info
+                        .row_facets
+                        .groups
+                        .get(row_idx)
+                        .map_or(0, |g| g.original_index);
+
+                    if let Some((x, _)) = axis_ranges.get_mut(&(col_idx, row_original_idx)) {
+                        *x = x_axis.clone();
+                    }
+                }
+            } else {
+                // Global range: update all cells
+                for (_, (x, _)) in axis_ranges.iter_mut() {
+                    *x = x_axis.clone();
+                }
+            }
+        }
+
+        crate::info_log!("  Loaded X-axis ranges from table");
+        Ok(())
+    }
+
+    /// Stream and parse the X-axis table into per-`.ci` ranges
+    async fn fetch_x_ranges(
+        client: &TercenClient,
+        x_axis_table_id: &str,
+        facet_info: &FacetInfo,
+        schema_cache: &Option<SchemaCache>,
+    ) -> Result<CachedXRanges, Box<dyn std::error::Error>> {
         let streamer = Self::create_streamer(client, schema_cache);
 
         // Fetch the X-axis table schema
-        println!("  Fetching X-axis table schema...");
-        let schema = streamer.get_schema(x_axis_table_id).await?;
+        crate::info_log!("  Fetching X-axis table schema...");
+        let schema = with_retry_from_env(|| streamer.get_schema(x_axis_table_id)).await?;
         let column_names = extract_column_names_from_schema(&schema)?;
-        println!("  X-axis table columns: {:?}", column_names);
+        crate::info_log!("  X-axis table columns: {:?}", column_names);
 
         // Check for required columns
         let has_ci = column_names.contains(&".ci".to_string());
@@ -1190,129 +4117,135 @@ impl TercenStreamGenerator {
 
         // Log the range type
         if has_ci {
-            println!("  Per-column X-axis range (indexed by .ci)");
+            crate::info_log!("  Per-column X-axis range (indexed by .ci)");
         } else {
-            println!("  Global X-axis range (single row, applies to all columns)");
+            crate::info_log!("  Global X-axis range (single row, applies to all columns)");
         }
 
         // Fetch all rows from X-axis table
         let expected_rows = facet_info.n_col_facets();
-        println!(
+        crate::info_log!(
             "  Fetching X-axis ranges (expecting {} rows - one per col facet)...",
             expected_rows
         );
-        let data = streamer
-            .stream_tson(
+        let data = with_retry_from_env(|| {
+            streamer.stream_tson(
                 x_axis_table_id,
-                Some(columns_to_fetch),
+                Some(columns_to_fetch.clone()),
                 0,
                 expected_rows as i64,
             )
-            .await?;
+        })
+        .await?;
 
-        println!("  Parsing {} bytes...", data.len());
+        crate::info_log!("  Parsing {} bytes...", data.len());
         let df = ggrs_core::data::DataFrame::from_polars(tson_to_dataframe(&data)?);
-        println!("  Parsed {} rows", df.nrow());
+        crate::info_log!("  Parsed {} rows", df.nrow());
 
         let has_ci = df.columns().contains(&".ci".to_string());
+        let mut ranges = HashMap::new();
 
         // Process each row in X-axis table
         for i in 0..df.nrow() {
             let col_idx = if has_ci {
-                let col_idx_from_table = match df.get_value(i, ".ci")? {
-                    ggrs_core::data::Value::Int(v) => v as usize,
-                    _ => return Err(format!("Invalid .ci at row {}", i).into()),
-                };
-
-                // Map table's .ci (filtered index) to original index
-                facet_info
-                    .col_facets
-                    .groups
-                    .get(col_idx_from_table)
-                    .map(|g| g.original_index)
-                    .ok_or_else(|| {
-                        format!(
-                            "X-axis table row {} has .ci={} but FacetInfo only has {} col groups",
-                            i,
-                            col_idx_from_table,
-                            facet_info.col_facets.groups.len()
-                        )
-                    })?
+                Self::table_index(&df, i, ".ci")?
             } else {
-                0 // Will replicate to all columns below
+                0 // Global range
             };
 
-            let min_x = match df.get_value(i, ".minX")? {
-                ggrs_core::data::Value::Float(v) => v,
-                _ => return Err(format!("Invalid .minX at row {}", i).into()),
-            };
+            let min_x = Self::table_f64(&df, i, ".minX")?;
 
-            let max_x = match df.get_value(i, ".maxX")? {
-                ggrs_core::data::Value::Float(v) => v,
-                _ => return Err(format!("Invalid .maxX at row {}", i).into()),
-            };
+            let max_x = Self::table_f64(&df, i, ".maxX")?;
 
-            println!(
+            crate::info_log!(
                 "  X range row {}: ci={}, X [{}, {}]",
-                i, col_idx, min_x, max_x
+                i,
+                col_idx,
+                min_x,
+                max_x
             );
 
-            // Update axis_ranges based on whether we have per-column or global range
-            if has_ci {
-                // Per-column range: update all rows for this column
-                for row_idx in 0..facet_info.n_row_facets() {
-                    let row_original_idx = facet_info
-                        .row_facets
-                        .groups
-                        .get(row_idx)
-                        .expect("row_idx within n_row_facets() bounds must be valid")
-                        .original_index;
-
-                    if let Some((x_axis, _)) = axis_ranges.get_mut(&(col_idx, row_original_idx)) {
-                        *x_axis = AxisData::Numeric(NumericAxisData {
-                            min_value: min_x,
-                            max_value: max_x,
-                            min_axis: min_x,
-                            max_axis: max_x,
-                            transform: None,
-                        });
-                    }
-                }
-            } else {
-                // Global range: update all cells
-                for (_, (x_axis, _)) in axis_ranges.iter_mut() {
-                    *x_axis = AxisData::Numeric(NumericAxisData {
-                        min_value: min_x,
-                        max_value: max_x,
-                        min_axis: min_x,
-                        max_axis: max_x,
-                        transform: None,
-                    });
-                }
+            // Duplicate .ci rows: keep the union rather than the last row
+            if let Some((prev_min, prev_max)) = ranges.insert(col_idx, (min_x, max_x)) {
+                let merged = (prev_min.min(min_x), prev_max.max(max_x));
+                crate::warn_log!(
+                    "X-axis table has several rows for ci={} - merging [{}, {}] and [{}, {}] into [{}, {}]",
+                    col_idx, prev_min, prev_max, min_x, max_x, merged.0, merged.1
+                );
+                ranges.insert(col_idx, merged);
             }
         }
 
-        println!("  Loaded X-axis ranges from table");
-        Ok(())
+        Ok(CachedXRanges {
+            per_column: has_ci,
+            ranges,
+        })
     }
 
-    /// Create a generic legend for level-based colors
+    /// Create generic legend entries for level-based colors
     ///
     /// When we can't get actual category names, use generic labels: "Level 0", "Level 1", etc.
-    fn create_generic_level_legend(
-        factor_name: &str,
-    ) -> Result<LegendScale, Box<dyn std::error::Error>> {
-        let entries: Vec<(String, [u8; 3])> = (0..DEFAULT_PALETTE_LEVELS)
+    fn generic_level_entries(n_levels: usize) -> Vec<(String, [u8; 3])> {
+        (0..n_levels)
             .map(|i| {
                 let label = format!("Level {}", i);
                 let color = tercen_rs::categorical_color_from_level(i as i32);
                 (label, color)
             })
-            .collect();
-        Ok(LegendScale::Discrete {
-            entries,
-            aesthetic_name: factor_name.to_string(),
-        })
+            .collect()
+    }
+
+    /// Build discrete legend entries for a categorical color factor
+    ///
+    /// Prefers explicit label→color mappings, then color table labels, then
+    /// generic "Level N" labels from n_levels (or the default palette size).
+    fn categorical_legend_entries(
+        color_info: &tercen_rs::ColorInfo,
+        legend_name: &str,
+    ) -> Vec<(String, [u8; 3])> {
+        let tercen_rs::ColorMapping::Categorical(color_map) = &color_info.mapping else {
+            return Vec::new();
+        };
+
+        if !color_map.mappings.is_empty() {
+            // Explicit label→color mappings from palette
+            let mut entries: Vec<(String, [u8; 3])> = color_map
+                .mappings
+                .iter()
+                .map(|(label, color)| (label.clone(), *color))
+                .collect();
+            entries.sort_by(|a, b| a.0.cmp(&b.0));
+            entries
+        } else if let Some(ref labels) = color_info.color_labels {
+            // Use actual color labels from the color table with palette colors
+            crate::debug_log!(
+                "Using {} color labels from color table for '{}'",
+                labels.len(),
+                legend_name
+            );
+            labels
+                .iter()
+                .enumerate()
+                .map(|(i, label)| {
+                    let color = tercen_rs::categorical_color_from_level(i as i32);
+                    (label.clone(), color)
+                })
+                .collect()
+        } else if let Some(n_levels) = color_info.n_levels {
+            // Fallback: Use n_levels from color table schema with generic labels
+            crate::debug_log!(
+                "Using n_levels={} with generic labels for '{}' (no color_labels)",
+                n_levels,
+                legend_name
+            );
+            Self::generic_level_entries(n_levels)
+        } else {
+            // No explicit mappings and no n_levels - use default generic level labels
+            crate::debug_log!(
+                "No explicit mappings or n_levels, using default generic level labels"
+            );
+            Self::generic_level_entries(DEFAULT_PALETTE_LEVELS)
+        }
     }
 
     /// Load legend scale data during initialization
@@ -1321,6 +4254,10 @@ impl TercenStreamGenerator {
     /// For continuous colors, extracts the min/max from the palette.
     /// For mixed-layer scenarios (some layers with colors, some without),
     /// creates a combined legend with sections for each type.
+    ///
+    /// With several color factors, each continuous factor gets its own section
+    /// (duplicates with the same name and palette are dropped) and all categorical
+    /// factors share one discrete section, since they share `.colorLevels`.
     fn load_legend_scale(
         color_infos: &[tercen_rs::ColorInfo],
         per_layer_colors: Option<&tercen_rs::PerLayerColorConfig>,
@@ -1330,13 +4267,13 @@ impl TercenStreamGenerator {
         if let Some(plc) = per_layer_colors {
             if plc.is_mixed() {
                 // Mixed scenario: some layers have colors, some don't
-                eprintln!("DEBUG: Building combined legend for mixed-layer scenario");
+                crate::debug_log!("Building combined legend for mixed-layer scenario");
                 return Self::build_combined_legend(plc, layer_y_factor_names);
             }
 
             // Not mixed - if all layers have constant colors, create a discrete legend
             if plc.has_constant_colors() && !plc.has_explicit_colors() {
-                eprintln!("DEBUG: Building discrete legend for layer-based colors");
+                crate::debug_log!("Building discrete legend for layer-based colors");
                 return Self::build_layer_based_legend(plc, layer_y_factor_names);
             }
         }
@@ -1352,19 +4289,38 @@ impl TercenStreamGenerator {
             .filter(|ci| matches!(ci.mapping, tercen_rs::ColorMapping::Categorical(_)))
             .map(|ci| ci.factor_name.as_str())
             .collect();
-        let combined_name = if categorical_names.is_empty() {
-            color_infos[0].factor_name.clone()
-        } else {
-            categorical_names.join(", ")
-        };
+        let combined_name = categorical_names.join(", ");
 
-        // Only handle the first color factor for now
-        let color_info = &color_infos[0];
+        let mut sections: Vec<LegendSection> = Vec::new();
+        let mut seen_continuous: Vec<(String, Vec<(f64, [u8; 3])>)> = Vec::new();
+        let mut has_categorical_section = false;
+
+        for color_info in color_infos {
+            match &color_info.mapping {
+                tercen_rs::ColorMapping::Continuous(palette) => {
+                    // For continuous colors, get the min/max and color stops from the palette
+                    let Some((min_val, max_val)) = palette.range() else {
+                        // Empty palette - no legend section
+                        continue;
+                    };
+
+                    let key = (
+                        color_info.factor_name.clone(),
+                        palette
+                            .stops
+                            .iter()
+                            .map(|stop| (stop.value, stop.color))
+                            .collect::<Vec<_>>(),
+                    );
+                    if seen_continuous.contains(&key) {
+                        crate::debug_log!(
+                            "Skipping duplicate continuous legend section for '{}'",
+                            color_info.factor_name
+                        );
+                        continue;
+                    }
+                    seen_continuous.push(key);
 
-        match &color_info.mapping {
-            tercen_rs::ColorMapping::Continuous(palette) => {
-                // For continuous colors, get the min/max and color stops from the palette
-                if let Some((min_val, max_val)) = palette.range() {
                     // Convert Tercen ColorStops to GGRS LegendColorStops
                     let color_stops: Vec<LegendColorStop> = palette
                         .stops
@@ -1372,84 +4328,66 @@ impl TercenStreamGenerator {
                         .map(|stop| LegendColorStop::new(stop.value, stop.color))
                         .collect();
 
-                    eprintln!(
-                        "DEBUG: Legend scale using {} color stops from palette (range: {} to {})",
+                    crate::debug_log!(
+                        "Legend section '{}' using {} color stops from palette (range: {} to {})",
+                        color_info.factor_name,
                         color_stops.len(),
                         min_val,
                         max_val
                     );
 
-                    Ok(LegendScale::Continuous {
+                    sections.push(LegendSection::Continuous {
                         min: min_val,
                         max: max_val,
-                        aesthetic_name: color_info.factor_name.clone(),
+                        title: color_info.factor_name.clone(),
                         color_stops,
-                    })
-                } else {
-                    // Empty palette - no legend
-                    Ok(LegendScale::None)
+                    });
                 }
-            }
-            tercen_rs::ColorMapping::Categorical(color_map) => {
-                // For categorical colors, check if we have explicit mappings
-                if !color_map.mappings.is_empty() {
-                    // Explicit label→color mappings from palette
-                    let mut entries: Vec<(String, [u8; 3])> = color_map
-                        .mappings
-                        .iter()
-                        .map(|(label, color)| (label.clone(), *color))
-                        .collect();
-                    entries.sort_by(|a, b| a.0.cmp(&b.0));
+                tercen_rs::ColorMapping::Categorical(_) => {
+                    // All categorical factors share .colorLevels - one section for all
+                    if has_categorical_section {
+                        continue;
+                    }
+                    has_categorical_section = true;
 
-                    Ok(LegendScale::Discrete {
-                        entries,
-                        aesthetic_name: combined_name.clone(),
-                    })
-                } else if let Some(ref labels) = color_info.color_labels {
-                    // Use actual color labels from the color table with palette colors
-                    eprintln!(
-                        "DEBUG: Using {} color labels from color table for '{}'",
-                        labels.len(),
-                        combined_name
-                    );
-                    let entries: Vec<(String, [u8; 3])> = labels
-                        .iter()
-                        .enumerate()
-                        .map(|(i, label)| {
-                            let color = tercen_rs::categorical_color_from_level(i as i32);
-                            (label.clone(), color)
-                        })
-                        .collect();
-                    Ok(LegendScale::Discrete {
-                        entries,
-                        aesthetic_name: combined_name.clone(),
-                    })
-                } else if let Some(n_levels) = color_info.n_levels {
-                    // Fallback: Use n_levels from color table schema with generic labels
-                    eprintln!(
-                        "DEBUG: Using n_levels={} with generic labels for '{}' (no color_labels)",
-                        n_levels, combined_name
-                    );
-                    let entries: Vec<(String, [u8; 3])> = (0..n_levels)
-                        .map(|i| {
-                            let label = format!("Level {}", i);
-                            let color = tercen_rs::categorical_color_from_level(i as i32);
-                            (label, color)
-                        })
-                        .collect();
-                    Ok(LegendScale::Discrete {
+                    let entries = Self::categorical_legend_entries(color_info, &combined_name);
+                    sections.push(LegendSection::Discrete {
                         entries,
-                        aesthetic_name: combined_name.clone(),
-                    })
-                } else {
-                    // No explicit mappings and no n_levels - use default generic level labels
-                    eprintln!(
-                        "DEBUG: No explicit mappings or n_levels, using default generic level labels"
-                    );
-                    Self::create_generic_level_legend(&combined_name)
+                        title: combined_name.clone(),
+                    });
                 }
             }
         }
+
+        Ok(Self::sections_to_legend_scale(sections))
+    }
+
+    /// Convert legend sections to a LegendScale
+    ///
+    /// A single section is returned in its non-combined form.
+    fn sections_to_legend_scale(sections: Vec<LegendSection>) -> LegendScale {
+        if sections.len() > 1 {
+            return LegendScale::Combined { sections };
+        }
+
+        match sections.into_iter().next() {
+            None => LegendScale::None,
+            Some(LegendSection::Continuous {
+                min,
+                max,
+                title,
+                color_stops,
+            }) => LegendScale::Continuous {
+                min,
+                max,
+                aesthetic_name: title,
+                color_stops,
+            },
+            Some(LegendSection::Discrete { entries, title }) => LegendScale::Discrete {
+                entries,
+                aesthetic_name: title,
+            },
+        }
     }
 
     /// Build a combined legend for mixed-layer scenarios
@@ -1485,9 +4423,10 @@ impl TercenStreamGenerator {
                             title: factor_name.clone(),
                             color_stops,
                         });
-                        eprintln!(
-                            "DEBUG: Added continuous section for layer {} ('{}')",
-                            layer_idx, factor_name
+                        crate::debug_log!(
+                            "Added continuous section for layer {} ('{}')",
+                            layer_idx,
+                            factor_name
                         );
                     }
                 }
@@ -1513,9 +4452,10 @@ impl TercenStreamGenerator {
                             entries,
                             title: factor_name.clone(),
                         });
-                        eprintln!(
-                            "DEBUG: Added discrete section for layer {} ('{}')",
-                            layer_idx, factor_name
+                        crate::debug_log!(
+                            "Added discrete section for layer {} ('{}')",
+                            layer_idx,
+                            factor_name
                         );
                     }
                 }
@@ -1531,39 +4471,837 @@ impl TercenStreamGenerator {
                         entries: vec![(y_factor_name.clone(), *color)],
                         title: String::new(), // No separate title, the entry IS the label
                     });
-                    eprintln!(
-                        "DEBUG: Added layer-based entry for layer {} (Y-factor: '{}', color: {:?})",
-                        layer_idx, y_factor_name, color
+                    crate::debug_log!(
+                        "Added layer-based entry for layer {} (Y-factor: '{}', color: {:?})",
+                        layer_idx,
+                        y_factor_name,
+                        color
                     );
                 }
             }
         }
 
-        if sections.is_empty() {
-            Ok(LegendScale::None)
-        } else if sections.len() == 1 {
-            // Single section - convert to non-combined form
-            let section = sections.into_iter().next().unwrap();
-            match section {
-                LegendSection::Continuous {
-                    min,
-                    max,
-                    title,
-                    color_stops,
-                } => Ok(LegendScale::Continuous {
-                    min,
-                    max,
-                    aesthetic_name: title,
-                    color_stops,
-                }),
-                LegendSection::Discrete { entries, title } => Ok(LegendScale::Discrete {
-                    entries,
-                    aesthetic_name: title,
-                }),
+        Ok(Self::sections_to_legend_scale(sections))
+    }
+
+    /// Return the aggregated heatmap data, aggregating and caching it on first use
+    fn heatmap_data(&self) -> DataFrame {
+        if let Some(ref cached) = *self.heatmap_cached_data.read().unwrap() {
+            return cached.clone();
+        }
+
+        let aggregated = tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(self.aggregate_heatmap_data_in_time())
+        })
+        .unwrap_or_else(|e| {
+            panic!(
+                "Failed to aggregate heatmap data: {}. \
+                This indicates a data processing error.",
+                e
+            )
+        });
+
+        let mut cache_write = self.heatmap_cached_data.write().unwrap();
+        *cache_write = Some(aggregated.clone());
+        aggregated
+    }
+
+    /// `aggregate_heatmap_data`, failing after `aggregation_timeout` when one is set
+    async fn aggregate_heatmap_data_in_time(
+        &self,
+    ) -> Result<DataFrame, Box<dyn std::error::Error>> {
+        match self.aggregation_timeout {
+            Some(limit) => tokio::time::timeout(limit, self.aggregate_heatmap_data())
+                .await
+                .unwrap_or_else(|_| {
+                    Err(format!(
+                        "timed out after {}s (raise 'heatmap.aggregation.timeout')",
+                        limit.as_secs()
+                    )
+                    .into())
+                }),
+            None => self.aggregate_heatmap_data().await,
+        }
+    }
+
+    /// Fetch the data of every chunk the renderer will request, before rendering
+    ///
+    /// The render fetches chunks by blocking on the runtime, which a single-threaded
+    /// runtime can't do from its own thread. There the data is streamed here instead
+    /// and served from memory: heatmaps are aggregated, histograms already hold
+    /// their bars, and other charts keep one data frame per chunk until it is read.
+    /// Chunks already in the shared data cache are not fetched again.
+    pub async fn preload(&self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.heatmap_mode.is_some() {
+            if self.heatmap_cached_data.read().unwrap().is_none() {
+                let aggregated = self.aggregate_heatmap_data_in_time().await?;
+                *self.heatmap_cached_data.write().unwrap() = Some(aggregated);
+            }
+            return Ok(());
+        }
+        if self.histogram_data.is_some() {
+            return Ok(());
+        }
+
+        let total_rows = self.n_total_data_rows();
+        let chunk_size = self.preferred_chunk_size().unwrap_or(total_rows).max(1);
+        let mut chunks = HashMap::new();
+        for start in (0..total_rows).step_by(chunk_size) {
+            let cached = self
+                .data_cache
+                .as_ref()
+                .is_some_and(|cache| cache.lock().unwrap().contains(start));
+            if cached {
+                continue;
+            }
+            let end = (start + chunk_size).min(total_rows);
+            chunks.insert(start, self.stream_bulk_data(Range::new(start, end)).await?);
+        }
+        crate::debug_log!("Preloaded {} chunks ({} rows)", chunks.len(), total_rows);
+        *self.preloaded_chunks.lock().unwrap() = chunks;
+        Ok(())
+    }
+
+    /// Replace the min/max of continuous legend sections with displayed value ranges
+    ///
+    /// Sections are matched by title (the color factor name); sections without a
+    /// recorded range keep the palette range.
+    fn apply_legend_value_ranges(
+        scale: LegendScale,
+        ranges: &HashMap<String, (f64, f64)>,
+    ) -> LegendScale {
+        match scale {
+            LegendScale::Continuous {
+                min,
+                max,
+                aesthetic_name,
+                color_stops,
+            } => {
+                let (min, max) = ranges.get(&aesthetic_name).copied().unwrap_or((min, max));
+                LegendScale::Continuous {
+                    min,
+                    max,
+                    aesthetic_name,
+                    color_stops,
+                }
+            }
+            LegendScale::Combined { sections } => LegendScale::Combined {
+                sections: sections
+                    .into_iter()
+                    .map(|section| match section {
+                        LegendSection::Continuous {
+                            min,
+                            max,
+                            title,
+                            color_stops,
+                        } => {
+                            let (min, max) = ranges.get(&title).copied().unwrap_or((min, max));
+                            LegendSection::Continuous {
+                                min,
+                                max,
+                                title,
+                                color_stops,
+                            }
+                        }
+                        other => other,
+                    })
+                    .collect(),
+            },
+            other => other,
+        }
+    }
+
+    /// Category labels of a categorical color factor in color level order
+    ///
+    /// The color table labels, otherwise the sorted palette labels.
+    fn base_color_levels(color_info: &tercen_rs::ColorInfo) -> Vec<String> {
+        match (&color_info.color_labels, &color_info.mapping) {
+            (Some(labels), _) => labels.clone(),
+            (None, tercen_rs::ColorMapping::Categorical(color_map)) => {
+                let mut labels: Vec<String> =
+                    color_map.mappings.iter().map(|(l, _)| l.clone()).collect();
+                labels.sort();
+                labels
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Category → color level order for every page of a multi-page plot
+    ///
+    /// When the categorical color factor is folded into a facet, levels come from
+    /// the facet labels (`add_facet_color_levels`), and labels missing from the
+    /// color table would be numbered in the order of each page's own facets. This
+    /// appends them in the order of the full, unfiltered facet table instead, so a
+    /// category gets the same color on every page. None when the color factor is
+    /// not a facet factor.
+    pub async fn global_color_levels(
+        client: &TercenClient,
+        col_facet_table_id: &str,
+        row_facet_table_id: &str,
+        color_infos: &[tercen_rs::ColorInfo],
+    ) -> Result<Option<Vec<String>>, Box<dyn std::error::Error>> {
+        let Some(color_info) = color_infos
+            .iter()
+            .find(|ci| matches!(ci.mapping, tercen_rs::ColorMapping::Categorical(_)))
+        else {
+            return Ok(None);
+        };
+
+        let facet_info = FacetInfo::load(client, col_facet_table_id, row_facet_table_id).await?;
+        let is_factor = |names: &[String]| names.len() == 1 && names[0] == color_info.factor_name;
+        let facets = if is_factor(&facet_info.col_facets.column_names) {
+            &facet_info.col_facets
+        } else if is_factor(&facet_info.row_facets.column_names) {
+            &facet_info.row_facets
+        } else {
+            return Ok(None);
+        };
+
+        let mut levels = Self::base_color_levels(color_info);
+        for group in &facets.groups {
+            if !levels.contains(&group.label) {
+                levels.push(group.label.clone());
+            }
+        }
+        Ok(Some(levels))
+    }
+
+    /// Derive `.colorLevels` from the facet index when the color factor is a facet factor
+    ///
+    /// Tercen folds a categorical color factor that is also the (only) column or row
+    /// facet factor into the facet, so the main table has no `.colorLevels`. Each facet
+    /// group's label is then the category: `.ci`/`.ri` is mapped to its level, in the
+    /// order of the color table labels (or the sorted palette labels).
+    fn add_facet_color_levels(
+        &self,
+        mut df: polars::frame::DataFrame,
+    ) -> Result<polars::frame::DataFrame, Box<dyn std::error::Error>> {
+        use polars::prelude::*;
+
+        if df.column(".colorLevels").is_ok() {
+            return Ok(df);
+        }
+        let Some(color_info) = self
+            .color_infos
+            .iter()
+            .find(|ci| matches!(ci.mapping, tercen_rs::ColorMapping::Categorical(_)))
+        else {
+            return Ok(df);
+        };
+
+        let is_factor = |names: &[String]| names.len() == 1 && names[0] == color_info.factor_name;
+        let (index_column, facets) = if is_factor(&self.facet_info.col_facets.column_names) {
+            (".ci", &self.facet_info.col_facets)
+        } else if is_factor(&self.facet_info.row_facets.column_names) {
+            (".ri", &self.facet_info.row_facets)
+        } else {
+            return Ok(df);
+        };
+        let Ok(index) = df.column(index_column) else {
+            return Ok(df);
+        };
+
+        // Page-independent levels when the pipeline built them from all facets
+        let levels = self
+            .color_levels
+            .clone()
+            .unwrap_or_else(|| Self::base_color_levels(color_info));
+        let facet_levels = facet_color_levels(
+            facets
+                .groups
+                .iter()
+                .map(|g| (g.original_index, g.label.as_str())),
+            &levels,
+        );
+        crate::debug_log!(
+            "Color factor '{}' is the {} facet factor - deriving .colorLevels from {}",
+            color_info.factor_name,
+            if index_column == ".ci" {
+                "column"
+            } else {
+                "row"
+            },
+            index_column
+        );
+
+        let color_levels: Vec<i32> = index
+            .cast(&DataType::Int64)?
+            .i64()?
+            .into_iter()
+            .map(|v| {
+                v.and_then(|v| facet_levels.get(&(v.max(0) as usize)).copied())
+                    .unwrap_or(0)
+            })
+            .collect();
+        df.with_column(Series::new(".colorLevels".into(), color_levels))?;
+        Ok(df)
+    }
+
+    /// Add color columns, handling NaN/Inf continuous color values first
+    ///
+    /// Non-finite values would otherwise be interpolated into arbitrary colors.
+    /// Affected rows are dropped, or colored with `nan_color` when set (packed as
+    /// 0xRRGGBB into the `.color` column). The affected row count is logged.
+    fn add_color_columns_checked(
+        &self,
+        df: polars::frame::DataFrame,
+    ) -> Result<polars::frame::DataFrame, Box<dyn std::error::Error>> {
+        use polars::prelude::*;
+
+        let df = self.add_facet_color_levels(df)?;
+
+        // Rows where any continuous color factor is NaN or infinite
+        let mut mask: Option<BooleanChunked> = None;
+        for color_info in &self.color_infos {
+            if !matches!(color_info.mapping, tercen_rs::ColorMapping::Continuous(_)) {
+                continue;
+            }
+            let values = df
+                .column(&color_info.factor_name)?
+                .cast(&DataType::Float64)?;
+            let non_finite: BooleanChunked = values
+                .f64()?
+                .into_iter()
+                .map(|v| Some(v.is_some_and(|v| !v.is_finite())))
+                .collect();
+            mask = Some(match mask {
+                Some(m) => &m | &non_finite,
+                None => non_finite,
+            });
+        }
+
+        let Some(mask) = mask.filter(|m| m.any()) else {
+            return Ok(tercen_rs::color_processor::add_color_columns(
+                df,
+                &self.color_infos,
+            )?);
+        };
+        let n_bad = mask.sum().unwrap_or(0);
+
+        let Some([r, g, b]) = self.nan_color else {
+            crate::warn_log!("Dropped {} rows with NaN/Inf color values", n_bad);
+            let df = df.filter(&!&mask)?;
+            return Ok(tercen_rs::color_processor::add_color_columns(
+                df,
+                &self.color_infos,
+            )?);
+        };
+
+        crate::warn_log!(
+            "{} rows with NaN/Inf color values drawn in #{:02X}{:02X}{:02X}",
+            n_bad,
+            r,
+            g,
+            b
+        );
+        let mut df = tercen_rs::color_processor::add_color_columns(df, &self.color_infos)?;
+        let color = df.column(".color")?.as_materialized_series().clone();
+        let packed = ((r as u32) << 16) | ((g as u32) << 8) | b as u32;
+        let nan_colors =
+            Series::new(".color".into(), vec![packed; df.height()]).cast(color.dtype())?;
+        df.with_column(nan_colors.zip_with(&mask, &color)?)?;
+        Ok(df)
+    }
+
+    /// Recolor `.colorLevels` values outside the known categories
+    ///
+    /// `categorical_color_from_level` gives unexpected levels a default color, so
+    /// rows with a level >= n_levels (or negative) are logged and colored by the
+    /// level modulo the default palette size, keeping them distinct and stable.
+    /// Only applies when every color factor is categorical.
+    fn check_color_levels(
+        &self,
+        df: polars::frame::DataFrame,
+    ) -> Result<polars::frame::DataFrame, Box<dyn std::error::Error>> {
+        use polars::prelude::*;
+
+        if !self
+            .color_infos
+            .iter()
+            .all(|ci| matches!(ci.mapping, tercen_rs::ColorMapping::Categorical(_)))
+        {
+            return Ok(df);
+        }
+        let Some(n_levels) = self
+            .color_infos
+            .iter()
+            .filter_map(|ci| {
+                ci.n_levels
+                    .or_else(|| ci.color_labels.as_ref().map(|labels| labels.len()))
+            })
+            .max()
+        else {
+            return Ok(df);
+        };
+        let (Ok(levels), Ok(color)) = (df.column(".colorLevels"), df.column(".color")) else {
+            return Ok(df);
+        };
+
+        let levels = levels.cast(&DataType::Int64)?;
+        let levels = levels.i64()?;
+        let n_overflow = levels
+            .into_iter()
+            .flatten()
+            .filter(|&l| l < 0 || l as usize >= n_levels)
+            .count();
+        if n_overflow == 0 {
+            return Ok(df);
+        }
+        crate::warn_log!(
+            "{} rows have .colorLevels outside 0..{} - coloring them by level modulo {}",
+            n_overflow,
+            n_levels,
+            DEFAULT_PALETTE_LEVELS
+        );
+
+        let color_dtype = color.dtype().clone();
+        let original = color.cast(&DataType::UInt32)?;
+        let recolored: Vec<Option<u32>> = levels
+            .into_iter()
+            .zip(original.u32()?.into_iter())
+            .map(|(level, packed)| match level {
+                Some(l) if l < 0 || l as usize >= n_levels => {
+                    let wrapped = l.rem_euclid(DEFAULT_PALETTE_LEVELS as i64) as i32;
+                    let [r, g, b] = tercen_rs::categorical_color_from_level(wrapped);
+                    Some(((r as u32) << 16) | ((g as u32) << 8) | b as u32)
+                }
+                _ => packed,
+            })
+            .collect();
+        let recolored = Series::new(".color".into(), recolored).cast(&color_dtype)?;
+
+        let mut df = df;
+        df.with_column(recolored)?;
+        Ok(df)
+    }
+
+    /// Recolor crosstab palettes with an accessible override palette
+    ///
+    /// Categorical factors get `colors` by sorted label; continuous palettes are
+    /// resampled to Viridis when `continuous` is set. Constant layer colors are
+    /// handled by the custom layer palette (see apply_layer_palette).
+    fn apply_palette_override(
+        mut color_infos: Vec<tercen_rs::ColorInfo>,
+        mut per_layer_colors: Option<tercen_rs::PerLayerColorConfig>,
+        colors: &[[u8; 3]],
+        continuous: bool,
+    ) -> (
+        Vec<tercen_rs::ColorInfo>,
+        Option<tercen_rs::PerLayerColorConfig>,
+    ) {
+        use tercen_rs::LayerColorConfig;
+
+        for color_info in &mut color_infos {
+            palette::recolor_categorical(&mut color_info.mapping, colors);
+            if let tercen_rs::ColorMapping::Continuous(ref mut p) = color_info.mapping {
+                if continuous {
+                    palette::resample_viridis(&mut p.stops, |s| &mut s.value, |s| &mut s.color);
+                }
+            }
+        }
+        if let Some(ref mut plc) = per_layer_colors {
+            for config in plc.layer_configs.iter_mut() {
+                match config {
+                    LayerColorConfig::Categorical { color_map, .. } => {
+                        // Same label order as palette::recolor_categorical
+                        let mut labels: Vec<String> =
+                            color_map.mappings.iter().map(|(l, _)| l.clone()).collect();
+                        labels.sort();
+                        for (label, color) in color_map.mappings.iter_mut() {
+                            let level = labels.iter().position(|l| l == &*label).unwrap_or(0);
+                            *color = palette::level_color(colors, level as i64);
+                        }
+                    }
+                    LayerColorConfig::Continuous { palette: p, .. } if continuous => {
+                        palette::resample_viridis(&mut p.stops, |s| &mut s.value, |s| &mut s.color);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        (color_infos, per_layer_colors)
+    }
+
+    /// Factor-level order of the categorical legend labels
+    ///
+    /// Color table labels when available, otherwise the palette mapping order.
+    fn legend_data_order(color_infos: &[tercen_rs::ColorInfo]) -> Vec<String> {
+        color_infos
+            .iter()
+            .find_map(|ci| match (&ci.mapping, &ci.color_labels) {
+                (tercen_rs::ColorMapping::Categorical(_), Some(labels)) => Some(labels.clone()),
+                (tercen_rs::ColorMapping::Categorical(color_map), None) => Some(
+                    color_map
+                        .mappings
+                        .iter()
+                        .map(|(label, _)| label.clone())
+                        .collect(),
+                ),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+
+    /// Order titled discrete legend sections
+    ///
+    /// Runs after all recoloring, so entries keep their colors. `Data` orders by
+    /// position in `data_order` (labels not in it keep their relative order, last);
+    /// `Alpha` sorts by label. Layer entries (untitled sections) are left as is.
+    fn order_legend_entries(
+        scale: LegendScale,
+        sort: LegendSort,
+        data_order: &[String],
+    ) -> LegendScale {
+        let order = |mut entries: Vec<(String, [u8; 3])>| -> Vec<(String, [u8; 3])> {
+            match sort {
+                LegendSort::Alpha => entries.sort_by(|a, b| a.0.cmp(&b.0)),
+                LegendSort::Data => entries.sort_by_key(|(label, _)| {
+                    data_order
+                        .iter()
+                        .position(|l| l == label)
+                        .unwrap_or(usize::MAX)
+                }),
+            }
+            entries
+        };
+        match scale {
+            LegendScale::Discrete {
+                entries,
+                aesthetic_name,
+            } if !aesthetic_name.is_empty() => LegendScale::Discrete {
+                entries: order(entries),
+                aesthetic_name,
+            },
+            LegendScale::Combined { sections } => LegendScale::Combined {
+                sections: sections
+                    .into_iter()
+                    .map(|section| match section {
+                        LegendSection::Discrete { entries, title } if !title.is_empty() => {
+                            LegendSection::Discrete {
+                                entries: order(entries),
+                                title,
+                            }
+                        }
+                        other => other,
+                    })
+                    .collect(),
+            },
+            other => other,
+        }
+    }
+
+    /// Apply legend title overrides
+    ///
+    /// A single-section legend takes `titles.title` when set; otherwise (and for
+    /// every section of a combined legend) factor names are mapped through
+    /// `titles.by_factor`.
+    fn retitle_legend(scale: LegendScale, titles: &LegendTitles) -> LegendScale {
+        let single = |name: String| {
+            titles
+                .title
+                .clone()
+                .unwrap_or_else(|| titles.display(&name))
+        };
+        match scale {
+            LegendScale::Continuous {
+                min,
+                max,
+                aesthetic_name,
+                color_stops,
+            } => LegendScale::Continuous {
+                min,
+                max,
+                aesthetic_name: single(aesthetic_name),
+                color_stops,
+            },
+            LegendScale::Discrete {
+                entries,
+                aesthetic_name,
+            } => LegendScale::Discrete {
+                entries,
+                aesthetic_name: single(aesthetic_name),
+            },
+            LegendScale::Combined { sections } => LegendScale::Combined {
+                sections: sections
+                    .into_iter()
+                    .map(|section| match section {
+                        LegendSection::Continuous {
+                            min,
+                            max,
+                            title,
+                            color_stops,
+                        } => LegendSection::Continuous {
+                            min,
+                            max,
+                            title: titles.display(&title),
+                            color_stops,
+                        },
+                        LegendSection::Discrete { entries, title } => LegendSection::Discrete {
+                            entries,
+                            title: titles.display(&title),
+                        },
+                    })
+                    .collect(),
+            },
+            other => other,
+        }
+    }
+
+    /// Show binned continuous color factors as discrete legends of their bands
+    ///
+    /// Continuous sections titled by a factor in `bands` become discrete sections
+    /// listing the band ranges; other sections are unchanged.
+    fn band_legend(
+        scale: LegendScale,
+        bands: &HashMap<String, Vec<(String, [u8; 3])>>,
+    ) -> LegendScale {
+        if bands.is_empty() {
+            return scale;
+        }
+        match scale {
+            LegendScale::Continuous { aesthetic_name, .. }
+                if bands.contains_key(&aesthetic_name) =>
+            {
+                LegendScale::Discrete {
+                    entries: bands[&aesthetic_name].clone(),
+                    aesthetic_name,
+                }
+            }
+            LegendScale::Combined { sections } => LegendScale::Combined {
+                sections: sections
+                    .into_iter()
+                    .map(|section| match section {
+                        LegendSection::Continuous { title, .. } if bands.contains_key(&title) => {
+                            LegendSection::Discrete {
+                                entries: bands[&title].clone(),
+                                title,
+                            }
+                        }
+                        other => other,
+                    })
+                    .collect(),
+            },
+            other => other,
+        }
+    }
+
+    /// Rescale every continuous palette onto a fixed value range
+    fn apply_color_range(
+        mut color_infos: Vec<tercen_rs::ColorInfo>,
+        mut per_layer_colors: Option<tercen_rs::PerLayerColorConfig>,
+        range: (f64, f64),
+    ) -> (
+        Vec<tercen_rs::ColorInfo>,
+        Option<tercen_rs::PerLayerColorConfig>,
+    ) {
+        for color_info in &mut color_infos {
+            if let tercen_rs::ColorMapping::Continuous(ref mut p) = color_info.mapping {
+                palette::rescale_stops(&mut p.stops, |s| &mut s.value, range);
+            }
+        }
+        if let Some(ref mut plc) = per_layer_colors {
+            for config in plc.layer_configs.iter_mut() {
+                if let tercen_rs::LayerColorConfig::Continuous { palette: p, .. } = config {
+                    palette::rescale_stops(&mut p.stops, |s| &mut s.value, range);
+                }
+            }
+        }
+        (color_infos, per_layer_colors)
+    }
+
+    /// Keep only the entries of titled discrete legend sections whose level occurs
+    ///
+    /// Categorical sections list levels in level order, so entry `i` is level `i`.
+    /// Runs after recoloring (which is also by index) and before ordering.
+    fn drop_unused_legend_levels(scale: LegendScale, observed: &BTreeSet<i64>) -> LegendScale {
+        let keep = |entries: Vec<(String, [u8; 3])>| -> Vec<(String, [u8; 3])> {
+            let n = entries.len();
+            let kept: Vec<(String, [u8; 3])> = entries
+                .into_iter()
+                .enumerate()
+                .filter(|(i, _)| observed.contains(&(*i as i64)))
+                .map(|(_, entry)| entry)
+                .collect();
+            if kept.len() < n {
+                crate::debug_log!("Dropped {} unused legend entries", n - kept.len());
+            }
+            kept
+        };
+        match scale {
+            LegendScale::Discrete {
+                entries,
+                aesthetic_name,
+            } if !aesthetic_name.is_empty() => LegendScale::Discrete {
+                entries: keep(entries),
+                aesthetic_name,
+            },
+            LegendScale::Combined { sections } => LegendScale::Combined {
+                sections: sections
+                    .into_iter()
+                    .map(|section| match section {
+                        LegendSection::Discrete { entries, title } if !title.is_empty() => {
+                            LegendSection::Discrete {
+                                entries: keep(entries),
+                                title,
+                            }
+                        }
+                        other => other,
+                    })
+                    .collect(),
+            },
+            other => other,
+        }
+    }
+
+    /// Recolor titled discrete legend sections by entry index
+    ///
+    /// Categorical factor sections list levels in level order; layer entries have
+    /// no title and already use the override through the layer palette.
+    fn recolor_legend_levels(scale: LegendScale, colors: &[[u8; 3]]) -> LegendScale {
+        let recolor = |entries: Vec<(String, [u8; 3])>| -> Vec<(String, [u8; 3])> {
+            entries
+                .into_iter()
+                .enumerate()
+                .map(|(i, (label, _))| (label, palette::level_color(colors, i as i64)))
+                .collect()
+        };
+        match scale {
+            LegendScale::Discrete {
+                entries,
+                aesthetic_name,
+            } if !aesthetic_name.is_empty() => LegendScale::Discrete {
+                entries: recolor(entries),
+                aesthetic_name,
+            },
+            LegendScale::Combined { sections } => LegendScale::Combined {
+                sections: sections
+                    .into_iter()
+                    .map(|section| match section {
+                        LegendSection::Discrete { entries, title } if !title.is_empty() => {
+                            LegendSection::Discrete {
+                                entries: recolor(entries),
+                                title,
+                            }
+                        }
+                        other => other,
+                    })
+                    .collect(),
+            },
+            other => other,
+        }
+    }
+
+    /// Recolor categorical rows from `.colorLevels` with the palette override
+    ///
+    /// Rows are categorical when every legacy color factor is categorical, or when
+    /// their layer (`.axisIndex`) has a categorical color config. Colors are packed
+    /// as 0xRRGGBB into the existing `.color` column.
+    fn recolor_categorical_levels(
+        &self,
+        df: polars::frame::DataFrame,
+    ) -> Result<polars::frame::DataFrame, Box<dyn std::error::Error>> {
+        use polars::prelude::*;
+
+        let Some(colors) = palette::categorical_colors(self.palette_override) else {
+            return Ok(df);
+        };
+        let (Ok(levels), Ok(color)) = (df.column(".colorLevels"), df.column(".color")) else {
+            return Ok(df);
+        };
+
+        let layer_is_categorical: Vec<bool> = match self.per_layer_colors {
+            Some(ref plc) => plc
+                .layer_configs
+                .iter()
+                .map(|c| matches!(c, tercen_rs::LayerColorConfig::Categorical { .. }))
+                .collect(),
+            None => vec![self
+                .color_infos
+                .iter()
+                .all(|ci| matches!(ci.mapping, tercen_rs::ColorMapping::Categorical(_)))],
+        };
+        let layers: Vec<i64> = match df.column(".axisIndex") {
+            Ok(c) if self.per_layer_colors.is_some() => c
+                .cast(&DataType::Int64)?
+                .i64()?
+                .into_iter()
+                .map(|v| v.unwrap_or(0))
+                .collect(),
+            _ => vec![0; df.height()],
+        };
+
+        let color_dtype = color.dtype().clone();
+        let original = color.cast(&DataType::UInt32)?;
+        let recolored: Vec<Option<u32>> = levels
+            .cast(&DataType::Int64)?
+            .i64()?
+            .into_iter()
+            .zip(original.u32()?.into_iter())
+            .zip(layers)
+            .map(|((level, packed), layer)| {
+                let categorical = layer_is_categorical
+                    .get(layer.max(0) as usize)
+                    .copied()
+                    .unwrap_or(false);
+                match level {
+                    Some(level) if categorical => {
+                        let [r, g, b] = palette::level_color(colors, level);
+                        Some(((r as u32) << 16) | ((g as u32) << 8) | b as u32)
+                    }
+                    _ => packed,
+                }
+            })
+            .collect();
+        let recolored = Series::new(".color".into(), recolored).cast(&color_dtype)?;
+
+        let mut df = df;
+        df.with_column(recolored)?;
+        Ok(df)
+    }
+
+    /// Replace constant layer colors with a custom palette (cycling by layer index)
+    fn apply_layer_palette(
+        mut per_layer_colors: tercen_rs::PerLayerColorConfig,
+        palette: &[[u8; 3]],
+    ) -> tercen_rs::PerLayerColorConfig {
+        use tercen_rs::LayerColorConfig;
+
+        for (layer_idx, config) in per_layer_colors.layer_configs.iter_mut().enumerate() {
+            if let LayerColorConfig::Constant { color } = config {
+                *color = palette[layer_idx % palette.len()];
             }
-        } else {
-            Ok(LegendScale::Combined { sections })
         }
+        per_layer_colors
+    }
+
+    /// Color rows by `.axisIndex` using the custom layer palette
+    ///
+    /// Used instead of `add_layer_colors` when a custom palette is set. Colors are
+    /// packed as 0xRRGGBB into the `.color` column produced by `add_layer_colors`.
+    fn add_custom_layer_colors(
+        &self,
+        df: polars::frame::DataFrame,
+    ) -> Result<polars::frame::DataFrame, Box<dyn std::error::Error>> {
+        use polars::prelude::*;
+
+        let mut df =
+            tercen_rs::color_processor::add_layer_colors(df, self.layer_palette_name.as_deref())?;
+        let color_dtype = df.column(".color")?.dtype().clone();
+        let palette = &self.categorical_palette;
+
+        let packed: Vec<u32> = df
+            .column(".axisIndex")?
+            .cast(&DataType::Int64)?
+            .i64()?
+            .into_iter()
+            .map(|layer| {
+                let [r, g, b] = palette[layer.unwrap_or(0).max(0) as usize % palette.len()];
+                ((r as u32) << 16) | ((g as u32) << 8) | b as u32
+            })
+            .collect();
+        df.with_column(Series::new(".color".into(), packed).cast(&color_dtype)?)?;
+        Ok(df)
     }
 
     /// Build a discrete legend for layer-based colors (all layers with constant colors)
@@ -1590,10 +5328,7 @@ impl TercenStreamGenerator {
             })
             .collect();
 
-        eprintln!(
-            "DEBUG: Built layer-based legend with {} entries",
-            entries.len()
-        );
+        crate::debug_log!("Built layer-based legend with {} entries", entries.len());
 
         Ok(LegendScale::Discrete {
             entries,
@@ -1601,22 +5336,46 @@ impl TercenStreamGenerator {
         })
     }
 
-    // Stream data for a specific facet cell in chunks
-    // NOTE: Per-facet streaming not used - commented out since GGRS uses bulk mode
-    /// Stream data in bulk across ALL facets (includes .ci and .ri columns)
-    async fn stream_bulk_data(
-        &self,
-        data_range: Range,
-    ) -> Result<DataFrame, Box<dyn std::error::Error>> {
-        eprintln!(
-            "DEBUG: stream_bulk_data called with range {}..{} (requesting {} rows)",
-            data_range.start,
-            data_range.end,
-            data_range.end - data_range.start
-        );
+    /// Whether some layers have color factors and others don't
+    fn is_mixed_layer(&self) -> bool {
+        self.per_layer_colors
+            .as_ref()
+            .map(|plc| plc.is_mixed())
+            .unwrap_or(false)
+    }
 
-        let streamer = Self::create_streamer(&self.client, &self.schema_cache);
+    /// Whether points are colored by layer (multiple layers, no color factors)
+    fn uses_layer_colors(&self) -> bool {
+        self.color_infos.is_empty() && self.n_layers > 1 && !self.is_mixed_layer()
+    }
+
+    /// Columns fetched from the main table in bulk mode
+    ///
+    /// Requested columns the main table doesn't have are skipped, so charts
+    /// without auxiliary columns (e.g. `.y0s`, `.axisIndex`) still stream.
+    /// Each column is requested once, in first-requested order.
+    fn bulk_columns(&self) -> Vec<String> {
+        let mut columns = self.requested_bulk_columns();
+        if !self.main_columns.is_empty() {
+            columns.retain(|c| self.main_columns.contains(c));
+        }
+        dedup_columns(&mut columns);
+        columns
+    }
+
+    /// Requested bulk columns missing from the main table
+    fn missing_bulk_columns(&self) -> Vec<String> {
+        if self.main_columns.is_empty() {
+            return Vec::new();
+        }
+        self.requested_bulk_columns()
+            .into_iter()
+            .filter(|c| !self.main_columns.contains(c))
+            .collect()
+    }
 
+    /// Columns the chart configuration asks for in bulk mode
+    fn requested_bulk_columns(&self) -> Vec<String> {
         // For bulk streaming, include facet indices and quantized coordinates
         // Note: We DON'T request .x/.y columns - axis ranges come from:
         //   - Y-axis table (always exists, Y is mandatory)
@@ -1632,14 +5391,27 @@ impl TercenStreamGenerator {
 
         // Add chart-type specific columns
         match self.chart_kind {
-            ChartKind::Bar => {
-                // Bar charts need .y0s (baseline) for stacked bars
-                columns.push(".y0s".to_string());
-                eprintln!("DEBUG: Bar chart - fetching .y0s baseline column");
-            }
+            ChartKind::Bar => match self.bar_mode {
+                BarMode::Stack => {
+                    // Stacked bars need .y0s (baseline)
+                    columns.push(".y0s".to_string());
+                    crate::debug_log!("Bar chart - fetching .y0s baseline column");
+                }
+                BarMode::Dodge => {
+                    // Dodged bars start at the axis baseline; layers are offset along X
+                    // via .axisIndex (fetched below when multiple layers exist)
+                    crate::debug_log!("Bar chart (dodge) - bars offset by .axisIndex");
+                }
+            },
             ChartKind::Line => {
-                // Line charts may need ordering/grouping columns in the future
-                eprintln!("DEBUG: Line chart - no extra columns needed yet");
+                // Line charts group series by layer (.axisIndex) and categorical color
+                // (.colorLevels); both are fetched below when present. Points are ordered
+                // by .order when the table has one, otherwise along X via .xs.
+                crate::debug_log!("Line chart - series grouped by .axisIndex/.colorLevels");
+                if self.main_columns.iter().any(|c| c == ".order") {
+                    columns.push(".order".to_string());
+                    crate::debug_log!("Line chart - fetching .order column");
+                }
             }
             ChartKind::Heatmap | ChartKind::Point => {
                 // Standard columns are sufficient
@@ -1656,18 +5428,14 @@ impl TercenStreamGenerator {
         // 2. Pure layer-based: no colors on any layer (use layer palette)
         // 3. Uniform colors: all layers share the same color config (legacy color_infos)
 
-        let is_mixed_layer = self
-            .per_layer_colors
-            .as_ref()
-            .map(|plc| plc.is_mixed())
-            .unwrap_or(false);
-        let use_layer_colors = self.color_infos.is_empty() && self.n_layers > 1 && !is_mixed_layer;
+        let is_mixed_layer = self.is_mixed_layer();
+        let use_layer_colors = self.uses_layer_colors();
 
         // Always fetch .axisIndex when multiple layers exist (for shape cycling and color handling)
         if self.n_layers > 1 && !columns.contains(&".axisIndex".to_string()) {
             columns.push(".axisIndex".to_string());
-            eprintln!(
-                "DEBUG: Multi-layer ({} layers) - fetching .axisIndex for shape/color cycling",
+            crate::debug_log!(
+                "Multi-layer ({} layers) - fetching .axisIndex for shape/color cycling",
                 self.n_layers
             );
         }
@@ -1675,8 +5443,8 @@ impl TercenStreamGenerator {
         if is_mixed_layer {
             // Mixed-layer scenario: always need .axisIndex to determine which coloring to use
             columns.push(".axisIndex".to_string());
-            eprintln!(
-                "DEBUG: Mixed-layer coloring ({} layers) - fetching .axisIndex",
+            crate::debug_log!(
+                "Mixed-layer coloring ({} layers) - fetching .axisIndex",
                 self.n_layers
             );
 
@@ -1688,8 +5456,8 @@ impl TercenStreamGenerator {
                         LayerColorConfig::Categorical { .. } => {
                             if !columns.contains(&".colorLevels".to_string()) {
                                 columns.push(".colorLevels".to_string());
-                                eprintln!(
-                                    "DEBUG: Layer {} has categorical colors - fetching .colorLevels",
+                                crate::debug_log!(
+                                    "Layer {} has categorical colors - fetching .colorLevels",
                                     layer_idx
                                 );
                             }
@@ -1697,14 +5465,15 @@ impl TercenStreamGenerator {
                         LayerColorConfig::Continuous { factor_name, .. } => {
                             if !columns.contains(factor_name) {
                                 columns.push(factor_name.clone());
-                                eprintln!(
-                                    "DEBUG: Layer {} has continuous colors - fetching '{}'",
-                                    layer_idx, factor_name
+                                crate::debug_log!(
+                                    "Layer {} has continuous colors - fetching '{}'",
+                                    layer_idx,
+                                    factor_name
                                 );
                             }
                         }
                         LayerColorConfig::Constant { color } => {
-                            eprintln!("DEBUG: Layer {} has constant color {:?}", layer_idx, color);
+                            crate::debug_log!("Layer {} has constant color {:?}", layer_idx, color);
                         }
                     }
                 }
@@ -1712,8 +5481,8 @@ impl TercenStreamGenerator {
         } else if use_layer_colors {
             // Pure layer-based coloring: fetch .axisIndex
             columns.push(".axisIndex".to_string());
-            eprintln!(
-                "DEBUG: Multi-layer ({} layers) with no colors - will use layer-based coloring",
+            crate::debug_log!(
+                "Multi-layer ({} layers) with no colors - will use layer-based coloring",
                 self.n_layers
             );
         } else {
@@ -1727,46 +5496,303 @@ impl TercenStreamGenerator {
                         }
                     }
                     tercen_rs::ColorMapping::Continuous(_) => {
-                        // Add the factor column for continuous colors
-                        columns.push(color_info.factor_name.clone());
+                        // Add the factor column for continuous colors, unless it is
+                        // the Y factor (derived from .ys, see add_y_color_column)
+                        if self.y_color_factor.as_ref() != Some(&color_info.factor_name) {
+                            columns.push(color_info.factor_name.clone());
+                        }
+                    }
+                }
+            }
+        }
+
+        // Per-layer transforms: raw Y values to requantize (see retransform_layer_y)
+        if !self.layer_y_transforms.is_empty() && !columns.contains(&".y".to_string()) {
+            columns.push(".y".to_string());
+        }
+
+        // Categorical X: level labels (see position_x_categories)
+        if let Some(ref factor) = self.x_categorical_factor {
+            if !self.x_categories.is_empty() && !columns.contains(factor) {
+                columns.push(factor.clone());
+            }
+        }
+
+        columns
+    }
+
+    /// Rows per stream_tson request so a message stays within the budget
+    ///
+    /// Assumes 8 bytes per value (worst case for numeric columns), so 20 columns
+    /// get a fifth of the rows of 4 columns. Never exceeds the configured chunk size.
+    fn effective_chunk_size(&self, n_columns: usize) -> usize {
+        const BYTES_PER_VALUE: usize = 8;
+        let rows_in_budget = self.message_budget_bytes / (n_columns.max(1) * BYTES_PER_VALUE);
+        self.chunk_size.min(rows_in_budget).max(1)
+    }
+
+    /// Data chunk served to GGRS: heatmap tiles, histogram bars or streamed rows
+    fn fetch_chunk(&self, data_range: Range) -> DataFrame {
+        // For heatmaps, aggregate all data by (ci, ri) and return mean values
+        // This ensures the displayed color reflects the aggregate (mean) rather than
+        // the last data point drawn (which would depend on streaming order)
+        if self.heatmap_mode.is_some() {
+            // Check if we already have cached aggregated data
+            {
+                let cache_read = self.heatmap_cached_data.read().unwrap();
+                if cache_read.is_some() {
+                    // Data already aggregated and returned on first call
+                    // Return empty DataFrame for subsequent calls
+                    if data_range.start > 0 {
+                        crate::debug_log!(
+                            "Heatmap data already returned, returning empty for range {}..{}",
+                            data_range.start,
+                            data_range.end
+                        );
+                        return DataFrame::new();
                     }
+                    // Return the cached data on first call
+                    crate::debug_log!("Returning cached aggregated heatmap data");
+                    return cache_read.as_ref().unwrap().clone();
                 }
             }
+
+            // First call - aggregate and cache
+            crate::debug_log!("First heatmap data request - aggregating all data");
+            let aggregated = self.heatmap_data();
+            crate::debug_log!("Returning {} aggregated heatmap rows", aggregated.nrow());
+            return aggregated;
+        }
+
+        // Histogram: all bars are returned with the first chunk
+        if let Some(ref bars) = self.histogram_data {
+            if data_range.start > 0 {
+                return DataFrame::new();
+            }
+            return bars.clone();
+        }
+
+        // Chunks streamed before rendering (see preload)
+        if let Some(df) = self
+            .preloaded_chunks
+            .lock()
+            .unwrap()
+            .remove(&data_range.start)
+        {
+            return df;
         }
 
-        // Fetch the requested range directly (GGRS handles chunking)
-        let offset = data_range.start as i64;
-        let limit = (data_range.end - data_range.start) as i64;
+        // Non-heatmap: stream data as usual
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current()
+                .block_on(async { self.stream_bulk_data(data_range).await })
+        })
+        .unwrap_or_else(|e| {
+            panic!(
+                "Failed to fetch bulk data from Tercen: {}. \
+                This indicates a network error or invalid table configuration.",
+                e
+            )
+        })
+    }
+
+    /// Append the rows of a served chunk to the data export as CSV lines
+    ///
+    /// `.x`/`.y` are dequantized from `.xs`/`.ys` with the cell's numeric axis range
+    /// and mapped back to data units through the inverse of the axis transform;
+    /// `.color` is written as `#RRGGBB`. Values that can't be derived (including
+    /// those on a transform without local functions, e.g. logicle) are left empty.
+    fn record_data_export(
+        &self,
+        export: &DataExport,
+        df: &DataFrame,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        use polars::prelude::*;
+        use std::fmt::Write;
+
+        let df = df.inner();
+        let n = df.height();
+        let ints = |name: &str| -> Result<Vec<Option<i64>>, Box<dyn std::error::Error>> {
+            match df.column(name) {
+                Ok(c) => Ok(c.cast(&DataType::Int64)?.i64()?.into_iter().collect()),
+                Err(_) => Ok(vec![None; n]),
+            }
+        };
+        let floats = |name: &str| -> Result<Vec<Option<f64>>, Box<dyn std::error::Error>> {
+            match df.column(name) {
+                Ok(c) => Ok(c.cast(&DataType::Float64)?.f64()?.into_iter().collect()),
+                Err(_) => Ok(vec![None; n]),
+            }
+        };
+        let (ci, ri, color) = (ints(".ci")?, ints(".ri")?, ints(".color")?);
+        let (xs, ys) = (floats(".xs")?, floats(".ys")?);
+
+        let dequantize = |q: f64, (min, max): (f64, f64)| min + q / 65535.0 * (max - min);
+        let value = |v: Option<f64>| v.map(|v| v.to_string()).unwrap_or_default();
+        let mut lines = String::new();
+        for i in 0..n {
+            let cell = (
+                ci[i].unwrap_or(0).max(0) as usize,
+                ri[i].unwrap_or(0).max(0) as usize,
+            );
+            let axes = self.axis_ranges.get(&cell);
+            let x_range = match axes {
+                Some((AxisData::Numeric(num), _)) => Some((num.min_value, num.max_value)),
+                _ => None,
+            };
+            let y_range = match axes {
+                Some((_, AxisData::Numeric(num))) => Some((num.min_value, num.max_value)),
+                _ => None,
+            };
+            let x = xs[i]
+                .zip(x_range)
+                .zip(self.x_value_transform)
+                .map(|((q, range), t)| t.inverse(dequantize(q, range)));
+            let y = ys[i]
+                .zip(y_range)
+                .zip(self.y_value_transform)
+                .map(|((q, range), t)| t.inverse(dequantize(q, range)));
+            let color = color[i]
+                .map(|c| format!("#{:06X}", c & 0xFF_FFFF))
+                .unwrap_or_default();
+            writeln!(
+                lines,
+                "{},{},{},{},{}",
+                ci[i].map(|v| v.to_string()).unwrap_or_default(),
+                ri[i].map(|v| v.to_string()).unwrap_or_default(),
+                value(x),
+                value(y),
+                color
+            )?;
+        }
+        export.lock().unwrap().push_str(&lines);
+        Ok(())
+    }
 
-        eprintln!(
-            "DEBUG: Calling stream_tson with offset={}, limit={}",
-            offset, limit
+    /// Stream data in bulk across ALL facets (includes .ci and .ri columns)
+    async fn stream_bulk_data(
+        &self,
+        data_range: Range,
+    ) -> Result<DataFrame, Box<dyn std::error::Error>> {
+        crate::debug_log!(
+            "stream_bulk_data called with range {}..{} (requesting {} rows)",
+            data_range.start,
+            data_range.end,
+            data_range.end - data_range.start
         );
-        eprintln!("DEBUG: Requested columns: {:?}", columns);
 
-        // Stream data from Tercen (no caching - GGRS handles caching)
-        let tson_data = streamer
-            .stream_tson(&self.main_table_id, Some(columns.clone()), offset, limit)
+        let streamer = Self::create_streamer(&self.client, &self.schema_cache);
+        let columns = self.bulk_columns();
+        let use_layer_colors = self.uses_layer_colors();
+
+        // Fetch the requested range, split into sub-requests that fit the message
+        // budget (GGRS normally requests preferred_chunk_size() rows already)
+        let sub_chunk = self.effective_chunk_size(columns.len());
+        crate::debug_log!("Requested columns: {:?}", columns);
+
+        let mut parts: Vec<polars::frame::DataFrame> = Vec::new();
+        let mut offset = data_range.start;
+        while offset < data_range.end {
+            let limit = (data_range.end - offset).min(sub_chunk);
+            crate::debug_log!(
+                "Calling stream_tson with offset={}, limit={}",
+                offset,
+                limit
+            );
+
+            // Stream data from Tercen (no caching - GGRS handles caching)
+            let tson_data = with_retry_from_env(|| {
+                streamer.stream_tson(
+                    &self.main_table_id,
+                    Some(columns.clone()),
+                    offset as i64,
+                    limit as i64,
+                )
+            })
             .await?;
+            crate::debug_log!("stream_tson returned {} bytes", tson_data.len());
+
+            if tson_data.is_empty() {
+                break;
+            }
 
-        eprintln!("DEBUG: stream_tson returned {} bytes", tson_data.len());
+            // Parse TSON to DataFrame - contains .ci, .ri, .xs, .ys, and color factors
+            let part = tson_to_dataframe(&tson_data)?;
+            if part.height() == 0 {
+                break;
+            }
+            offset += part.height();
+            parts.push(part);
+        }
 
-        if tson_data.is_empty() {
-            eprintln!("DEBUG: Empty TSON data, returning empty DataFrame");
+        let mut parts = parts.into_iter();
+        let Some(mut df) = parts.next() else {
+            crate::debug_log!("Empty TSON data, returning empty DataFrame");
             return Ok(ggrs_core::data::DataFrame::new());
+        };
+        for part in parts {
+            df.vstack_mut(&part)?;
+        }
+        crate::debug_log!("Parsed DataFrame with {} rows", df.height());
+        crate::debug_log!("Returned columns: {:?}", df.get_column_names());
+
+        let streamed = self.rows_streamed.fetch_add(df.height(), Ordering::Relaxed) + df.height();
+        crate::progress_log!(
+            "rows streamed {}/{} ({}%)",
+            streamed,
+            self.total_rows,
+            crate::logging::percent(streamed, self.total_rows)
+        );
+
+        // Layers with their own Y transform: re-express in the axis transform's space
+        if !self.layer_y_transforms.is_empty() {
+            df = self.retransform_layer_y(df)?;
         }
 
-        // Parse TSON to DataFrame - contains .ci, .ri, .xs, .ys, and color factors
-        let mut df = tson_to_dataframe(&tson_data)?;
-        eprintln!("DEBUG: Parsed DataFrame with {} rows", df.height());
-        eprintln!("DEBUG: Returned columns: {:?}", df.get_column_names());
+        // Categorical X: place each point at its level index. Done before any filtering
+        // so the jitter hash is keyed by the main-table row index.
+        if !self.x_categories.is_empty() {
+            df = self.position_x_categories(df, data_range.start)?;
+        }
+
+        // Downsampling: drop the rows outside each dense cell's sample. Done before any
+        // other filtering so row positions are main-table row indices.
+        if !self.sampled_rows.is_empty() {
+            df = self.sample_rows(df, data_range.start)?;
+        }
+
+        // Split rendering: only the rows of this generator's facet cell
+        if let Some((ci, ri)) = self.cell_filter {
+            use polars::prelude::*;
+            let mut keep = lit(true);
+            for (name, idx) in [(".ci", ci), (".ri", ri)] {
+                if df.column(name).is_ok() {
+                    keep = keep.and(col(name).cast(DataType::Int64).eq(lit(idx as i64)));
+                }
+            }
+            df = df.lazy().filter(keep).collect()?;
+        }
+
+        // Line charts: assign a series group and order points along X within each series,
+        // otherwise the polyline connects points across unrelated series
+        if matches!(self.chart_kind, ChartKind::Line) {
+            df = Self::sort_line_series(df)?;
+        }
+
+        // Dodged bars: shift each layer within its X position so bars sit side by side
+        if matches!(self.chart_kind, ChartKind::Bar)
+            && self.bar_mode == BarMode::Dodge
+            && self.n_layers > 1
+        {
+            df = self.dodge_bars(df)?;
+        }
 
         // DEBUG: Print heatmap column info (first chunk only)
         if data_range.start == 0 {
             if let Ok(n_x_levels) = df.column(".nXLevels") {
                 if let Ok(n_x_i64) = n_x_levels.i64() {
                     let n_levels = n_x_i64.get(0).unwrap_or(0);
-                    eprintln!("DEBUG HEATMAP: Total X levels (columns) = {}", n_levels);
+                    crate::debug_log!("HEATMAP: Total X levels (columns) = {}", n_levels);
                 }
             }
             // Compare .xs, .ys, .xLevels
@@ -1786,53 +5812,331 @@ impl TercenStreamGenerator {
                             _ => None,
                         })
                         .collect();
-                    eprintln!("DEBUG HEATMAP: First 10 (xs, ys, xLevels): {:?}", tuples);
+                    crate::debug_log!("HEATMAP: First 10 (xs, ys, xLevels): {:?}", tuples);
                 }
             }
         }
 
-        // NO FILTERING! Operator is dumb - just streams raw data.
-        // GGRS handles all filtering using original_index mapping.
+        // NO FILTERING! Operator is dumb - just streams raw data.
+        // GGRS handles all filtering using original_index mapping.
+
+        // Map color values to RGB based on the coloring mode
+        // Priority order:
+        // 1. Per-layer colors (multi-layer: respects .axisIndex for per-layer color config)
+        // 2. Legacy color_infos (single-layer: explicit color factors from step)
+        // 3. Layer-based coloring (multiple layers, no explicit colors — palette by .axisIndex)
+
+        if let Some(ref plc) = self.per_layer_colors {
+            // Multi-layer: per-layer color config (handles mixed, explicit, and constant)
+            crate::debug_log!(
+                "Adding per-layer colors for {} layers (explicit={}, mixed={}, constant={})",
+                self.n_layers,
+                plc.has_explicit_colors(),
+                plc.is_mixed(),
+                plc.has_constant_colors()
+            );
+            df = tercen_rs::color_processor::add_mixed_layer_colors(df, plc)?;
+            df = self.recolor_categorical_levels(df)?;
+            crate::debug_log!("Per-layer colors added successfully");
+        } else if !self.color_infos.is_empty() {
+            // Single-layer: legacy uniform colors (explicit color factors)
+            crate::debug_log!(
+                "Adding color columns for {} color factors (legacy path)",
+                self.color_infos.len()
+            );
+            if let Some(ref name) = self.y_color_factor {
+                df = self.add_y_color_column(df, name)?;
+            }
+            df = self.add_color_columns_checked(df)?;
+            df = self.check_color_levels(df)?;
+            df = self.recolor_categorical_levels(df)?;
+            crate::debug_log!("Color columns added successfully");
+        } else if use_layer_colors {
+            // Pure layer-based coloring (no color factors on any layer)
+            crate::debug_log!(
+                "Adding layer-based colors for {} layers using palette {:?}",
+                self.n_layers,
+                self.layer_palette_name
+            );
+            df = if self.categorical_palette.is_empty() {
+                tercen_rs::color_processor::add_layer_colors(
+                    df,
+                    self.layer_palette_name.as_deref(),
+                )?
+            } else {
+                self.add_custom_layer_colors(df)?
+            };
+            crate::debug_log!("Layer colors added successfully");
+        }
+
+        Ok(ggrs_core::data::DataFrame::from_polars(df))
+    }
+
+    /// Sort rows into line series for rendering
+    ///
+    /// A series is identified by its layer (`.axisIndex`) and categorical color level
+    /// (`.colorLevels`); missing columns count as a single group. Rows are sorted by
+    /// facet cell, series, then `.order` (or `.xs` when the table has no `.order`) so
+    /// each polyline is drawn in order. GGRS splits polylines by color.
+    ///
+    /// Sorting is per streamed chunk: a series whose points span several chunks is
+    /// ordered within each chunk, and GGRS joins the last point of one chunk to the
+    /// first of the next, so such a series can still double back at chunk boundaries.
+    fn sort_line_series(
+        df: polars::frame::DataFrame,
+    ) -> Result<polars::frame::DataFrame, Box<dyn std::error::Error>> {
+        use polars::prelude::*;
+
+        let columns = df.get_column_names_owned();
+        let has_column = |name: &str| columns.iter().any(|c| c.as_str() == name);
+
+        let layer_expr = if has_column(".axisIndex") {
+            col(".axisIndex").cast(DataType::Int64)
+        } else {
+            lit(0i64)
+        };
+        let level_expr = if has_column(".colorLevels") {
+            col(".colorLevels").cast(DataType::Int64)
+        } else {
+            lit(0i64)
+        };
+
+        let order = if has_column(".order") {
+            ".order"
+        } else {
+            ".xs"
+        };
+
+        let grouped = df
+            .lazy()
+            .sort_by_exprs(
+                [
+                    col(".ci"),
+                    col(".ri"),
+                    layer_expr * lit(65_536i64) + level_expr,
+                    col(order),
+                ],
+                SortMultipleOptions::default(),
+            )
+            .collect()?;
+
+        crate::debug_log!(
+            "Line chart - sorted {} rows by series and {}",
+            grouped.height(),
+            order
+        );
+        Ok(grouped)
+    }
+
+    /// Offset quantized `.xs` per layer for dodged bars
+    ///
+    /// Each X position (one data unit wide) gets a 0.9-wide band split evenly between
+    /// layers, matching ggplot2's default bar width. The offset is converted to quantized
+    /// units using the cell's X range and clamped to the quantized range (0-65535).
+    fn dodge_bars(
+        &self,
+        df: polars::frame::DataFrame,
+    ) -> Result<polars::frame::DataFrame, Box<dyn std::error::Error>> {
+        use polars::prelude::*;
+
+        const BAND_WIDTH: f64 = 0.9;
+        let n_layers = self.n_layers as f64;
+        let slot = BAND_WIDTH / n_layers;
+
+        let xs_dtype = df.column(".xs")?.dtype().clone();
+        let cells = facet_cells(&df)?;
+        let xs = int_column(&df, ".xs")?;
+        let layer = index_column(&df, ".axisIndex")?;
+
+        let dodged: Vec<f64> = (0..df.height())
+            .map(|i| {
+                let x_range = self
+                    .axis_ranges
+                    .get(&cells[i])
+                    .and_then(|(x_axis, _)| match x_axis {
+                        AxisData::Numeric(num) => Some(num.max_value - num.min_value),
+                        AxisData::Categorical(_) => None,
+                    })
+                    .filter(|r| r.is_finite() && *r > 0.0);
+                let Some(x_range) = x_range else {
+                    return xs[i] as f64;
+                };
+                let offset = (layer[i] as f64 + 0.5) * slot - BAND_WIDTH / 2.0;
+                let offset_q = offset * 65535.0 / x_range;
+                (xs[i] as f64 + offset_q).round().clamp(0.0, 65535.0)
+            })
+            .collect();
+
+        let mut df = df;
+        df.with_column(Series::new(".xs".into(), dodged).cast(&xs_dtype)?)?;
+
+        crate::debug_log!(
+            "Bar chart (dodge) - offset {} rows across {} layers",
+            df.height(),
+            self.n_layers
+        );
+        Ok(df)
+    }
+
+    /// Recompute `.ys` of layers with their own Y transform
+    ///
+    /// A row's `.y` is in its layer's transform space; it is mapped back to data
+    /// units with that transform's inverse, into the axis transform's space, and
+    /// quantized over the cell's Y-axis range. GGRS then dequantizes it and inverts
+    /// the axis transform like every other row, so e.g. a linear reference layer
+    /// lines up with a log data layer. Values outside the range are clamped. Rows
+    /// of other layers or without a finite value keep their `.ys`.
+    fn retransform_layer_y(
+        &self,
+        df: polars::frame::DataFrame,
+    ) -> Result<polars::frame::DataFrame, Box<dyn std::error::Error>> {
+        use polars::prelude::*;
+
+        let cells = facet_cells(&df)?;
+        let layer = index_column(&df, ".axisIndex")?;
+        let ys_column = df.column(".ys")?;
+        let ys_dtype = ys_column.dtype().clone();
+        let ys = ys_column.cast(&DataType::Float64)?;
+        let y = df.column(".y")?.cast(&DataType::Float64)?;
+
+        let requantized: Vec<Option<f64>> = ys
+            .f64()?
+            .into_iter()
+            .zip(y.f64()?.into_iter())
+            .enumerate()
+            .map(|(i, (ys, y))| {
+                let Some(layer_transform) = self.layer_y_transforms.get(&(layer[i] as i64)) else {
+                    return ys;
+                };
+                let cell = cells[i];
+                let range = match self.axis_ranges.get(&cell) {
+                    Some((_, AxisData::Numeric(num))) => (num.min_value, num.max_value),
+                    _ => return ys,
+                };
+                let axis_transform = self.y_value_transform.unwrap_or(ValueTransform::Identity);
+                y.and_then(|y| retransform_y(y, *layer_transform, axis_transform, range))
+                    .or(ys)
+            })
+            .collect();
+
+        let mut df = df;
+        df.with_column(Series::new(".ys".into(), requantized).cast(&ys_dtype)?)?;
+        Ok(df)
+    }
+
+    /// Keep the sampled rows of dense facet cells
+    ///
+    /// `row_offset` is the main-table index of the first row of `df`.
+    fn sample_rows(
+        &self,
+        df: polars::frame::DataFrame,
+        row_offset: usize,
+    ) -> Result<polars::frame::DataFrame, Box<dyn std::error::Error>> {
+        use polars::prelude::*;
+
+        let index = |name: &str| -> Result<Vec<usize>, Box<dyn std::error::Error>> {
+            match df.column(name) {
+                Ok(c) => Ok(c
+                    .cast(&DataType::Int64)?
+                    .i64()?
+                    .into_iter()
+                    .map(|v| v.unwrap_or(0).max(0) as usize)
+                    .collect()),
+                Err(_) => Ok(vec![0; df.height()]),
+            }
+        };
+        let keep: BooleanChunked = index(".ci")?
+            .into_iter()
+            .zip(index(".ri")?)
+            .enumerate()
+            .map(|(i, cell)| {
+                Some(
+                    self.sampled_rows
+                        .get(&cell)
+                        .is_none_or(|rows| rows.contains(&(row_offset + i))),
+                )
+            })
+            .collect();
+        Ok(df.filter(&keep)?)
+    }
+
+    /// Set `.xs` from the categorical X factor column
+    ///
+    /// Level `i` of `n` is quantized over [-0.5, n - 0.5] so it dequantizes to the
+    /// integer index `i`, the same convention as the heatmap grid axes.
+    ///
+    /// With `jitter_width`, each point is shifted by up to ±width/2 of a category band.
+    /// The shift is a hash of the main-table row index (`row_offset` + position in an
+    /// unfiltered `df`), so a point keeps its shift on every render, whatever rows are
+    /// sampled or filtered out, and width < 1 keeps it inside the band.
+    fn position_x_categories(
+        &self,
+        df: polars::frame::DataFrame,
+        row_offset: usize,
+    ) -> Result<polars::frame::DataFrame, Box<dyn std::error::Error>> {
+        use polars::prelude::*;
+
+        let Some(ref factor) = self.x_categorical_factor else {
+            return Ok(df);
+        };
+        let index: HashMap<&str, usize> = self
+            .x_categories
+            .iter()
+            .enumerate()
+            .map(|(i, level)| (level.as_str(), i))
+            .collect();
+        let n_levels = self.x_categories.len() as f64;
+
+        let xs_dtype = df.column(".xs")?.dtype().clone();
+        let levels = df.column(factor)?.cast(&DataType::String)?;
+        let jitter = self.jitter_width.unwrap_or(0.0);
+        let positioned: Vec<f64> = levels
+            .str()?
+            .into_iter()
+            .enumerate()
+            .map(|(row, level)| {
+                let i = level.and_then(|l| index.get(l)).copied().unwrap_or(0) as f64;
+                let shift = if jitter > 0.0 {
+                    (unit_hash((row_offset + row) as u64) - 0.5) * jitter
+                } else {
+                    0.0
+                };
+                ((i + 0.5 + shift) / n_levels * 65535.0).round()
+            })
+            .collect();
+
+        let mut df = df;
+        df.with_column(Series::new(".xs".into(), positioned).cast(&xs_dtype)?)?;
+        Ok(df)
+    }
 
-        // Map color values to RGB based on the coloring mode
-        // Priority order:
-        // 1. Per-layer colors (multi-layer: respects .axisIndex for per-layer color config)
-        // 2. Legacy color_infos (single-layer: explicit color factors from step)
-        // 3. Layer-based coloring (multiple layers, no explicit colors — palette by .axisIndex)
+    /// Add the color factor column `name` from the dequantized `.ys`
+    ///
+    /// Used when the continuous color factor is the Y factor, so the factor column
+    /// is not streamed. Dequantizes with the cell's Y-axis range like GGRS does, so
+    /// colors follow the plotted values. Rows of cells without a range get no value.
+    fn add_y_color_column(
+        &self,
+        df: polars::frame::DataFrame,
+        name: &str,
+    ) -> Result<polars::frame::DataFrame, Box<dyn std::error::Error>> {
+        use polars::prelude::*;
 
-        if let Some(ref plc) = self.per_layer_colors {
-            // Multi-layer: per-layer color config (handles mixed, explicit, and constant)
-            eprintln!(
-                "DEBUG: Adding per-layer colors for {} layers (explicit={}, mixed={}, constant={})",
-                self.n_layers,
-                plc.has_explicit_colors(),
-                plc.is_mixed(),
-                plc.has_constant_colors()
-            );
-            df = tercen_rs::color_processor::add_mixed_layer_colors(df, plc)?;
-            eprintln!("DEBUG: Per-layer colors added successfully");
-        } else if !self.color_infos.is_empty() {
-            // Single-layer: legacy uniform colors (explicit color factors)
-            eprintln!(
-                "DEBUG: Adding color columns for {} color factors (legacy path)",
-                self.color_infos.len()
-            );
-            df = tercen_rs::color_processor::add_color_columns(df, &self.color_infos)?;
-            eprintln!("DEBUG: Color columns added successfully");
-        } else if use_layer_colors {
-            // Pure layer-based coloring (no color factors on any layer)
-            eprintln!(
-                "DEBUG: Adding layer-based colors for {} layers using palette {:?}",
-                self.n_layers, self.layer_palette_name
-            );
-            df = tercen_rs::color_processor::add_layer_colors(
-                df,
-                self.layer_palette_name.as_deref(),
-            )?;
-            eprintln!("DEBUG: Layer colors added successfully");
-        }
+        let cells = facet_cells(&df)?;
+        let ys = int_column(&df, ".ys")?;
 
-        Ok(ggrs_core::data::DataFrame::from_polars(df))
+        let values: Vec<Option<f64>> = (0..df.height())
+            .map(|i| match self.axis_ranges.get(&cells[i]) {
+                Some((_, AxisData::Numeric(num))) => {
+                    Some(num.min_value + (ys[i] as f64 / 65535.0) * (num.max_value - num.min_value))
+                }
+                _ => None,
+            })
+            .collect();
+
+        let mut df = df;
+        df.with_column(Series::new(name.into(), values))?;
+        Ok(df)
     }
 
     // NOTE: Dequantization now happens in GGRS, not in the operator
@@ -1864,13 +6168,19 @@ impl StreamGenerator for TercenStreamGenerator {
     }
 
     fn n_total_data_rows(&self) -> usize {
+        // Histogram: one row per bar
+        if let Some(ref bars) = self.histogram_data {
+            return bars.nrow();
+        }
         // For heatmaps, return the number of tiles (aggregated data rows)
         // instead of raw data rows
         if let Some((n_cols, n_rows)) = self.heatmap_mode {
             let n_tiles = n_cols * n_rows;
-            eprintln!(
-                "DEBUG: Heatmap mode - returning {} tiles as total rows ({}×{})",
-                n_tiles, n_cols, n_rows
+            crate::debug_log!(
+                "Heatmap mode - returning {} tiles as total rows ({}×{})",
+                n_tiles,
+                n_cols,
+                n_rows
             );
             return n_tiles;
         }
@@ -1881,32 +6191,21 @@ impl StreamGenerator for TercenStreamGenerator {
         use polars::prelude::{NamedFrom, Series};
 
         // Create a Polars Series from the labels
-        let labels: Vec<String> = self
-            .facet_info
-            .col_facets
-            .groups
-            .iter()
-            .map(|group| group.label.clone())
-            .collect();
+        let labels = self.facet_group_labels(
+            self.facet_info
+                .col_facets
+                .groups
+                .iter()
+                .map(|group| group.label.as_str()),
+            "Column",
+        );
 
         if labels.is_empty() {
             return ggrs_core::data::DataFrame::new();
         }
 
-        // Use the first non-empty column name from the facet metadata, or "label" as fallback
-        let column_name = self
-            .facet_info
-            .col_facets
-            .column_names
-            .first()
-            .and_then(|name| {
-                if name.is_empty() {
-                    None
-                } else {
-                    Some(name.clone())
-                }
-            })
-            .unwrap_or_else(|| "label".to_string());
+        // Same variable name as the facet spec
+        let column_name = Self::facet_variable(&self.facet_info.col_facets.column_names, "column");
 
         let series = Series::new(column_name.into(), labels);
         let polars_df = polars::frame::DataFrame::new(vec![series.into_column()])
@@ -1919,32 +6218,21 @@ impl StreamGenerator for TercenStreamGenerator {
         use polars::prelude::{NamedFrom, Series};
 
         // Create a Polars Series from the labels
-        let labels: Vec<String> = self
-            .facet_info
-            .row_facets
-            .groups
-            .iter()
-            .map(|group| group.label.clone())
-            .collect();
+        let labels = self.facet_group_labels(
+            self.facet_info
+                .row_facets
+                .groups
+                .iter()
+                .map(|group| group.label.as_str()),
+            "Row",
+        );
 
         if labels.is_empty() {
             return ggrs_core::data::DataFrame::new();
         }
 
-        // Use the first non-empty column name from the facet metadata, or "label" as fallback
-        let column_name = self
-            .facet_info
-            .row_facets
-            .column_names
-            .first()
-            .and_then(|name| {
-                if name.is_empty() {
-                    None
-                } else {
-                    Some(name.clone())
-                }
-            })
-            .unwrap_or_else(|| "label".to_string());
+        // Same variable name as the facet spec
+        let column_name = Self::facet_variable(&self.facet_info.row_facets.column_names, "row");
 
         let series = Series::new(column_name.into(), labels);
         let polars_df = polars::frame::DataFrame::new(vec![series.into_column()])
@@ -1988,15 +6276,7 @@ impl StreamGenerator for TercenStreamGenerator {
             .get(&(original_col_idx, original_row_idx))
             .map(|(x_axis, _)| x_axis.clone())
             .unwrap_or_else(|| {
-                panic!(
-                    "No X-axis range for cell ({}, {}) [original: ({}, {})]. \
-                    axis_ranges has {} entries. This indicates missing axis range data.",
-                    col_idx,
-                    row_idx,
-                    original_col_idx,
-                    original_row_idx,
-                    self.axis_ranges.len()
-                )
+                self.fallback_axis(col_idx, row_idx, original_col_idx, original_row_idx, true)
             })
     }
 
@@ -2035,21 +6315,24 @@ impl StreamGenerator for TercenStreamGenerator {
             .get(&(original_col_idx, original_row_idx))
             .map(|(_, y_axis)| y_axis.clone())
             .unwrap_or_else(|| {
-                panic!(
-                    "No Y-axis range for cell ({}, {}) [original: ({}, {})]. \
-                    axis_ranges has {} entries. This indicates missing axis range data.",
-                    col_idx,
-                    row_idx,
-                    original_col_idx,
-                    original_row_idx,
-                    self.axis_ranges.len()
-                )
+                self.fallback_axis(col_idx, row_idx, original_col_idx, original_row_idx, false)
             })
     }
 
     fn query_legend_scale(&self) -> LegendScale {
         // Return cached legend scale (loaded during initialization)
-        self.cached_legend_scale.clone()
+        let scale = if self.heatmap_mode.is_none() || self.legend_range == LegendRange::Palette {
+            self.cached_legend_scale.clone()
+        } else {
+            // legend.range = data: aggregate now (cached for the data query) and
+            // narrow continuous sections to the displayed values
+            self.heatmap_data();
+            let ranges = self.heatmap_value_ranges.read().unwrap();
+            Self::apply_legend_value_ranges(self.cached_legend_scale.clone(), &ranges)
+        };
+
+        // Retitle last: value ranges are matched by factor name
+        Self::retitle_legend(scale, &self.legend_titles)
     }
 
     fn query_color_metadata(&self) -> ggrs_core::stream::ColorMetadata {
@@ -2084,10 +6367,13 @@ impl StreamGenerator for TercenStreamGenerator {
     }
 
     fn preferred_chunk_size(&self) -> Option<usize> {
-        // Return the chunk size from operator config
+        // Return the chunk size from operator config, reduced for wide column sets
         // This allows Tercen operator to communicate its optimal chunk size
         // based on gRPC message efficiency and memory constraints
-        Some(self.chunk_size)
+        if self.heatmap_mode.is_some() {
+            return Some(self.chunk_size);
+        }
+        Some(self.effective_chunk_size(self.bulk_columns().len()))
     }
 
     // NOTE: Per-facet streaming not used - GGRS uses bulk mode for faceted plots
@@ -2098,68 +6384,26 @@ impl StreamGenerator for TercenStreamGenerator {
     }
 
     fn query_data_multi_facet(&self, data_range: Range) -> DataFrame {
-        // For heatmaps, aggregate all data by (ci, ri) and return mean values
-        // This ensures the displayed color reflects the aggregate (mean) rather than
-        // the last data point drawn (which would depend on streaming order)
-        if self.heatmap_mode.is_some() {
-            // Check if we already have cached aggregated data
-            {
-                let cache_read = self.heatmap_cached_data.read().unwrap();
-                if cache_read.is_some() {
-                    // Data already aggregated and returned on first call
-                    // Return empty DataFrame for subsequent calls
-                    if data_range.start > 0 {
-                        eprintln!(
-                            "DEBUG: Heatmap data already returned, returning empty for range {}..{}",
-                            data_range.start, data_range.end
-                        );
-                        return DataFrame::new();
-                    }
-                    // Return the cached data on first call
-                    eprintln!("DEBUG: Returning cached aggregated heatmap data");
-                    return cache_read.as_ref().unwrap().clone();
-                }
+        let offset = data_range.start;
+        let cached = self
+            .data_cache
+            .as_ref()
+            .and_then(|cache| cache.lock().unwrap().get(offset));
+        let df = match (cached, &self.data_cache) {
+            (Some(df), _) => df,
+            (None, Some(cache)) => {
+                let df = self.fetch_chunk(data_range);
+                cache.lock().unwrap().insert(offset, df.clone());
+                df
             }
-
-            // First call - aggregate and cache
-            eprintln!("DEBUG: First heatmap data request - aggregating all data");
-            let aggregated = tokio::task::block_in_place(|| {
-                tokio::runtime::Handle::current()
-                    .block_on(async { self.aggregate_heatmap_data().await })
-            })
-            .unwrap_or_else(|e| {
-                panic!(
-                    "Failed to aggregate heatmap data: {}. \
-                    This indicates a data processing error.",
-                    e
-                )
-            });
-
-            // Cache the aggregated data
-            {
-                let mut cache_write = self.heatmap_cached_data.write().unwrap();
-                *cache_write = Some(aggregated.clone());
+            (None, None) => self.fetch_chunk(data_range),
+        };
+        if let Some(ref export) = self.data_export {
+            if let Err(e) = self.record_data_export(export, &df) {
+                crate::warn_log!("Failed to record exported data: {}", e);
             }
-
-            eprintln!(
-                "DEBUG: Returning {} aggregated heatmap rows",
-                aggregated.nrow()
-            );
-            return aggregated;
         }
-
-        // Non-heatmap: stream data as usual
-        tokio::task::block_in_place(|| {
-            tokio::runtime::Handle::current()
-                .block_on(async { self.stream_bulk_data(data_range).await })
-        })
-        .unwrap_or_else(|e| {
-            panic!(
-                "Failed to fetch bulk data from Tercen: {}. \
-                This indicates a network error or invalid table configuration.",
-                e
-            )
-        })
+        df
     }
 
     fn get_original_col_idx(&self, col_idx: usize) -> usize {
@@ -2198,3 +6442,479 @@ impl StreamGenerator for TercenStreamGenerator {
             })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unit_hash_is_reproducible() {
+        for seed in 0..1000 {
+            let u = unit_hash(seed);
+            assert!((0.0..1.0).contains(&u));
+            assert_eq!(u, unit_hash(seed));
+        }
+        assert_ne!(unit_hash(1), unit_hash(2));
+    }
+
+    #[test]
+    fn test_facet_color_levels_color_is_column_facet() {
+        // Column facets B, A, C (original indices 0-2) colored by the same factor
+        let groups = [(0, "B"), (1, "A"), (2, "C")];
+        let levels = vec!["A".to_string(), "B".to_string(), "C".to_string()];
+        let facet_levels = facet_color_levels(groups.into_iter(), &levels);
+
+        assert_eq!(facet_levels[&0], 1);
+        assert_eq!(facet_levels[&1], 0);
+        assert_eq!(facet_levels[&2], 2);
+    }
+
+    #[test]
+    fn test_sort_line_series_by_order() {
+        use polars::prelude::*;
+
+        // Two layers in one cell, interleaved; .order runs against .xs
+        let df = df!(
+            ".ci" => [0i64, 0, 0, 0],
+            ".ri" => [0i64, 0, 0, 0],
+            ".axisIndex" => [1i64, 0, 1, 0],
+            ".xs" => [10i64, 20, 30, 40],
+            ".order" => [2i64, 2, 1, 1],
+        )
+        .unwrap();
+        let sorted = TercenStreamGenerator::sort_line_series(df).unwrap();
+        let xs: Vec<i64> = sorted
+            .column(".xs")
+            .unwrap()
+            .i64()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        assert_eq!(xs, vec![40, 20, 30, 10]);
+
+        // Without .order, points follow .xs
+        let df = df!(
+            ".ci" => [0i64, 0, 0],
+            ".ri" => [0i64, 0, 0],
+            ".xs" => [30i64, 10, 20],
+        )
+        .unwrap();
+        let sorted = TercenStreamGenerator::sort_line_series(df).unwrap();
+        let xs: Vec<i64> = sorted
+            .column(".xs")
+            .unwrap()
+            .i64()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        assert_eq!(xs, vec![10, 20, 30]);
+    }
+
+    #[tokio::test]
+    async fn test_x_ranges_decoded_once_across_pages() {
+        let counter = AtomicUsize::new(0);
+        let fetches = &counter;
+        let fetch = move || async move {
+            fetches.fetch_add(1, Ordering::Relaxed);
+            Ok::<_, Box<dyn std::error::Error>>(CachedXRanges {
+                per_column: true,
+                ranges: HashMap::from([(0, (1.0, 5.0)), (1, (2.0, 8.0))]),
+            })
+        };
+
+        // Three pages sharing the range cache: one decode
+        let cache = Some(new_range_cache());
+        for _ in 0..3 {
+            let x_ranges = cached_x_ranges(&cache, "x_table", fetch).await.unwrap();
+            assert_eq!(x_ranges.ranges[&1], (2.0, 8.0));
+        }
+        assert_eq!(fetches.load(Ordering::Relaxed), 1);
+
+        // Without a cache (single page) every call decodes
+        cached_x_ranges(&None, "x_table", fetch).await.unwrap();
+        assert_eq!(fetches.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn test_pad_zero_width() {
+        assert_eq!(pad_zero_width(0.0, 0.0), (-1.0, 1.0));
+        assert_eq!(pad_zero_width(100.0, 100.0), (95.0, 105.0));
+        assert_eq!(pad_zero_width(-20.0, -20.0), (-21.0, -19.0));
+        // Non-empty and placeholder ranges are left alone
+        assert_eq!(pad_zero_width(1.0, 5.0), (1.0, 5.0));
+        let (min, max) = pad_zero_width(f64::NAN, f64::NAN);
+        assert!(min.is_nan() && max.is_nan());
+    }
+
+    #[test]
+    fn test_quantize_y() {
+        assert_eq!(quantize_y(0.0, (0.0, 10.0)), 0.0);
+        assert_eq!(quantize_y(10.0, (0.0, 10.0)), 65535.0);
+        assert_eq!(quantize_y(5.0, (0.0, 10.0)), 32768.0);
+        // Clamped outside the range; an empty range maps to the middle
+        assert_eq!(quantize_y(20.0, (0.0, 10.0)), 65535.0);
+        assert_eq!(quantize_y(3.0, (1.0, 1.0)), 32767.0);
+    }
+
+    #[test]
+    fn test_retransform_y() {
+        // A linear layer value on a log10 axis spanning 1..1000 (0..3 in log units)
+        let log10 = ValueTransform::Log { base: 10.0 };
+        let ys = retransform_y(100.0, ValueTransform::Identity, log10, (0.0, 3.0));
+        assert_eq!(ys, Some(quantize_y(2.0, (0.0, 3.0))));
+
+        // An asinh layer on a linear axis is mapped back to data units
+        let asinh = ValueTransform::Asinh { cofactor: 5.0 };
+        let ys = retransform_y(
+            asinh.apply(25.0),
+            asinh,
+            ValueTransform::Identity,
+            (0.0, 100.0),
+        );
+        assert_eq!(ys, Some(16384.0));
+
+        // Values with no image on the axis keep their original position
+        assert_eq!(
+            retransform_y(-1.0, ValueTransform::Identity, log10, (0.0, 3.0)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_dedup_columns_name_collision() {
+        // Continuous color factor "conc" that is also the label factor
+        let mut columns: Vec<String> = [".ci", ".ri", ".xs", ".ys", "conc", ".axisIndex", "conc"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        dedup_columns(&mut columns);
+
+        assert_eq!(
+            columns,
+            vec![".ci", ".ri", ".xs", ".ys", "conc", ".axisIndex"]
+        );
+        // The streamed table still has the color factor column for add_color_columns
+        let df = polars::prelude::DataFrame::new(
+            columns
+                .iter()
+                .map(|c| polars::prelude::Column::new(c.as_str().into(), [1.0f64]))
+                .collect(),
+        )
+        .unwrap();
+        assert!(df.column("conc").is_ok());
+    }
+
+    #[test]
+    fn test_facet_sort_order() {
+        // Numeric keys sort by value, not as strings
+        assert_eq!(facet_sort_order(&["10", "2", "1.5"]), vec![2, 1, 0]);
+        // Any non-numeric key sorts all keys alphabetically; ties keep table order
+        assert_eq!(facet_sort_order(&["b", "10", "a", "b"]), vec![1, 2, 0, 3]);
+    }
+
+    #[test]
+    fn test_retain_facet_cells() {
+        let grid = |cols: usize, rows: usize| -> HashMap<(usize, usize), ()> {
+            (0..cols)
+                .flat_map(|c| (0..rows).map(move |r| ((c, r), ())))
+                .collect()
+        };
+
+        // Preview of a 4 × 3 grid: first 2 columns and rows (original indices)
+        let mut cells = grid(4, 3);
+        retain_facet_cells(&mut cells, &[0, 1], &[0, 1]);
+        let mut kept: Vec<_> = cells.into_keys().collect();
+        kept.sort();
+        assert_eq!(kept, vec![(0, 0), (0, 1), (1, 0), (1, 1)]);
+
+        // Sorted facets keep their original indices; no row facets keeps every row
+        let mut cells = grid(5, 1);
+        retain_facet_cells(&mut cells, &[4, 2], &[]);
+        let mut kept: Vec<_> = cells.into_keys().collect();
+        kept.sort();
+        assert_eq!(kept, vec![(2, 0), (4, 0)]);
+    }
+
+    #[test]
+    fn test_prescan_visit() {
+        use polars::prelude::*;
+
+        let request = PrescanRequest {
+            sample_size: Some(2),
+            color_levels: true,
+            color_factors: vec!["conc".to_string(), "absent".to_string()],
+        };
+        let chunk = df!(
+            ".ci" => [0i32, 0, 0, 1],
+            ".ri" => [0i32, 0, 0, 0],
+            ".colorLevels" => [2i32, 0, 2, 5],
+            "conc" => [0.5f64, f64::INFINITY, 1.5, 2.5]
+        )
+        .unwrap();
+
+        // Two chunks of the same table: rows 0-3 and 4-7
+        let mut prescan = Prescan::default();
+        prescan.visit(&request, &chunk, 0).unwrap();
+        prescan.visit(&request, &chunk, 4).unwrap();
+
+        assert_eq!(prescan.counts[&(0, 0)], 6);
+        assert_eq!(prescan.counts[&(1, 0)], 2);
+        assert_eq!(
+            prescan.color_levels,
+            Some([0, 2, 5].into_iter().collect::<BTreeSet<i64>>())
+        );
+        assert_eq!(prescan.color_values["conc"].seen, 6);
+        assert_eq!(
+            prescan.color_values["conc"].values,
+            vec![0.5, 1.5, 2.5, 0.5, 1.5, 2.5]
+        );
+        assert!(!prescan.color_values.contains_key("absent"));
+
+        // Only the cell above the sample size is sampled, from its own rows
+        let sampled = prescan.sampled_rows(2);
+        assert_eq!(sampled.len(), 1);
+        assert_eq!(sampled[&(0, 0)].len(), 2);
+        assert!(sampled[&(0, 0)]
+            .iter()
+            .all(|row| [0, 1, 2, 4, 5, 6].contains(row)));
+    }
+
+    #[test]
+    fn test_facet_cells_defaults_to_zero() {
+        use polars::prelude::*;
+
+        // Null and negative indices count as 0; a missing .ri (no row facets) too
+        let df = df!(".ci" => [Some(2i32), None, Some(-1)]).unwrap();
+        assert_eq!(facet_cells(&df).unwrap(), vec![(2, 0), (0, 0), (0, 0)]);
+        assert_eq!(index_column(&df, ".axisIndex").unwrap(), vec![0, 0, 0]);
+        assert!(int_column(&df, ".ys").is_err());
+    }
+
+    #[test]
+    fn test_value_sample_is_bounded() {
+        // Fits: all values kept in order
+        let mut sample = ValueSample::default();
+        for (row, v) in [3.0, 1.0, 2.0].into_iter().enumerate() {
+            sample.add(10, row, v);
+        }
+        assert_eq!(sample.into_values(), vec![3.0, 1.0, 2.0]);
+
+        // 10k values, 500 kept: exact extremes, quartiles within a tenth of the exact ones
+        let mut sample = ValueSample::default();
+        for row in 0..10_000 {
+            sample.add(500, row, row as f64);
+        }
+        let mut values = sample.into_values();
+        assert_eq!(values.len(), 500);
+        assert_eq!(values[0], 0.0);
+        assert_eq!(values[499], 9_999.0);
+        let (p25, p75) = palette::percentile_range(&mut values, (25.0, 75.0)).unwrap();
+        assert!((1_500.0..3_500.0).contains(&p25), "{}", p25);
+        assert!((6_500.0..8_500.0).contains(&p75), "{}", p75);
+    }
+
+    #[test]
+    fn test_reservoir_sample_is_uniform() {
+        // 100k rows in one cell, 1000 kept: each tenth of the rows gets about a tenth
+        let mut prescan = Prescan::default();
+        for row in 0..100_000 {
+            prescan.add_point(Some(1_000), row, (0, 0));
+        }
+        let rows = &prescan.reservoirs[&(0, 0)];
+        assert_eq!(rows.len(), 1_000);
+        let mut per_decile = [0usize; 10];
+        for &row in rows {
+            per_decile[row / 10_000] += 1;
+        }
+        assert!(
+            per_decile.iter().all(|&n| (50..=150).contains(&n)),
+            "{:?}",
+            per_decile
+        );
+    }
+
+    #[test]
+    fn test_sparse_facets() {
+        // 3 × 2 grid: column 1 is sparse in both rows, row 1 only in columns 0 and 1
+        let counts: HashMap<(usize, usize), usize> = [
+            ((0, 0), 50),
+            ((0, 1), 2),
+            ((1, 0), 3),
+            ((2, 0), 40),
+            ((2, 1), 30),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(
+            sparse_facets(&[0, 1, 2], &[0, 1], &counts, 10),
+            Some((vec![1], vec![]))
+        );
+
+        // Columns only: the sparse ones are dropped
+        let counts: HashMap<(usize, usize), usize> =
+            [((0, 0), 5), ((1, 0), 20)].into_iter().collect();
+        assert_eq!(
+            sparse_facets(&[0, 1, 2], &[], &counts, 10),
+            Some((vec![0, 2], vec![]))
+        );
+
+        // Every facet sparse: keep them all
+        assert_eq!(sparse_facets(&[0, 1, 2], &[], &counts, 100), None);
+        // Nothing sparse
+        assert_eq!(sparse_facets(&[1], &[], &counts, 10), None);
+        // No facets at all
+        assert_eq!(sparse_facets(&[], &[], &counts, 10), None);
+    }
+
+    #[test]
+    fn test_facet_color_levels_unknown_labels() {
+        // Page 2 facets (original indices 3-4), one label missing from the color table
+        let groups = [(3, "A"), (4, "D")];
+        let levels = vec!["A".to_string(), "B".to_string()];
+        let facet_levels = facet_color_levels(groups.into_iter(), &levels);
+
+        assert_eq!(facet_levels[&3], 0);
+        assert_eq!(facet_levels[&4], 2);
+    }
+
+    #[test]
+    fn test_axis_table_int_and_float_columns() {
+        use polars::prelude::*;
+
+        // Count data: integer ranges, float facet index
+        let df = df!(
+            ".ri" => [0.0f64, 1.0],
+            ".minY" => [0i32, 2],
+            ".maxY" => [1.5f64, 4.0]
+        )
+        .unwrap();
+        let df = ggrs_core::data::DataFrame::from_polars(df);
+
+        assert_eq!(
+            TercenStreamGenerator::table_index(&df, 1, ".ri").unwrap(),
+            1
+        );
+        assert_eq!(
+            TercenStreamGenerator::table_f64(&df, 1, ".minY").unwrap(),
+            2.0
+        );
+        assert_eq!(
+            TercenStreamGenerator::table_f64(&df, 0, ".maxY").unwrap(),
+            1.5
+        );
+    }
+
+    #[test]
+    fn test_weighted_mean_expr() {
+        use polars::prelude::*;
+
+        let data = df!(
+            ".ci" => [0i32, 0, 1, 1],
+            "value" => [1.0, 4.0, 2.0, 6.0],
+            "count" => [3.0, 1.0, 0.0, 0.0],
+        )
+        .unwrap();
+        let means = data
+            .lazy()
+            .group_by([col(".ci")])
+            .agg([TercenStreamGenerator::weighted_mean_expr("value", "count").alias("value")])
+            .sort([".ci"], SortMultipleOptions::default())
+            .collect()
+            .unwrap();
+
+        let values: Vec<Option<f64>> = means
+            .column("value")
+            .unwrap()
+            .f64()
+            .unwrap()
+            .into_iter()
+            .collect();
+        // (1*3 + 4*1) / 4, then all-zero weights fall back to the mean
+        assert_eq!(values, vec![Some(1.75), Some(4.0)]);
+    }
+
+    #[test]
+    fn test_retitle_legend() {
+        let titles = LegendTitles {
+            title: None,
+            by_factor: HashMap::from([("ds0.gene".to_string(), "Gene".to_string())]),
+        };
+        let discrete = LegendScale::Discrete {
+            entries: vec![("A".to_string(), [0, 0, 0])],
+            aesthetic_name: "ds0.gene, ds0.group".to_string(),
+        };
+        match TercenStreamGenerator::retitle_legend(discrete.clone(), &titles) {
+            LegendScale::Discrete { aesthetic_name, .. } => {
+                assert_eq!(aesthetic_name, "Gene, ds0.group")
+            }
+            other => panic!("expected a discrete legend, got {:?}", other),
+        }
+
+        let titles = LegendTitles {
+            title: Some("Condition".to_string()),
+            ..titles
+        };
+        match TercenStreamGenerator::retitle_legend(discrete, &titles) {
+            LegendScale::Discrete { aesthetic_name, .. } => assert_eq!(aesthetic_name, "Condition"),
+            other => panic!("expected a discrete legend, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_histogram_bars_replace_axes() {
+        let numeric = |min: f64, max: f64| {
+            AxisData::Numeric(NumericAxisData {
+                min_value: min,
+                max_value: max,
+                min_axis: min,
+                max_axis: max,
+                transform: None,
+            })
+        };
+        let mut axis_ranges = HashMap::from([((0, 0), (numeric(1.0, 5.0), numeric(10.0, 0.0)))]);
+        let counts = HashMap::from([((0, 0), vec![2, 0, 4, 1])]);
+
+        let bars = TercenStreamGenerator::histogram_bars(&counts, 4, &mut axis_ranges).unwrap();
+        assert_eq!(bars.nrow(), 3);
+
+        match &axis_ranges[&(0, 0)] {
+            (AxisData::Numeric(x), AxisData::Numeric(y)) => {
+                assert_eq!((x.min_value, x.max_value), (0.0, 10.0));
+                assert_eq!((y.min_value, y.max_value), (0.0, 4.0));
+            }
+            _ => panic!("expected numeric axes"),
+        }
+    }
+
+    #[test]
+    fn test_log_x_linear_y_transforms() {
+        let numeric = |min: f64, max: f64| {
+            AxisData::Numeric(NumericAxisData {
+                min_value: min,
+                max_value: max,
+                min_axis: min,
+                max_axis: max,
+                transform: None,
+            })
+        };
+        let mut axis_ranges = HashMap::from([
+            ((0, 0), (numeric(0.0, 3.0), numeric(-5.0, 5.0))),
+            ((1, 0), (numeric(1.0, 2.0), numeric(0.0, 1.0))),
+        ]);
+        let x_transform = parse_transform("log:base=10").unwrap();
+        assert!(x_transform.is_some());
+
+        TercenStreamGenerator::apply_axis_transforms(&mut axis_ranges, &x_transform, &None);
+
+        for (x, y) in axis_ranges.values() {
+            match (x, y) {
+                (AxisData::Numeric(x), AxisData::Numeric(y)) => {
+                    let x_parameters = x.transform.as_ref().map(|t| t.parameters.clone());
+                    assert_eq!(x_parameters, Some(vec![10.0]));
+                    assert!(y.transform.is_none());
+                }
+                _ => panic!("expected numeric axes"),
+            }
+        }
+    }
+}
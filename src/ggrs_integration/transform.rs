@@ -0,0 +1,354 @@
+//! Axis transform specification parsing
+//!
+//! Transform strings come from the Tercen model (`ctx.y_transform()`) or from the
+//! `axis.y.transform` / `axis.x.transform` overrides. Besides the bare names understood
+//! by GGRS (`log`, `asinh`, `logicle`, ...), parameters can be given after a colon:
+//!
+//! - `asinh:cofactor=150` → GGRS `asinh` transform with `parameters: vec![150.0]`
+//! - `log2` or `log:base=2` → GGRS `log` transform with `parameters: vec![2.0]`
+//!   (inverse is `base^x`)
+//! - `sqrt:negative=shift` → sqrt with a negative value policy (see [`sqrt_policy`])
+//!
+//! GGRS transforms are `Transform { transform_type, parameters }` values: the type
+//! comes from `Transform::parse` on the bare name and the parameters are set here.
+//!
+//! Parameters must match Tercen's forward transform, otherwise dequantized values
+//! and tick positions will be wrong.
+//!
+//! Values the operator re-expresses itself (per-layer Y transforms) go through
+//! [`ValueTransform`], which computes the forward and inverse functions locally
+//! from the same spec instead of relying on GGRS.
+
+use crate::config::SqrtNegativePolicy;
+use ggrs_core::stream::{NumericAxisData, Transform};
+
+/// Parse a transform specification into a GGRS Transform
+///
+/// Returns Ok(None) for an empty spec or a bare name GGRS doesn't recognize
+/// (same as `Transform::parse`). Errors on malformed or unsupported parameters.
+pub fn parse_transform(spec: &str) -> Result<Option<Transform>, String> {
+    let spec = spec.trim();
+    if spec.is_empty() {
+        return Ok(None);
+    }
+
+    let Some((name, params)) = spec.split_once(':') else {
+        if spec.eq_ignore_ascii_case("log2") {
+            return with_parameters(spec, "log", vec![2.0]).map(Some);
+        }
+        return Ok(Transform::parse(spec));
+    };
+    let name = name.trim().to_lowercase();
+    let params = parse_params(spec, params)?;
+
+    match name.as_str() {
+        "asinh" => {
+            let mut cofactor = None;
+            for (key, value) in params {
+                match key.as_str() {
+                    "cofactor" => cofactor = Some(parse_positive(spec, &key, &value)?),
+                    _ => return Err(unknown_param(spec, &key)),
+                }
+            }
+            let cofactor = cofactor.ok_or_else(|| {
+                format!(
+                    "Transform '{}' is missing 'cofactor'. Expected e.g. 'asinh:cofactor=150'.",
+                    spec
+                )
+            })?;
+            with_parameters(spec, "asinh", vec![cofactor]).map(Some)
+        }
+        "log" => {
+            let mut base = None;
+            for (key, value) in params {
+                match key.as_str() {
+                    "base" => base = Some(parse_positive(spec, &key, &value)?),
+                    _ => return Err(unknown_param(spec, &key)),
+                }
+            }
+            match base {
+                Some(b) if b != 1.0 => with_parameters(spec, "log", vec![b]).map(Some),
+                Some(_) => Err(format!("Transform '{}': log base cannot be 1.", spec)),
+                None => Err(format!(
+                    "Transform '{}' is missing 'base'. Expected e.g. 'log:base=2'.",
+                    spec
+                )),
+            }
+        }
+        "sqrt" => {
+            // Only the negative value policy; validated by sqrt_policy()
+            sqrt_policy(spec, SqrtNegativePolicy::default())?;
+            Ok(Transform::parse("sqrt"))
+        }
+        _ => Err(format!(
+            "Transform '{}' does not accept parameters \
+             (supported: asinh:cofactor=N, log:base=N, sqrt:negative=clamp|shift|signed).",
+            spec
+        )),
+    }
+}
+
+/// GGRS transform `name` carrying `parameters` (e.g. the asinh cofactor)
+fn with_parameters(spec: &str, name: &str, parameters: Vec<f64>) -> Result<Transform, String> {
+    let mut transform = Transform::parse(name)
+        .ok_or_else(|| format!("Transform '{}': GGRS has no '{}' transform.", spec, name))?;
+    transform.parameters = parameters;
+    Ok(transform)
+}
+
+/// Forward and inverse function of a transform spec, computed by the operator
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ValueTransform {
+    /// Untransformed values (empty spec, `linear`, or a name GGRS doesn't know)
+    Identity,
+    /// `log` / `ln` (base e), `log2`, `log10`, `log:base=N`
+    Log { base: f64 },
+    /// `asinh(x / cofactor)`; a bare `asinh` uses GGRS's cofactor, or 1
+    Asinh { cofactor: f64 },
+    /// Signed square root, so values below zero stay on their side of the axis
+    Sqrt,
+}
+
+impl ValueTransform {
+    /// Parse a transform spec, with the same validation as [`parse_transform`]
+    ///
+    /// Returns Ok(None) for transforms GGRS knows but the operator has no local
+    /// functions for (e.g. `logicle`).
+    pub fn parse(spec: &str) -> Result<Option<Self>, String> {
+        let Some(transform) = parse_transform(spec)? else {
+            return Ok(Some(Self::Identity));
+        };
+        let parameter = transform.parameters.first().copied();
+        let name = spec.split(':').next().unwrap_or("").trim().to_lowercase();
+        Ok(match name.as_str() {
+            "linear" | "identity" => Some(Self::Identity),
+            "log" | "ln" => Some(Self::Log {
+                base: parameter.unwrap_or(std::f64::consts::E),
+            }),
+            "log2" => Some(Self::Log { base: 2.0 }),
+            "log10" => Some(Self::Log { base: 10.0 }),
+            "asinh" => Some(Self::Asinh {
+                cofactor: parameter.unwrap_or(1.0),
+            }),
+            "sqrt" => Some(Self::Sqrt),
+            _ => None,
+        })
+    }
+
+    /// Data units → transformed units
+    pub fn apply(self, v: f64) -> f64 {
+        match self {
+            Self::Identity => v,
+            Self::Log { base } => v.log(base),
+            Self::Asinh { cofactor } => (v / cofactor).asinh(),
+            Self::Sqrt => v.signum() * v.abs().sqrt(),
+        }
+    }
+
+    /// Transformed units → data units
+    pub fn inverse(self, v: f64) -> f64 {
+        match self {
+            Self::Identity => v,
+            Self::Log { base } => base.powf(v),
+            Self::Asinh { cofactor } => v.sinh() * cofactor,
+            Self::Sqrt => v.signum() * v * v,
+        }
+    }
+}
+
+/// Negative value policy for a sqrt transform spec
+///
+/// Returns None if the spec is not a sqrt transform, otherwise the policy from
+/// `sqrt:negative=...` or `default` when no parameter is given.
+pub fn sqrt_policy(
+    spec: &str,
+    default: SqrtNegativePolicy,
+) -> Result<Option<SqrtNegativePolicy>, String> {
+    let spec = spec.trim();
+    let (name, params) = spec.split_once(':').unwrap_or((spec, ""));
+    if !name.trim().eq_ignore_ascii_case("sqrt") {
+        return Ok(None);
+    }
+
+    let mut policy = default;
+    for (key, value) in parse_params(spec, params)? {
+        match key.as_str() {
+            "negative" => match value.to_lowercase().as_str() {
+                "clamp" | "shift" | "signed" => policy = SqrtNegativePolicy::parse(&value),
+                _ => {
+                    return Err(format!(
+                        "Invalid value '{}' for 'negative' in transform '{}'. \
+                         Expected clamp, shift or signed.",
+                        value, spec
+                    ))
+                }
+            },
+            _ => return Err(unknown_param(spec, &key)),
+        }
+    }
+    Ok(Some(policy))
+}
+
+/// Apply a sqrt negative value policy to an axis range with negative bounds
+///
+/// Ranges whose lower bound is already >= 0 are left untouched.
+pub fn apply_sqrt_policy(num: &mut NumericAxisData, policy: SqrtNegativePolicy) {
+    if !num.min_value.is_finite() {
+        // No usable lower bound (sqrt of negatives upstream): start at 0
+        num.min_value = 0.0;
+        num.min_axis = num.min_axis.max(0.0);
+    }
+    if num.min_value >= 0.0 && num.min_axis >= 0.0 {
+        return;
+    }
+
+    match policy {
+        SqrtNegativePolicy::Clamp => {
+            // Display only; dequantization still uses min_value/max_value
+            num.min_axis = num.min_axis.max(0.0);
+        }
+        SqrtNegativePolicy::Shift => {
+            // Dequantization is linear in min_value/max_value, so shifting both
+            // shifts every point by the same amount
+            let shift = -num.min_value.min(num.min_axis);
+            num.min_value += shift;
+            num.max_value += shift;
+            num.min_axis += shift;
+            num.max_axis += shift;
+        }
+        SqrtNegativePolicy::SignedSqrt => {
+            // Squaring would fold negatives onto positives: keep sqrt units
+            num.transform = None;
+        }
+    }
+}
+
+/// Split "key=value,key=value" into lowercase keys and raw values
+fn parse_params(spec: &str, params: &str) -> Result<Vec<(String, String)>, String> {
+    params
+        .split(',')
+        .filter(|p| !p.trim().is_empty())
+        .map(|p| {
+            p.split_once('=')
+                .map(|(k, v)| (k.trim().to_lowercase(), v.trim().to_string()))
+                .ok_or_else(|| {
+                    format!(
+                        "Invalid transform parameter '{}' in '{}'. Expected 'key=value'.",
+                        p.trim(),
+                        spec
+                    )
+                })
+        })
+        .collect()
+}
+
+/// Parse a strictly positive, finite number
+fn parse_positive(spec: &str, key: &str, value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(v) if v.is_finite() && v > 0.0 => Ok(v),
+        _ => Err(format!(
+            "Invalid value '{}' for '{}' in transform '{}'. Expected a positive number.",
+            value, key, spec
+        )),
+    }
+}
+
+fn unknown_param(spec: &str, key: &str) -> String {
+    format!("Unknown parameter '{}' in transform '{}'.", key, spec)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parameters(t: Option<Transform>) -> Vec<f64> {
+        t.expect("transform").parameters
+    }
+
+    #[test]
+    fn test_asinh_cofactor() {
+        let t = parse_transform("asinh:cofactor=150").unwrap();
+        assert_eq!(parameters(t), vec![150.0]);
+
+        let t = parse_transform(" ASINH : cofactor = 5 ").unwrap();
+        assert_eq!(parameters(t), vec![5.0]);
+    }
+
+    #[test]
+    fn test_log_base() {
+        let t = parse_transform("log2").unwrap();
+        assert_eq!(parameters(t), vec![2.0]);
+
+        let t = parse_transform("log:base=3").unwrap();
+        assert_eq!(parameters(t), vec![3.0]);
+
+        assert!(parse_transform("log:base=1").is_err());
+        assert!(parse_transform("log:base=-2").is_err());
+    }
+
+    #[test]
+    fn test_invalid_params() {
+        assert!(parse_transform("asinh:cofactor=0").is_err());
+        assert!(parse_transform("asinh:cofactor=abc").is_err());
+        assert!(parse_transform("asinh:scale=2").is_err());
+        assert!(parse_transform("asinh:cofactor").is_err());
+        assert!(parse_transform("logicle:cofactor=5").is_err());
+    }
+
+    #[test]
+    fn test_sqrt_policy() {
+        let default = SqrtNegativePolicy::Clamp;
+        assert_eq!(sqrt_policy("log", default).unwrap(), None);
+        assert_eq!(sqrt_policy("sqrt", default).unwrap(), Some(default));
+        assert_eq!(
+            sqrt_policy("sqrt:negative=shift", default).unwrap(),
+            Some(SqrtNegativePolicy::Shift)
+        );
+        assert!(sqrt_policy("sqrt:negative=abs", default).is_err());
+
+        let mut num = NumericAxisData {
+            min_value: -2.0,
+            max_value: 8.0,
+            min_axis: -2.0,
+            max_axis: 8.0,
+            transform: None,
+        };
+        apply_sqrt_policy(&mut num, SqrtNegativePolicy::Shift);
+        assert_eq!((num.min_value, num.max_value), (0.0, 10.0));
+        assert_eq!((num.min_axis, num.max_axis), (0.0, 10.0));
+    }
+
+    #[test]
+    fn test_value_transform_roundtrip() {
+        let log2 = ValueTransform::parse("log2").unwrap().unwrap();
+        assert_eq!(log2, ValueTransform::Log { base: 2.0 });
+        assert!((log2.apply(8.0) - 3.0).abs() < 1e-12);
+
+        let asinh = ValueTransform::parse("asinh:cofactor=150")
+            .unwrap()
+            .unwrap();
+        assert_eq!(asinh, ValueTransform::Asinh { cofactor: 150.0 });
+
+        let sqrt = ValueTransform::parse("sqrt:negative=signed")
+            .unwrap()
+            .unwrap();
+        assert_eq!(sqrt.apply(-9.0), -3.0);
+
+        for t in [log2, asinh, sqrt] {
+            for v in [0.5, 3.0, 1000.0] {
+                assert!((t.inverse(t.apply(v)) - v).abs() < 1e-9 * v.max(1.0));
+            }
+        }
+        assert_eq!(
+            ValueTransform::parse("").unwrap(),
+            Some(ValueTransform::Identity)
+        );
+        assert!(ValueTransform::parse("log:base=1").is_err());
+    }
+
+    #[test]
+    fn test_empty_spec() {
+        assert!(parse_transform("").unwrap().is_none());
+        assert!(parse_transform("   ").unwrap().is_none());
+    }
+}
@@ -5,6 +5,8 @@
 
 pub mod config;
 pub mod ggrs_integration;
+pub mod logging;
 pub mod memprof;
 pub mod operator_props;
 pub mod pipeline;
+pub mod retry;
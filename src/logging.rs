@@ -0,0 +1,133 @@
+//! Log level control for operator diagnostics
+//!
+//! The level is set once from `OperatorConfig.log_level` (property `log.level`)
+//! and checked by the `debug_log!` / `info_log!` / `warn_log!` macros, which all
+//! operator output goes through:
+//! - `error`: only errors (returned as `Err` and printed by the entry point)
+//! - `info`: progress messages and `WARNING:` lines (default)
+//! - `debug`: the above plus `DEBUG:` diagnostics
+//!
+//! Long renders also emit `PROGRESS:` milestone lines (`progress_log!`) at the
+//! `info` level, so the task log shows how far a render got. When a task log is set
+//! (`set_task_log`), those lines and the phase timing summary are also sent to it;
+//! the production entry point forwards them through `TercenLogger`.
+
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Mutex;
+use tokio::sync::mpsc::UnboundedSender;
+
+/// Operator log verbosity
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum LogLevel {
+    /// Errors only
+    Error,
+    /// Progress messages (default)
+    #[default]
+    Info,
+    /// Progress messages and debug diagnostics
+    Debug,
+}
+
+impl LogLevel {
+    /// Parse from string value
+    ///
+    /// This is an internal enum - validation happens in OperatorPropertyReader.get_enum()
+    pub fn parse(s: &str) -> Self {
+        match s.to_lowercase().as_str() {
+            "error" => Self::Error,
+            "debug" => Self::Debug,
+            _ => Self::Info, // "info" or any other value
+        }
+    }
+}
+
+static LEVEL: AtomicU8 = AtomicU8::new(LogLevel::Info as u8);
+
+/// Set the global log level
+pub fn set_level(level: LogLevel) {
+    LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+/// Get the global log level
+pub fn level() -> LogLevel {
+    match LEVEL.load(Ordering::Relaxed) {
+        0 => LogLevel::Error,
+        1 => LogLevel::Info,
+        _ => LogLevel::Debug,
+    }
+}
+
+/// True when `DEBUG:` diagnostics should be printed
+pub fn debug_enabled() -> bool {
+    level() >= LogLevel::Debug
+}
+
+/// True when progress messages should be printed
+pub fn info_enabled() -> bool {
+    level() >= LogLevel::Info
+}
+
+static TASK_LOG: Mutex<Option<UnboundedSender<String>>> = Mutex::new(None);
+
+/// Set (or with None, close) the task log that progress lines are also sent to
+pub fn set_task_log(sender: Option<UnboundedSender<String>>) {
+    *TASK_LOG.lock().unwrap() = sender;
+}
+
+/// Send a line to the task log, if one is set
+pub fn task_log(line: String) {
+    if let Some(sender) = TASK_LOG.lock().unwrap().as_ref() {
+        let _ = sender.send(line);
+    }
+}
+
+/// Percentage of `done` over `total`, clamped to 0-100 (100 when `total` is 0)
+pub fn percent(done: usize, total: usize) -> u32 {
+    if total == 0 {
+        return 100;
+    }
+    ((done as f64 / total as f64) * 100.0).min(100.0) as u32
+}
+
+/// Print a `DEBUG:` line to stderr when the log level is `debug`
+#[macro_export]
+macro_rules! debug_log {
+    ($($arg:tt)*) => {
+        if $crate::logging::debug_enabled() {
+            eprintln!("DEBUG: {}", format_args!($($arg)*));
+        }
+    };
+}
+
+/// Print a progress line to stdout when the log level is `info` or `debug`
+#[macro_export]
+macro_rules! info_log {
+    ($($arg:tt)*) => {
+        if $crate::logging::info_enabled() {
+            println!($($arg)*);
+        }
+    };
+}
+
+/// Print a `WARNING:` line to stderr when the log level is `info` or `debug`
+#[macro_export]
+macro_rules! warn_log {
+    ($($arg:tt)*) => {
+        if $crate::logging::info_enabled() {
+            eprintln!("WARNING: {}", format_args!($($arg)*));
+        }
+    };
+}
+
+/// Print a `PROGRESS:` milestone line to stdout and the task log when the log level
+/// is `info` or `debug`
+#[macro_export]
+macro_rules! progress_log {
+    ($($arg:tt)*) => {
+        if $crate::logging::info_enabled() {
+            let line = format!("PROGRESS: {}", format_args!($($arg)*));
+            println!("{}", line);
+            $crate::logging::task_log(line);
+        }
+    };
+}
@@ -10,10 +10,13 @@
 
 pub mod config;
 pub mod ggrs_integration;
+pub mod logging;
 pub mod memprof;
 pub mod operator_props;
 pub mod pipeline;
+pub mod retry;
 
+use memprof::PhaseTimer;
 use tercen_rs::TercenContext;
 
 #[cfg(feature = "jemalloc")]
@@ -23,39 +26,101 @@ use tikv_jemallocator::Jemalloc;
 #[global_allocator]
 static GLOBAL: Jemalloc = Jemalloc;
 
-#[tokio::main]
-async fn main() {
-    println!("GGRS Plot Operator v{}", env!("CARGO_PKG_VERSION"));
-    println!("Ready to generate high-performance plots!\n");
+fn main() {
+    crate::info_log!("GGRS Plot Operator v{}", env!("CARGO_PKG_VERSION"));
+    crate::info_log!("Ready to generate high-performance plots!\n");
 
+    let single_thread = std::env::var("GGRS_SINGLE_THREAD").is_ok_and(|v| v == "1");
+    build_runtime(single_thread).block_on(run());
+}
+
+/// Build the tokio runtime, single-threaded when asked or where worker threads
+/// can't be spawned
+///
+/// Without `single_thread` the multi-threaded runtime is used, unless a probe
+/// thread or the runtime itself fails to spawn (sandboxes reporting "Operation not
+/// permitted"). On the single-threaded runtime each page's data is fetched before
+/// it renders (see `TercenStreamGenerator::preload`), so the render runs serially
+/// on the calling thread.
+fn build_runtime(single_thread: bool) -> tokio::runtime::Runtime {
+    if !single_thread {
+        match std::thread::Builder::new().spawn(|| {}) {
+            Ok(probe) => {
+                let _ = probe.join();
+                match tokio::runtime::Builder::new_multi_thread()
+                    .enable_all()
+                    .build()
+                {
+                    Ok(runtime) => return runtime,
+                    Err(e) => crate::warn_log!("Multi-threaded runtime unavailable: {}", e),
+                }
+            }
+            Err(e) => crate::warn_log!("Cannot spawn worker threads: {}", e),
+        }
+    }
+
+    crate::info_log!("Running single-threaded (serial rendering)\n");
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("failed to build single-threaded tokio runtime")
+}
+
+/// Connect to Tercen and process the task from the environment
+async fn run() {
     // Parse command-line arguments (Tercen passes --taskId, --serviceUri, --token)
     let args: Vec<String> = std::env::args().collect();
     parse_args(&args);
+    // --validate: check the step configuration without streaming data or rendering
+    let validate_only = args.iter().any(|a| a == "--validate");
 
     // Print environment info
     print_env_info();
 
     // Connect to Tercen
-    println!("Attempting to connect to Tercen...");
+    let mut timer = PhaseTimer::new();
+    timer.phase("connect");
+    if let Ok(uri) = std::env::var("TERCEN_URI") {
+        if let Err(e) = validate_tercen_uri(&uri) {
+            eprintln!("✗ Invalid TERCEN_URI '{}': {}", uri, e);
+            eprintln!("  TERCEN_URI must be like https://host:5400");
+            std::process::exit(1);
+        }
+    }
+    crate::info_log!("Attempting to connect to Tercen...");
     match tercen_rs::TercenClient::from_env().await {
         Ok(client) => {
-            println!("✓ Successfully connected to Tercen!\n");
+            crate::info_log!("✓ Successfully connected to Tercen!\n");
 
             let client_arc = std::sync::Arc::new(client);
 
             // Process task if TERCEN_TASK_ID is set
             if let Ok(task_id) = std::env::var("TERCEN_TASK_ID") {
-                match process_task(client_arc.clone(), &task_id).await {
+                let task_logger = spawn_task_logger(client_arc.clone(), &task_id);
+                let mut step_ref = None;
+                let result = process_task(
+                    client_arc.clone(),
+                    &task_id,
+                    validate_only,
+                    &mut timer,
+                    &mut step_ref,
+                )
+                .await;
+                timer.log_summary();
+                logging::set_task_log(None);
+                let _ = task_logger.await;
+                match result {
                     Ok(()) => {
-                        println!("\n✓ Task processed successfully!");
+                        crate::info_log!("\n✓ Task processed successfully!");
                     }
                     Err(e) => {
                         eprintln!("\n✗ Task processing failed: {}", e);
+                        report_cube_query_state(&client_arc, &task_id, step_ref).await;
                         std::process::exit(1);
                     }
                 }
             } else {
-                println!("No TERCEN_TASK_ID set, skipping task processing");
+                crate::info_log!("No TERCEN_TASK_ID set, skipping task processing");
             }
         }
         Err(e) => {
@@ -68,7 +133,31 @@ async fn main() {
         }
     }
 
-    println!("\nOperator completed!");
+    crate::info_log!("\nOperator completed!");
+}
+
+/// Forward `PROGRESS:` lines and the phase timing summary to the task log
+///
+/// Each line is sent as a log message through `TercenLogger` (it is also printed to
+/// stdout). The first failed send, e.g. while `EventService.create()` is unimplemented
+/// on the server, is reported once and closes the task log. Close the task log and
+/// await the returned handle to flush the remaining lines.
+fn spawn_task_logger(
+    client: std::sync::Arc<tercen_rs::TercenClient>,
+    task_id: &str,
+) -> tokio::task::JoinHandle<()> {
+    let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel::<String>();
+    logging::set_task_log(Some(sender));
+    let logger = tercen_rs::TercenLogger::new(client, task_id.to_string());
+    tokio::spawn(async move {
+        while let Some(line) = receiver.recv().await {
+            if let Err(e) = logger.log(&line).await {
+                crate::warn_log!("Task log unavailable, progress is printed only: {}", e);
+                logging::set_task_log(None);
+                break;
+            }
+        }
+    })
 }
 
 /// Parse command-line arguments and set environment variables
@@ -93,52 +182,193 @@ fn parse_args(args: &[String]) {
     }
 }
 
+/// Check that a Tercen URI has a scheme, host and port before connecting
+///
+/// A malformed URI otherwise surfaces as an opaque transport error from the
+/// gRPC connect. IPv6 hosts are bracketed (`http://[::1]:5400`).
+fn validate_tercen_uri(uri: &str) -> Result<(), String> {
+    let (scheme, rest) = uri
+        .split_once("://")
+        .ok_or_else(|| "missing scheme (http:// or https://)".to_string())?;
+    if scheme != "http" && scheme != "https" {
+        return Err(format!("unsupported scheme '{}'", scheme));
+    }
+    let authority = rest.split('/').next().unwrap_or_default();
+    let (host, port) = match authority.strip_prefix('[') {
+        Some(bracketed) => {
+            let (host, after) = bracketed
+                .split_once(']')
+                .ok_or_else(|| "unclosed '[' in IPv6 host".to_string())?;
+            let port = after
+                .strip_prefix(':')
+                .ok_or_else(|| "missing port".to_string())?;
+            (host, port)
+        }
+        None => authority
+            .rsplit_once(':')
+            .ok_or_else(|| "missing port".to_string())?,
+    };
+    if host.is_empty() {
+        return Err("missing host".to_string());
+    }
+    if port.parse::<u16>().is_err() {
+        return Err(format!("invalid port '{}'", port));
+    }
+    Ok(())
+}
+
 /// Print environment info for debugging
 fn print_env_info() {
     if let Ok(task_id) = std::env::var("TERCEN_TASK_ID") {
-        println!("TERCEN_TASK_ID: {}", task_id);
+        crate::info_log!("TERCEN_TASK_ID: {}", task_id);
     } else {
-        println!("TERCEN_TASK_ID not set");
+        crate::info_log!("TERCEN_TASK_ID not set");
     }
 
     if let Ok(uri) = std::env::var("TERCEN_URI") {
-        println!("TERCEN_URI: {}", uri);
+        crate::info_log!("TERCEN_URI: {}", uri);
     } else {
-        println!("TERCEN_URI not set");
+        crate::info_log!("TERCEN_URI not set");
     }
 
     if let Ok(token) = std::env::var("TERCEN_TOKEN") {
-        println!(
+        crate::info_log!(
             "TERCEN_TOKEN: {}...{}",
             &token[..8.min(token.len())],
             if token.len() > 8 { "***" } else { "" }
         );
     } else {
-        println!("TERCEN_TOKEN not set");
+        crate::info_log!("TERCEN_TOKEN not set");
+    }
+    crate::info_log!();
+}
+
+/// Task state as reported by Tercen
+#[derive(Debug)]
+enum TaskState {
+    Done,
+    Failed(String),
+    Running,
+    Init,
+    Other(String),
+}
+
+impl std::fmt::Display for TaskState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TaskState::Done => write!(f, "DoneState"),
+            TaskState::Failed(reason) => write!(f, "FailedState: {}", reason),
+            TaskState::Running => write!(f, "RunningState"),
+            TaskState::Init => write!(f, "InitState"),
+            TaskState::Other(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+/// Fetch a task and decode its state (including the failure reason)
+async fn get_task_info(
+    client: &tercen_rs::TercenClient,
+    task_id: &str,
+) -> Result<TaskState, Box<dyn std::error::Error>> {
+    use tercen_rs::client::proto::{e_state, e_task, GetRequest};
+
+    let mut task_service = client.task_service()?;
+    let task = task_service
+        .get(tonic::Request::new(GetRequest {
+            id: task_id.to_string(),
+            ..Default::default()
+        }))
+        .await?
+        .into_inner();
+
+    let state = match &task.object {
+        Some(e_task::Object::Runcomputationtask(t)) => t.state.as_ref(),
+        Some(e_task::Object::Computationtask(t)) => t.state.as_ref(),
+        Some(e_task::Object::Cubequerytask(t)) => t.state.as_ref(),
+        Some(_) => return Ok(TaskState::Other("UnsupportedTaskType".to_string())),
+        None => return Err(format!("Task {} has no object", task_id).into()),
+    };
+
+    Ok(match state.and_then(|s| s.object.as_ref()) {
+        Some(e_state::Object::Donestate(_)) => TaskState::Done,
+        Some(e_state::Object::Failedstate(fs)) => TaskState::Failed(fs.reason.clone()),
+        Some(e_state::Object::Runningstate(_)) => TaskState::Running,
+        Some(e_state::Object::Initstate(_)) => TaskState::Init,
+        Some(other) => TaskState::Other(format!("{:?}", other)),
+        None => TaskState::Other("NoState".to_string()),
+    })
+}
+
+/// Report the state of the CubeQueryTask behind a failed operator task
+///
+/// The CubeQueryTask computing the crosstab data is referenced by the data step's
+/// model (`step_ref` = workflow and step ids, known once the task context loaded).
+/// Its failure reason is usually what the user needs. Without a step reference,
+/// falls back to the operator task's own state.
+async fn report_cube_query_state(
+    client: &tercen_rs::TercenClient,
+    task_id: &str,
+    step_ref: Option<(String, String)>,
+) {
+    let Some((workflow_id, step_id)) = step_ref else {
+        match get_task_info(client, task_id).await {
+            Ok(state) => eprintln!("  Operator task state: {}", state),
+            Err(e) => eprintln!("  Could not retrieve task state: {}", e),
+        }
+        return;
+    };
+
+    match pipeline::cube_query_task_id(client, &workflow_id, &step_id).await {
+        Ok(Some(cube_task_id)) => match get_task_info(client, &cube_task_id).await {
+            Ok(state) => eprintln!("  CubeQueryTask {} state: {}", cube_task_id, state),
+            Err(e) => eprintln!("  Could not retrieve CubeQueryTask state: {}", e),
+        },
+        Ok(None) => eprintln!("  Step {} has no CubeQueryTask", step_id),
+        Err(e) => eprintln!("  Could not resolve CubeQueryTask: {}", e),
     }
-    println!();
 }
 
 /// Process a Tercen task: fetch data, generate plot, upload result
+///
+/// Each step is recorded as a phase of `timer`. Once the task context is loaded,
+/// `step_ref` holds its workflow and step ids for failure reporting. With `validate_only`, stops after
+/// checking the context and configuration (nothing is rendered or uploaded).
 async fn process_task(
     client_arc: std::sync::Arc<tercen_rs::TercenClient>,
     task_id: &str,
+    validate_only: bool,
+    timer: &mut PhaseTimer,
+    step_ref: &mut Option<(String, String)>,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    println!("=== Task Processing Started ===");
-    println!("Task ID: {}\n", task_id);
+    crate::info_log!("=== Task Processing Started ===");
+    crate::info_log!("Task ID: {}\n", task_id);
 
     // Create ProductionContext
+    timer.phase("load task context");
     let ctx = tercen_rs::ProductionContext::from_task_id(client_arc.clone(), task_id).await?;
+    *step_ref = Some((ctx.workflow_id().to_string(), ctx.step_id().to_string()));
 
     // Load configuration
+    // Chart point opacity is not exposed by ProductionContext yet (see
+    // docs/07_RUST_CONTEXT_IMPL.md), so only the operator property applies
     let config =
-        config::OperatorConfig::from_properties(ctx.operator_settings(), ctx.point_size())?;
+        config::OperatorConfig::from_properties(ctx.operator_settings(), ctx.point_size(), None)?;
+    logging::set_level(config.log_level);
+
+    if validate_only {
+        timer.phase("validate");
+        pipeline::validate_context(&ctx, &config).await?;
+        crate::info_log!("\n=== Validation Complete (nothing rendered) ===");
+        return Ok(());
+    }
 
     // Generate plots using shared pipeline
-    let plot_results = pipeline::generate_plots(&ctx, &config).await?;
+    timer.phase("render plots");
+    let output = pipeline::generate_plots(&ctx, &config).await?;
 
     // Upload results to Tercen
-    println!("\n[5/5] Uploading result(s) to Tercen...");
+    crate::info_log!("\n[5/5] Uploading result(s) to Tercen...");
+    timer.phase("upload results");
 
     let mut task_service = client_arc.task_service()?;
     let request = tonic::Request::new(tercen_rs::client::proto::GetRequest {
@@ -148,8 +378,8 @@ async fn process_task(
     let response = task_service.get(request).await?;
     let mut task = response.into_inner();
 
-    if plot_results.len() == 1 {
-        let plot = plot_results.into_iter().next().unwrap();
+    if output.plots.len() == 1 && output.data_files.is_empty() {
+        let plot = output.plots.into_iter().next().unwrap();
         tercen_rs::result::save_result(
             client_arc.clone(),
             ctx.project_id(),
@@ -162,11 +392,11 @@ async fn process_task(
             &mut task,
         )
         .await?;
-        println!("✓ Result uploaded and linked successfully");
+        crate::info_log!("✓ Result uploaded and linked successfully");
     } else {
-        println!("  Uploading {} plots...", plot_results.len());
-        for plot in &plot_results {
-            println!(
+        crate::info_log!("  Uploading {} plots...", output.plots.len());
+        for plot in &output.plots {
+            crate::info_log!(
                 "    - {}: {} bytes ({}×{})",
                 plot.label,
                 plot.png_buffer.len(),
@@ -174,6 +404,19 @@ async fn process_task(
                 plot.height
             );
         }
+        let mut plot_results = output.plots;
+        if !output.data_files.is_empty() {
+            crate::info_log!("  Uploading {} data file(s)...", output.data_files.len());
+            for file in &output.data_files {
+                crate::info_log!(
+                    "    - {}.{}: {} bytes",
+                    file.filename,
+                    file.extension,
+                    file.content.len()
+                );
+            }
+            plot_results.extend(output.data_files.into_iter().map(data_file_result));
+        }
 
         tercen_rs::result::save_results(
             client_arc.clone(),
@@ -183,9 +426,106 @@ async fn process_task(
             &mut task,
         )
         .await?;
-        println!("✓ All plots uploaded successfully");
+        crate::info_log!("✓ All plots uploaded successfully");
     }
 
-    println!("\n=== Task Processing Complete ===");
+    crate::info_log!("\n=== Task Processing Complete ===");
     Ok(())
 }
+
+/// Result row of a data file
+///
+/// `save_results` only takes `PlotResult` rows, so a data file is uploaded as one
+/// with no image size (see docs/07_RUST_CONTEXT_IMPL.md).
+fn data_file_result(file: pipeline::DataFile) -> tercen_rs::PlotResult {
+    tercen_rs::PlotResult {
+        label: file.label,
+        png_buffer: file.content,
+        width: 0,
+        height: 0,
+        page_factors: file.page_factors,
+        output_ext: file.extension,
+        filename: file.filename,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_tercen_uri() {
+        assert!(validate_tercen_uri("http://127.0.0.1:5400").is_ok());
+        assert!(validate_tercen_uri("https://tercen.example.com:443/api").is_ok());
+        assert!(validate_tercen_uri("http://[::1]:5400").is_ok());
+
+        assert_eq!(
+            validate_tercen_uri("127.0.0.1:5400").unwrap_err(),
+            "missing scheme (http:// or https://)"
+        );
+        assert_eq!(
+            validate_tercen_uri("grpc://127.0.0.1:5400").unwrap_err(),
+            "unsupported scheme 'grpc'"
+        );
+        assert_eq!(
+            validate_tercen_uri("http://127.0.0.1").unwrap_err(),
+            "missing port"
+        );
+        assert_eq!(
+            validate_tercen_uri("http://[::1]").unwrap_err(),
+            "missing port"
+        );
+        assert_eq!(
+            validate_tercen_uri("http://[::1:5400").unwrap_err(),
+            "unclosed '[' in IPv6 host"
+        );
+        assert_eq!(
+            validate_tercen_uri("http://:5400").unwrap_err(),
+            "missing host"
+        );
+        assert_eq!(
+            validate_tercen_uri("http://host:54o0").unwrap_err(),
+            "invalid port '54o0'"
+        );
+    }
+
+    #[test]
+    fn test_single_thread_runtime_fetches_in_render() {
+        let runtime = build_runtime(true);
+        assert_eq!(
+            runtime.handle().runtime_flavor(),
+            tokio::runtime::RuntimeFlavor::CurrentThread
+        );
+
+        // Like a page preload: the fetch (here a timer) runs on the calling thread,
+        // which drives the runtime while it waits
+        let caller = std::thread::current().id();
+        let (value, thread) = runtime.block_on(async {
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+            (42, std::thread::current().id())
+        });
+        assert_eq!(value, 42);
+        assert_eq!(thread, caller);
+    }
+
+    #[test]
+    fn test_multi_thread_runtime_fetches_in_render() {
+        let runtime = build_runtime(false);
+        assert_eq!(
+            runtime.handle().runtime_flavor(),
+            tokio::runtime::RuntimeFlavor::MultiThread
+        );
+
+        // Like a chunk fetch during rendering: sync code blocking on a timer from
+        // the thread that called block_on
+        let value = runtime.block_on(async {
+            tokio::task::block_in_place(|| {
+                tokio::runtime::Handle::current().block_on(async {
+                    tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+                    42
+                })
+            })
+        });
+        assert_eq!(value, 42);
+    }
+}
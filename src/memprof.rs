@@ -2,9 +2,10 @@
 //!
 //! - Memory: Reads RSS (Resident Set Size) from /proc/self/status
 //! - Timing: Uses std::time::Instant for precise measurements
+//! - Phases: `PhaseTimer` collects named phase durations of a production run
 
 use std::fs;
-use std::time::Instant;
+use std::time::{Duration, Instant, SystemTime};
 
 /// Get current RSS (Resident Set Size) in bytes from /proc/self/status
 pub fn get_rss_bytes() -> u64 {
@@ -82,3 +83,95 @@ pub fn time_delta(label: &str, start: Instant, section_start: Instant) -> Instan
     );
     Instant::now()
 }
+
+// ============================================================================
+// Phase timing
+// ============================================================================
+
+/// Named phase durations of one run, logged as a summary table at the end
+///
+/// Durations use the monotonic clock; the start is also recorded as UTC wall-clock
+/// time so a summary attached to a report can be matched to server-side logs.
+pub struct PhaseTimer {
+    started_at: SystemTime,
+    start: Instant,
+    current: Option<(String, Instant)>,
+    phases: Vec<(String, Duration)>,
+}
+
+impl Default for PhaseTimer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PhaseTimer {
+    /// Start timing a run (no phase running yet)
+    pub fn new() -> Self {
+        Self {
+            started_at: SystemTime::now(),
+            start: Instant::now(),
+            current: None,
+            phases: Vec::new(),
+        }
+    }
+
+    /// Start phase `name`, ending the running phase
+    pub fn phase(&mut self, name: &str) {
+        self.finish();
+        self.current = Some((name.to_string(), Instant::now()));
+    }
+
+    /// End the running phase, if any
+    pub fn finish(&mut self) {
+        if let Some((name, started)) = self.current.take() {
+            self.phases.push((name, started.elapsed()));
+        }
+    }
+
+    /// Table of phase durations with their share of the total run time
+    pub fn summary(&self) -> String {
+        let total = self.start.elapsed();
+        let width = self
+            .phases
+            .iter()
+            .map(|(name, _)| name.chars().count())
+            .chain(std::iter::once("total".len()))
+            .max()
+            .unwrap_or(0);
+
+        let mut lines = vec![format!(
+            "Phase timing (started {}):",
+            crate::pipeline::utc_timestamp(self.started_at)
+        )];
+        for (name, duration) in &self.phases {
+            let share = if total.is_zero() {
+                0.0
+            } else {
+                duration.as_secs_f64() / total.as_secs_f64() * 100.0
+            };
+            lines.push(format!(
+                "  {:<width$}  {:>9.3}s  {:>5.1}%",
+                name,
+                duration.as_secs_f64(),
+                share
+            ));
+        }
+        lines.push(format!(
+            "  {:<width$}  {:>9.3}s",
+            "total",
+            total.as_secs_f64()
+        ));
+        lines.join("\n")
+    }
+
+    /// End the running phase and log the summary table (also to the task log)
+    pub fn log_summary(&mut self) {
+        self.finish();
+        if crate::logging::info_enabled() {
+            let summary = self.summary();
+            println!("\n{}", summary);
+            crate::logging::task_log(summary);
+        }
+    }
+}
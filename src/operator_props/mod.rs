@@ -305,6 +305,47 @@ impl OperatorPropertyReader {
         Ok(Some((x, y)))
     }
 
+    /// Parse numeric range string "min,max" into (f64, f64)
+    ///
+    /// Returns Ok(None) if empty, Err if the format is invalid or min >= max.
+    pub fn get_range(&self, name: &str) -> Result<Option<(f64, f64)>, String> {
+        let value = self.get_string(name);
+        if value.is_empty() {
+            return Ok(None);
+        }
+
+        let parts: Vec<&str> = value.split(',').collect();
+        if parts.len() != 2 {
+            return Err(format!(
+                "Invalid range format '{}' for property '{}'. Expected 'min,max'.",
+                value, name
+            ));
+        }
+
+        let min = parts[0].trim().parse::<f64>().map_err(|_| {
+            format!(
+                "Invalid minimum in '{}' for property '{}'. Expected a number.",
+                value, name
+            )
+        })?;
+
+        let max = parts[1].trim().parse::<f64>().map_err(|_| {
+            format!(
+                "Invalid maximum in '{}' for property '{}'. Expected a number.",
+                value, name
+            )
+        })?;
+
+        if !min.is_finite() || !max.is_finite() || min >= max {
+            return Err(format!(
+                "Range '{}' for property '{}' must have finite min < max.",
+                value, name
+            ));
+        }
+
+        Ok(Some((min, max)))
+    }
+
     /// Get boolean property with validation
     ///
     /// Accepts "true"/"1"/"yes" → true, "false"/"0"/"no"/"" → false.
@@ -375,12 +416,122 @@ impl OperatorPropertyReader {
             Ok(shapes)
         }
     }
+
+    /// Parse semicolon-separated list of numbers
+    ///
+    /// Format: "1000;2500.5" -> vec![1000.0, 2500.5]
+    /// Errors on any non-numeric or non-finite entry.
+    /// Returns an empty list if the property is empty.
+    pub fn get_f64_list(&self, name: &str) -> Result<Vec<f64>, String> {
+        let value = self.get_string(name);
+
+        let mut numbers = Vec::new();
+        for s in value.split(';') {
+            let trimmed = s.trim();
+            if trimmed.is_empty() {
+                continue;
+            }
+            let n = trimmed
+                .parse::<f64>()
+                .ok()
+                .filter(|v| v.is_finite())
+                .ok_or_else(|| {
+                    format!(
+                        "Invalid number '{}' in property '{}'. Expected numbers separated by ';'.",
+                        trimmed, name
+                    )
+                })?;
+            numbers.push(n);
+        }
+
+        Ok(numbers)
+    }
+
+    /// Parse semicolon-separated list of hex colors
+    ///
+    /// Format: "#1F77B4;#FF7F0E" -> vec![[31, 119, 180], [255, 127, 14]]
+    /// The leading '#' is optional. Errors on any malformed entry.
+    /// Returns an empty list if the property is empty.
+    pub fn get_color_list(&self, name: &str) -> Result<Vec<[u8; 3]>, String> {
+        let value = self.get_string(name);
+
+        let mut colors = Vec::new();
+        for s in value.split(';') {
+            let trimmed = s.trim();
+            if trimmed.is_empty() {
+                continue;
+            }
+            let hex = trimmed.strip_prefix('#').unwrap_or(trimmed);
+            let invalid = || {
+                format!(
+                    "Invalid color '{}' in property '{}'. Expected hex '#RRGGBB'.",
+                    trimmed, name
+                )
+            };
+            if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(invalid());
+            }
+            let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| invalid());
+            colors.push([channel(0)?, channel(2)?, channel(4)?]);
+        }
+
+        Ok(colors)
+    }
+
+    /// Parse a JSON object of strings
+    ///
+    /// Format: '{"a": "A", "b": "B"}'. Returns an empty map if the property is empty.
+    pub fn get_string_map(&self, name: &str) -> Result<HashMap<String, String>, String> {
+        let value = self.get_string(name);
+        if value.trim().is_empty() {
+            return Ok(HashMap::new());
+        }
+        serde_json::from_str(&value).map_err(|e| {
+            format!(
+                "Invalid value for property '{}': {}. Expected a JSON object of strings, e.g. '{{\"name\": \"Title\"}}'.",
+                name, e
+            )
+        })
+    }
+
+    /// Parse an optional single hex color
+    ///
+    /// Format: "#RRGGBB" (leading '#' optional). Returns None if empty.
+    pub fn get_optional_color(&self, name: &str) -> Result<Option<[u8; 3]>, String> {
+        let colors = self.get_color_list(name)?;
+        match colors.as_slice() {
+            [] => Ok(None),
+            [color] => Ok(Some(*color)),
+            _ => Err(format!(
+                "Property '{}' expects a single color, got {}.",
+                name,
+                colors.len()
+            )),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    /// Operator settings with the given (property, value) pairs set
+    fn settings_with(values: &[(&str, &str)]) -> OperatorSettings {
+        OperatorSettings {
+            operator_ref: Some(tercen_rs::client::proto::OperatorRef {
+                property_values: values
+                    .iter()
+                    .map(|(name, value)| tercen_rs::client::proto::PropertyValue {
+                        name: name.to_string(),
+                        value: value.to_string(),
+                    })
+                    .collect(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
     #[test]
     fn test_registry_loads() {
         let reg = registry();
@@ -430,6 +581,65 @@ mod tests {
         assert!(!reader.get_bool("text.disable").unwrap());
     }
 
+    #[test]
+    fn test_range_parsing() {
+        let reader = OperatorPropertyReader::new(None);
+        assert_eq!(reader.get_range("axis.y.range").unwrap(), None);
+
+        let settings = settings_with(&[("axis.y.range", "0, 5"), ("axis.x.range", "5,0")]);
+        let reader = OperatorPropertyReader::new(Some(&settings));
+        assert_eq!(reader.get_range("axis.y.range").unwrap(), Some((0.0, 5.0)));
+        assert!(reader.get_range("axis.x.range").is_err());
+    }
+
+    #[test]
+    fn test_color_list_parsing() {
+        let reader = OperatorPropertyReader::new(None);
+        assert!(reader.get_color_list("color.palette").unwrap().is_empty());
+
+        let settings = settings_with(&[("color.palette", "#1F77B4; ff7f0e")]);
+        let reader = OperatorPropertyReader::new(Some(&settings));
+        assert_eq!(
+            reader.get_color_list("color.palette").unwrap(),
+            vec![[31, 119, 180], [255, 127, 14]]
+        );
+
+        let settings = settings_with(&[("color.palette", "#1F77B4;#GG0000")]);
+        let reader = OperatorPropertyReader::new(Some(&settings));
+        assert!(reader.get_color_list("color.palette").is_err());
+    }
+
+    #[test]
+    fn test_string_map_parsing() {
+        let reader = OperatorPropertyReader::new(None);
+        assert!(reader.get_string_map("legend.titles").unwrap().is_empty());
+
+        let settings = settings_with(&[("legend.titles", r#"{"ds0.gene": "Gene"}"#)]);
+        let reader = OperatorPropertyReader::new(Some(&settings));
+        assert_eq!(
+            reader.get_string_map("legend.titles").unwrap(),
+            HashMap::from([("ds0.gene".to_string(), "Gene".to_string())])
+        );
+
+        let settings = settings_with(&[("legend.titles", "ds0.gene=Gene")]);
+        let reader = OperatorPropertyReader::new(Some(&settings));
+        assert!(reader.get_string_map("legend.titles").is_err());
+    }
+
+    #[test]
+    fn test_f64_list_parsing() {
+        let reader = OperatorPropertyReader::new(None);
+        assert!(reader.get_f64_list("point.sizes").unwrap().is_empty());
+
+        let settings = settings_with(&[("point.sizes", "4; 8.5;")]);
+        let reader = OperatorPropertyReader::new(Some(&settings));
+        assert_eq!(reader.get_f64_list("point.sizes").unwrap(), vec![4.0, 8.5]);
+
+        let settings = settings_with(&[("point.sizes", "4;big")]);
+        let reader = OperatorPropertyReader::new(Some(&settings));
+        assert!(reader.get_f64_list("point.sizes").is_err());
+    }
+
     #[test]
     fn test_optional_f64_defaults() {
         let reader = OperatorPropertyReader::new(None);
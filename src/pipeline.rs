@@ -9,20 +9,51 @@
 //! 3. Renders plots using GGRS
 //! 4. Returns plot results for output handling
 
-use crate::config::OperatorConfig;
-use crate::ggrs_integration::{TercenStreamConfig, TercenStreamGenerator};
+use crate::config::{LegendTitles, OperatorConfig};
+use crate::ggrs_integration::{
+    new_range_cache, DataCache, DataExport, TercenStreamConfig, TercenStreamGenerator,
+};
 use crate::memprof;
+use crate::retry::with_retry_from_env;
 use ggrs_core::scale::ContinuousScale;
-use ggrs_core::stream::{DataCache, StreamGenerator};
+use ggrs_core::stream::StreamGenerator;
 use ggrs_core::theme::elements::Element;
 use ggrs_core::{EnginePlotSpec, Geom, HeatmapLayout, PlotGenerator, PlotRenderer};
+use std::borrow::Cow;
+use std::sync::{Arc, Mutex};
 use tercen_rs::{
-    extract_page_values, new_schema_cache, ChartKind, ColorMapping, PlotResult, TercenContext,
+    extract_page_values, new_schema_cache, ChartKind, ColorMapping, PlotResult, SchemaCache,
+    TableStreamer, TercenClient, TercenContext,
 };
 
 /// Error type for pipeline operations
 pub type PipelineError = Box<dyn std::error::Error>;
 
+/// Maximum number of concurrent schema requests during prefetch
+const SCHEMA_PREFETCH_CONCURRENCY: usize = 4;
+
+/// Images and data files produced by [`generate_plots`]
+pub struct PlotOutput {
+    /// Rendered images: one per page, or a single combined image
+    pub plots: Vec<PlotResult>,
+    /// CSV exports of the plotted data, one per page image (empty unless `data.export`)
+    pub data_files: Vec<DataFile>,
+}
+
+/// A non-image result file
+pub struct DataFile {
+    /// Label of the image the data was drawn in
+    pub label: String,
+    /// File name without extension
+    pub filename: String,
+    /// File extension (e.g. "csv")
+    pub extension: String,
+    /// File bytes
+    pub content: Vec<u8>,
+    /// Page factor values of the image
+    pub page_factors: Vec<(String, String)>,
+}
+
 /// Generate plots from a TercenContext
 ///
 /// This is the main entry point for the shared pipeline. It takes any type
@@ -33,22 +64,31 @@ pub type PipelineError = Box<dyn std::error::Error>;
 /// * `config` - Operator configuration
 ///
 /// # Returns
-/// A vector of PlotResult containing the rendered PNG images
+/// The rendered images and, with `data.export`, the CSV of each image's data
 pub async fn generate_plots<C: TercenContext>(
     ctx: &C,
     config: &OperatorConfig,
-) -> Result<Vec<PlotResult>, PipelineError> {
+) -> Result<PlotOutput, PipelineError> {
     let m0 = memprof::checkpoint_return("generate_plots START");
     let t0 = std::time::Instant::now();
 
+    // Single-threaded runtime: the render can't block on fetches, so each page's
+    // data is fetched before it renders (see TercenStreamGenerator::preload)
+    let preload = tokio::runtime::Handle::current().runtime_flavor()
+        == tokio::runtime::RuntimeFlavor::CurrentThread;
+
     // Display context information
     print_context_info(ctx, config);
 
     // Display color information
     print_color_info(ctx);
 
+    if ctx.y_axis_table_id().is_none() {
+        return Err(missing_y_axis_table_error(ctx).await);
+    }
+
     // Extract page information
-    println!("\n[2/4] Extracting page information...");
+    crate::info_log!("\n[2/4] Extracting page information...");
     let page_values = extract_page_values(ctx.client(), ctx.row_hash(), ctx.page_factors()).await?;
     let _m1 = memprof::delta("After extract_page_values", m0);
     let _t1 = memprof::time_delta("After extract_page_values", t0, t0);
@@ -57,36 +97,70 @@ pub async fn generate_plots<C: TercenContext>(
         return Err("No pages to generate".into());
     }
 
-    println!("  Pages to generate: {}", page_values.len());
+    crate::info_log!("  Pages to generate: {}", page_values.len());
     for (i, page_value) in page_values.iter().enumerate() {
-        println!("    Page {}: {}", i + 1, page_value.label);
+        crate::info_log!("    Page {}: {}", i + 1, page_value.label);
     }
 
-    // Create shared disk cache for all pages (only if multiple pages)
+    // Create shared data chunk cache for all pages (only if multiple pages)
     let cache = if page_values.len() > 1 {
-        let cache = DataCache::new(ctx.workflow_id(), ctx.step_id())?;
-        println!(
-            "  Created disk cache at /tmp/ggrs_cache_{}_{}/",
-            ctx.workflow_id(),
-            ctx.step_id()
-        );
-        Some(cache)
+        crate::info_log!("  Created data cache for multi-page plot");
+        Some(Arc::new(Mutex::new(DataCache::new())))
     } else {
-        println!("  Single page - cache disabled");
+        crate::info_log!("  Single page - cache disabled");
         None
     };
 
-    // Create shared schema cache for multi-page plots
-    // Schemas are reused across pages, reducing network requests
-    let schema_cache = if page_values.len() > 1 {
-        println!("  Created schema cache for multi-page plot");
-        Some(new_schema_cache())
+    // Create shared schema cache and prefetch the schemas the stream generator needs
+    // concurrently, instead of fetching them one at a time during initialization
+    let schema_cache = new_schema_cache();
+    let table_ids: Vec<String> = [
+        Some(ctx.qt_hash()),
+        ctx.y_axis_table_id(),
+        ctx.x_axis_table_id(),
+    ]
+    .into_iter()
+    .flatten()
+    .map(|s| s.to_string())
+    .collect();
+    prefetch_schemas(ctx.client(), &schema_cache, table_ids).await?;
+
+    // Axis ranges pinned in the crosstab; axis.x.range / axis.y.range override them
+    let (x_axis_limits, y_axis_limits) =
+        if config.x_axis_range.is_none() || config.y_axis_range.is_none() {
+            let (x, y) = crosstab_axis_limits(ctx.client(), ctx.workflow_id(), ctx.step_id()).await;
+            (config.x_axis_range.or(x), config.y_axis_range.or(y))
+        } else {
+            (config.x_axis_range, config.y_axis_range)
+        };
+
+    let schema_cache = Some(schema_cache);
+
+    // Create shared X-range cache for multi-page plots
+    // Parsed X-axis ranges are reused across pages, reducing network requests
+    let range_cache = if page_values.len() > 1 {
+        crate::info_log!("  Created range cache for multi-page plot");
+        Some(new_range_cache())
+    } else {
+        None
+    };
+
+    // Category → color level order from the unfiltered facets, so categories keep
+    // their color on every page
+    let color_levels = if page_values.len() > 1 {
+        TercenStreamGenerator::global_color_levels(
+            ctx.client(),
+            ctx.column_hash(),
+            ctx.row_hash(),
+            ctx.color_infos(),
+        )
+        .await?
     } else {
         None
     };
 
     // Generate plots for each page
-    println!(
+    crate::info_log!(
         "\n[3/4] Generating plots for {} page(s)...",
         page_values.len()
     );
@@ -94,15 +168,53 @@ pub async fn generate_plots<C: TercenContext>(
     let mut plot_results: Vec<PlotResult> = Vec::new();
     let client_arc = ctx.client().clone();
 
+    // Plotted data export: one CSV per image, kept apart so combined pages only
+    // stack the images
+    let data_export = config.export_data.then(DataExport::default);
+    let mut data_files: Vec<DataFile> = Vec::new();
+
+    // Facet grid of the first page: crosstab dimensions are scaled from it so
+    // pages with more or fewer facets get their own size
+    let mut reference_grid: Option<(usize, usize)> = None;
+
+    // Combined output: pages render as PNG (re-encoded once stitched) and are titled
+    // with their page label, which heads each page block of the combined image
+    let combine = config.combine_pages
+        && page_values.len() > 1
+        && matches!(config.output_format.as_str(), "png" | "webp");
+    if config.combine_pages && page_values.len() > 1 && !combine {
+        crate::warn_log!("pages.combine needs png or webp output - writing one image per page");
+    }
+
     for (page_idx, page_value) in page_values.iter().enumerate() {
+        let page_config = if combine {
+            let mut page_config = config.clone();
+            page_config.output_format = "png".to_string();
+            if !page_value.label.is_empty() {
+                page_config.plot_title = Some(match config.plot_title {
+                    Some(ref title) => format!("{} - {}", title, page_value.label),
+                    None => page_value.label.clone(),
+                });
+            }
+            Cow::Owned(page_config)
+        } else {
+            Cow::Borrowed(config)
+        };
+
         if page_values.len() > 1 {
-            println!(
+            crate::info_log!(
                 "\n=== Page {}/{}: {} ===",
                 page_idx + 1,
                 page_values.len(),
                 page_value.label
             );
         }
+        crate::progress_log!(
+            "page {}/{} started ({}%)",
+            page_idx + 1,
+            page_values.len(),
+            crate::logging::percent(page_idx, page_values.len())
+        );
 
         // Create StreamGenerator for this page
         let page_filter = if page_values.len() > 1 {
@@ -127,7 +239,18 @@ pub async fn generate_plots<C: TercenContext>(
         .per_layer_colors(ctx.per_layer_colors().cloned())
         .page_factors(ctx.page_factors().to_vec())
         .schema_cache(schema_cache.clone())
-        .heatmap_cell_aggregation(config.heatmap_cell_aggregation)
+        .range_cache(range_cache.clone())
+        .color_levels(color_levels.clone())
+        .heatmap_cell_aggregation(config.heatmap_cell_aggregation.clone())
+        .bar_mode(config.bar_mode)
+        .histogram_bins(config.histogram.then_some(config.histogram_bins))
+        .legend_range(config.legend_range)
+        .legend_sort(config.legend_sort)
+        .legend_drop_unused(config.legend_drop_unused)
+        .legend_titles(LegendTitles {
+            title: config.legend_title.clone(),
+            by_factor: config.legend_titles.clone(),
+        })
         .y_transform(
             config
                 .y_transform_override
@@ -140,10 +263,44 @@ pub async fn generate_plots<C: TercenContext>(
                 .clone()
                 .or_else(|| ctx.x_transform().map(|s| s.to_string())),
         )
+        .y_axis_limits(y_axis_limits)
+        .x_axis_limits(x_axis_limits)
+        .flip_y(config.flip_y)
+        .layer_y_transforms(config.layer_y_transforms.clone())
+        .sqrt_negative_policy(config.sqrt_negative_policy)
+        .strict_axis_ranges(config.strict_axis_ranges)
+        .message_budget_bytes(config.message_budget_bytes)
         .n_layers(ctx.n_layers())
         .layer_palette_name(ctx.layer_palette_name().map(|s| s.to_string()))
+        .categorical_palette(config.categorical_palette.clone())
+        .palette_override(config.palette_override, config.palette_override_continuous)
+        .facet_scales(config.facet_scales)
+        .facet_order(config.facet_order.clone())
+        .max_facets(config.max_facets)
+        .min_points_per_facet(config.min_points_per_facet)
+        .max_points_per_facet(config.max_points_per_facet)
+        .facet_label_max_len(config.facet_label_max_len)
+        .facet_label_overflow(config.facet_label_overflow)
+        .nan_color(config.nan_color)
+        .color_range(config.color_range)
+        .color_clip_percentiles(config.color_clip_percentiles)
+        .color_bins(config.color_bins)
         .layer_y_factor_names(ctx.layer_y_factor_names().to_vec())
-        .chart_kind(ctx.chart_kind());
+        .x_categorical_factor(
+            ctx.x_axis_factor()
+                .filter(|f| f.r#type == "string")
+                .map(|f| f.name.clone()),
+        )
+        .jitter_width(config.jitter_width)
+        .chart_kind(ctx.chart_kind())
+        .data_cache(cache.clone())
+        .data_export(data_export.clone())
+        .aggregation_timeout(
+            config
+                .heatmap_aggregation_timeout_secs
+                .map(std::time::Duration::from_secs),
+        )
+        .max_concurrent_chunks(config.max_concurrent_chunks);
 
         let mut stream_gen =
             TercenStreamGenerator::new(client_arc.clone(), stream_config, page_filter).await?;
@@ -155,45 +312,136 @@ pub async fn generate_plots<C: TercenContext>(
         // The original facet dimensions become the heatmap grid dimensions
         if matches!(ctx.chart_kind(), ChartKind::Heatmap) {
             let (n_cols, n_rows) = stream_gen.original_grid_dims();
-            println!(
+            crate::info_log!(
                 "  Heatmap mode: using grid {}×{} as tile positions",
-                n_cols, n_rows
+                n_cols,
+                n_rows
             );
             stream_gen.set_heatmap_mode(n_cols, n_rows);
         }
 
-        println!(
+        crate::info_log!(
             "  Facets: {} columns × {} rows = {} cells",
             stream_gen.n_col_facets(),
             stream_gen.n_row_facets(),
             stream_gen.n_col_facets() * stream_gen.n_row_facets()
         );
 
+        // Split mode: one 1×1 image per facet cell instead of the grid
+        if config.split_facets && !matches!(ctx.chart_kind(), ChartKind::Heatmap) {
+            let n_cols = stream_gen.n_col_facets().max(1);
+            let n_rows = stream_gen.n_row_facets().max(1);
+            // Each image gets one cell's share of the crosstab size
+            let grid = *reference_grid.get_or_insert(stream_gen.sizing_dims());
+            crate::info_log!("  Split facets: rendering {} images", n_cols * n_rows);
+
+            for col in 0..n_cols {
+                for row in 0..n_rows {
+                    let cell_gen = stream_gen.for_cell(col, row)?;
+                    if preload {
+                        cell_gen.preload().await?;
+                    }
+                    let ci = if cell_gen.n_col_facets() > 0 {
+                        cell_gen.get_original_col_idx(0)
+                    } else {
+                        0
+                    };
+                    let ri = if cell_gen.n_row_facets() > 0 {
+                        cell_gen.get_original_row_idx(0)
+                    } else {
+                        0
+                    };
+                    let name = format!("facet_{}_{}", ci, ri);
+                    crate::info_log!("\n  --- {} ---", name);
+
+                    let mut plot_result = render_page(
+                        ctx,
+                        &page_config,
+                        cell_gen,
+                        page_value,
+                        page_idx,
+                        page_values.len(),
+                        grid,
+                    )?;
+                    plot_result.label = if page_value.label.is_empty() {
+                        name.clone()
+                    } else {
+                        format!("{} {}", page_value.label, name)
+                    };
+                    plot_result.filename = name;
+                    if let Some(ref export) = data_export {
+                        data_files.push(data_export_file(export, &plot_result));
+                    }
+                    plot_results.push(plot_result);
+                }
+            }
+
+            crate::progress_log!(
+                "page {}/{} finished ({}%)",
+                page_idx + 1,
+                page_values.len(),
+                crate::logging::percent(page_idx + 1, page_values.len())
+            );
+            continue;
+        }
+
+        if preload {
+            stream_gen.preload().await?;
+        }
+        let reference_grid = *reference_grid.get_or_insert(stream_gen.sizing_dims());
+
         // Render the plot
         let plot_result = render_page(
             ctx,
-            config,
+            &page_config,
             stream_gen,
             page_value,
             page_idx,
             page_values.len(),
-            cache.as_ref(),
+            reference_grid,
         )?;
 
+        crate::progress_log!(
+            "page {}/{} finished ({}%)",
+            page_idx + 1,
+            page_values.len(),
+            crate::logging::percent(page_idx + 1, page_values.len())
+        );
+
+        if let Some(ref export) = data_export {
+            data_files.push(data_export_file(export, &plot_result));
+        }
         plot_results.push(plot_result);
     }
 
-    // Clean up cache
-    if let Some(ref cache_ref) = cache {
-        println!("  Cleaning up disk cache...");
-        cache_ref.clear()?;
+    if let Some(ref cache) = cache {
+        let cache = cache.lock().unwrap();
+        crate::info_log!(
+            "  cache: {} hits, {} misses, {:.1} MB",
+            cache.hits(),
+            cache.misses(),
+            cache.size_bytes() as f64 / (1024.0 * 1024.0)
+        );
+    }
+
+    if combine {
+        crate::info_log!("  Combining {} images into one", plot_results.len());
+        plot_results = vec![combine_page_images(plot_results, config)?];
+    }
+    if !data_files.is_empty() {
+        crate::info_log!("  Data export: {} CSV file(s)", data_files.len());
     }
 
-    println!("\n[4/4] Plot generation complete");
-    Ok(plot_results)
+    crate::info_log!("\n[4/4] Plot generation complete");
+    Ok(PlotOutput {
+        plots: plot_results,
+        data_files,
+    })
 }
 
 /// Render a single page/plot
+///
+/// `reference_grid` is the facet grid the crosstab dimensions apply to (the first page).
 fn render_page<C: TercenContext>(
     ctx: &C,
     config: &OperatorConfig,
@@ -201,26 +449,35 @@ fn render_page<C: TercenContext>(
     page_value: &tercen_rs::PageValue,
     page_idx: usize,
     total_pages: usize,
-    cache: Option<&DataCache>,
+    reference_grid: (usize, usize),
 ) -> Result<PlotResult, PipelineError> {
     use ggrs_core::renderer::{BackendChoice, OutputFormat};
 
-    // Resolve plot dimensions
-    // Priority: 1) crosstab dimensions from Tercen UI, 2) grid-based calculation
-    let crosstab_dims = ctx.crosstab_dimensions();
+    // Resolve plot dimensions per page
+    // Priority: 1) crosstab dimensions from Tercen UI (scaled to this page's grid),
+    // 2) grid-based calculation
     let (sizing_cols, sizing_rows) = stream_gen.sizing_dims();
+    let crosstab_dims = ctx
+        .crosstab_dimensions()
+        .map(|dims| scale_to_grid(dims, reference_grid, (sizing_cols, sizing_rows)));
     let (plot_width, plot_height) =
         config.resolve_dimensions_with_crosstab(crosstab_dims, sizing_cols, sizing_rows);
 
     if let Some((ct_w, ct_h)) = crosstab_dims {
-        println!(
+        crate::info_log!(
             "  Plot size: {}×{} pixels (from crosstab {}×{} + legend space)",
-            plot_width, plot_height, ct_w, ct_h
+            plot_width,
+            plot_height,
+            ct_w,
+            ct_h
         );
     } else {
-        println!(
+        crate::info_log!(
             "  Plot size: {}×{} pixels (from {}×{} grid + legend space)",
-            plot_width, plot_height, sizing_cols, sizing_rows
+            plot_width,
+            plot_height,
+            sizing_cols,
+            sizing_rows
         );
     }
 
@@ -232,7 +489,7 @@ fn render_page<C: TercenContext>(
     theme.legend_justification = config.legend_justification;
     theme.plot_title_position = config.plot_title_position.clone();
 
-    println!("  Theme: {}", config.theme);
+    crate::info_log!("  Theme: {}", config.theme);
 
     // Apply plot title justification if configured
     if let Some((just_x, just_y)) = config.plot_title_justification {
@@ -245,74 +502,84 @@ fn render_page<C: TercenContext>(
     // Apply tick label rotation if configured
     if config.x_tick_rotation != 0.0 {
         theme.set_x_tick_rotation(config.x_tick_rotation);
-        println!("  X-axis tick rotation: {}°", config.x_tick_rotation);
+        crate::info_log!("  X-axis tick rotation: {}°", config.x_tick_rotation);
     }
     if config.y_tick_rotation != 0.0 {
         theme.set_y_tick_rotation(config.y_tick_rotation);
-        println!("  Y-axis tick rotation: {}°", config.y_tick_rotation);
+        crate::info_log!("  Y-axis tick rotation: {}°", config.y_tick_rotation);
     }
 
     // Element disable toggles
     if config.grid_major_disable {
         theme.disable_grid_major();
-        println!("  Major grid: disabled");
+        crate::info_log!("  Major grid: disabled");
     }
     if config.grid_minor_disable {
         theme.disable_grid_minor();
-        println!("  Minor grid: disabled");
+        crate::info_log!("  Minor grid: disabled");
     }
     if config.axis_lines_disable {
         theme.disable_axis_lines();
-        println!("  Axis lines: disabled");
+        crate::info_log!("  Axis lines: disabled");
     }
     if config.text_disable {
         theme.disable_text();
-        println!("  Text labels: disabled");
+        crate::info_log!("  Text labels: disabled");
     }
 
     // Font size overrides
     if let Some(size) = config.title_font_size {
         theme.set_plot_title_size(size);
-        println!("  Title font size: {}pt", size);
+        crate::info_log!("  Title font size: {}pt", size);
     }
     if let Some(size) = config.axis_label_font_size {
         theme.set_axis_title_size(size);
-        println!("  Axis label font size: {}pt", size);
+        crate::info_log!("  Axis label font size: {}pt", size);
     }
     if let Some(size) = config.tick_label_font_size {
         theme.set_axis_text_size(size);
-        println!("  Tick label font size: {}pt", size);
+        crate::info_log!("  Tick label font size: {}pt", size);
     }
 
     // Axis line width
     if let Some(width) = config.axis_line_width {
         theme.set_panel_border_linewidth(width);
-        println!("  Axis line width: {}pt", width);
+        crate::info_log!("  Axis line width: {}pt", width);
     }
 
-    // Select geom based on chart kind
-    let geom = match ctx.chart_kind() {
+    // Select geoms based on chart kind (one per layer when point sizes differ)
+    let geoms = match ctx.chart_kind() {
         ChartKind::Heatmap => {
-            println!("  Chart kind: Heatmap (using Geom::tile())");
-            Geom::tile()
+            crate::info_log!("  Chart kind: Heatmap (using Geom::tile())");
+            vec![Geom::tile()]
         }
         ChartKind::Bar => {
-            println!("  Chart kind: Bar (using Geom::bar())");
-            Geom::bar()
+            crate::info_log!("  Chart kind: Bar (using Geom::bar())");
+            vec![Geom::bar()]
         }
         ChartKind::Point => {
-            println!(
-                "  Chart kind: Point (using Geom::point_sized({}))",
-                config.point_size
-            );
-            Geom::point_sized(config.point_size)
+            let sizes = config.layer_point_sizes_for(ctx.n_layers().max(1));
+            if sizes.iter().all(|&s| s == sizes[0]) {
+                crate::info_log!(
+                    "  Chart kind: Point (using Geom::point_sized({}))",
+                    sizes[0]
+                );
+                vec![Geom::point_sized(sizes[0])]
+            } else {
+                // Layer geoms follow .axisIndex order, like layer_shapes
+                crate::info_log!(
+                    "  Chart kind: Point (using Geom::point_sized per layer: {:?})",
+                    sizes
+                );
+                sizes.into_iter().map(Geom::point_sized).collect()
+            }
         }
         ChartKind::Line => {
-            println!(
+            crate::info_log!(
                 "  Chart kind: Line (using Geom::line_width({}))",
                 config.point_size
             );
-            Geom::line_width(config.point_size)
+            vec![Geom::line_width(config.point_size)]
         }
     };
 
@@ -324,7 +591,7 @@ fn render_page<C: TercenContext>(
     // .ci = X position, .ri = Y position (following legacy R operator)
     let facet_spec = match ctx.chart_kind() {
         ChartKind::Heatmap => {
-            println!("  Heatmap mode: using FacetSpec::none() (grid is the heatmap)");
+            crate::info_log!("  Heatmap mode: using FacetSpec::none() (grid is the heatmap)");
             ggrs_core::stream::FacetSpec::none()
         }
         _ => stream_gen.facet_spec().clone(),
@@ -335,15 +602,18 @@ fn render_page<C: TercenContext>(
         .aes(aes)
         .facet(facet_spec)
         .legend_scale(legend_scale)
-        .add_layer(geom)
         .theme(theme);
+    for geom in geoms {
+        plot_spec = plot_spec.add_layer(geom);
+    }
 
     // Set chart layout based on chart kind
     // HeatmapLayout: uses .ci/.ri for positions, discrete axes, single panel
     // DefaultLayout (default): uses .xs/.ys, continuous axes, faceted panels
     if let ChartKind::Heatmap = ctx.chart_kind() {
         let (n_cols, n_rows) = stream_gen.original_grid_dims();
-        plot_spec = plot_spec.chart_layout(Box::new(HeatmapLayout::new(n_cols, n_rows)));
+        let layout = HeatmapLayout::new(n_cols, n_rows);
+        plot_spec = plot_spec.chart_layout(Box::new(layout));
 
         // For heatmaps, use scales with NO expansion (discrete grid positions)
         // Default ContinuousScale has 5% expansion which distorts tile placement
@@ -353,25 +623,35 @@ fn render_page<C: TercenContext>(
             .scale_x(Box::new(x_scale))
             .scale_y(Box::new(y_scale));
 
-        println!(
+        crate::info_log!(
             "  Chart layout: HeatmapLayout (grid {}×{}, no expansion)",
-            n_cols, n_rows
+            n_cols,
+            n_rows
         );
     } else {
         // Non-heatmap charts: use default ContinuousScale
         // Transform handling will be implemented in GGRS via NumericAxisData.transform
-        println!("  Chart layout: Default (ContinuousScale)");
+        crate::info_log!("  Chart layout: Default (ContinuousScale)");
     }
 
     // Add text labels from configuration
-    if let Some(ref title) = config.plot_title {
+    // Empty tables still render facet panels and axes; the title says why there are no points
+    if stream_gen.is_empty() {
+        crate::info_log!("  No data rows - rendering empty plot");
+        let title = match config.plot_title {
+            Some(ref title) => format!("{} (No data)", title),
+            None => "No data".to_string(),
+        };
+        plot_spec = plot_spec.title(title);
+    } else if let Some(ref title) = config.plot_title {
         plot_spec = plot_spec.title(title.clone());
     }
-    if let Some(ref x_label) = config.x_axis_label {
-        plot_spec = plot_spec.x_label(x_label.clone());
+    let (x_title, y_title) = axis_titles(ctx, config);
+    if let Some(x_title) = x_title {
+        plot_spec = plot_spec.x_label(x_title);
     }
-    if let Some(ref y_label) = config.y_axis_label {
-        plot_spec = plot_spec.y_label(y_label.clone());
+    if let Some(y_title) = y_title {
+        plot_spec = plot_spec.y_label(y_title);
     }
 
     // Set point shapes per layer (cycles through layers based on .axisIndex)
@@ -387,17 +667,7 @@ fn render_page<C: TercenContext>(
     let m5 = memprof::delta("After PlotGenerator::new()", m4);
     let t5 = memprof::time_delta("After PlotGenerator::new()", t4, t4);
 
-    // Create PlotRenderer with cache (if enabled)
-    let mut renderer = if let Some(cache_ref) = cache {
-        PlotRenderer::new_with_cache(
-            &plot_gen,
-            plot_width as u32,
-            plot_height as u32,
-            cache_ref.clone(),
-        )
-    } else {
-        PlotRenderer::new(&plot_gen, plot_width as u32, plot_height as u32)
-    };
+    let mut renderer = PlotRenderer::new(&plot_gen, plot_width as u32, plot_height as u32);
 
     // Set PNG compression level
     let png_compression = match config.png_compression.to_lowercase().as_str() {
@@ -416,19 +686,26 @@ fn render_page<C: TercenContext>(
         },
     };
 
-    let ext = output_format.extension();
+    // WebP: GGRS renders PNG, re-encoded below
+    let ext = if config.output_format == "webp" {
+        "webp"
+    } else {
+        output_format.extension()
+    };
 
-    println!(
+    crate::info_log!(
         "  Rendering plot (backend: {}, format: {})...",
-        config.backend, ext
+        config.backend,
+        ext
     );
 
     // Render to temporary file (must use temp dir in production containers)
     let temp_dir = std::env::temp_dir();
+    let render_ext = output_format.extension();
     let temp_path = if total_pages > 1 {
-        temp_dir.join(format!("temp_plot_page_{}.{}", page_idx, ext))
+        temp_dir.join(format!("temp_plot_page_{}.{}", page_idx, render_ext))
     } else {
-        temp_dir.join(format!("temp_plot.{}", ext))
+        temp_dir.join(format!("temp_plot.{}", render_ext))
     };
 
     let _ = memprof::delta("Before render_to_file()", m5);
@@ -436,11 +713,24 @@ fn render_page<C: TercenContext>(
     renderer.render_to_file(&temp_path.to_string_lossy(), backend, output_format)?;
     let _ = memprof::time_delta("After render_to_file()", t5, t6);
 
-    // Read PNG into memory
+    // Read rendered image (PNG or SVG bytes) into memory
     let png_buffer = std::fs::read(&temp_path)?;
     std::fs::remove_file(&temp_path)?;
+    let png_buffer = if config.output_format == "webp" {
+        let png_size = png_buffer.len();
+        let webp_buffer = encode_webp(&png_buffer, config.webp_quality)?;
+        crate::info_log!(
+            "  WebP (quality {}): {} bytes (PNG: {} bytes)",
+            config.webp_quality,
+            webp_buffer.len(),
+            png_size
+        );
+        webp_buffer
+    } else {
+        png_buffer
+    };
 
-    println!("✓ Plot generated ({} bytes)", png_buffer.len());
+    crate::info_log!("✓ Plot generated ({} bytes)", png_buffer.len());
 
     // Build page factors for result
     let page_factors: Vec<(String, String)> = ctx
@@ -465,25 +755,159 @@ fn render_page<C: TercenContext>(
     })
 }
 
+/// Stack rendered PNG pages vertically into one image
+///
+/// Pages are left-aligned on a white background and separated by a gray line.
+/// The result is encoded as PNG, or WebP when that is the output format.
+fn combine_page_images(
+    pages: Vec<PlotResult>,
+    config: &OperatorConfig,
+) -> Result<PlotResult, PipelineError> {
+    const SEPARATOR: u32 = 4;
+    const SEPARATOR_COLOR: image::Rgba<u8> = image::Rgba([0xBF, 0xBF, 0xBF, 0xFF]);
+
+    let images = pages
+        .iter()
+        .map(|page| {
+            image::load_from_memory_with_format(&page.png_buffer, image::ImageFormat::Png)
+                .map(|image| image.to_rgba8())
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let width = images.iter().map(|image| image.width()).max().unwrap_or(0);
+    let height = images.iter().map(|image| image.height()).sum::<u32>()
+        + SEPARATOR * images.len().saturating_sub(1) as u32;
+
+    let mut combined =
+        image::RgbaImage::from_pixel(width, height, image::Rgba([0xFF, 0xFF, 0xFF, 0xFF]));
+    let mut y = 0;
+    for (i, page) in images.iter().enumerate() {
+        if i > 0 {
+            for row in y..y + SEPARATOR {
+                for x in 0..width {
+                    combined.put_pixel(x, row, SEPARATOR_COLOR);
+                }
+            }
+            y += SEPARATOR;
+        }
+        image::imageops::replace(&mut combined, page, 0, y as i64);
+        y += page.height();
+    }
+
+    let mut png_buffer = Vec::new();
+    combined.write_to(
+        &mut std::io::Cursor::new(&mut png_buffer),
+        image::ImageFormat::Png,
+    )?;
+    let (png_buffer, ext) = if config.output_format == "webp" {
+        (encode_webp(&png_buffer, config.webp_quality)?, "webp")
+    } else {
+        (png_buffer, "png")
+    };
+    crate::info_log!(
+        "✓ Combined image: {}×{} ({} bytes)",
+        width,
+        height,
+        png_buffer.len()
+    );
+
+    Ok(PlotResult {
+        label: String::new(),
+        png_buffer,
+        width: width as i32,
+        height: height as i32,
+        page_factors: Vec::new(),
+        output_ext: ext.to_string(),
+        filename: config.filename.clone(),
+    })
+}
+
+/// CSV of the data drawn in `plot`, drained from the export buffer
+fn data_export_file(export: &DataExport, plot: &PlotResult) -> DataFile {
+    let rows = std::mem::take(&mut *export.lock().unwrap());
+    DataFile {
+        label: plot.label.clone(),
+        filename: format!("{}_data", plot.filename),
+        extension: "csv".to_string(),
+        content: format!(".ci,.ri,.x,.y,.color\n{}", rows).into_bytes(),
+        page_factors: plot.page_factors.clone(),
+    }
+}
+
+/// Re-encode a rendered PNG as WebP
+///
+/// Quality 100 produces a lossless WebP, lower values a lossy one.
+fn encode_webp(png: &[u8], quality: f32) -> Result<Vec<u8>, PipelineError> {
+    let image = image::load_from_memory_with_format(png, image::ImageFormat::Png)?.to_rgba8();
+    let encoder = webp::Encoder::from_rgba(image.as_raw(), image.width(), image.height());
+    let encoded = if quality >= 100.0 {
+        encoder.encode_lossless()
+    } else {
+        encoder.encode(quality)
+    };
+    Ok(encoded.to_vec())
+}
+
+/// Check a context and configuration without streaming data or rendering
+///
+/// Prints the context and color summary, requires a Y-axis table and fetches the
+/// schemas of the main, axis and facet tables (confirming they exist), then
+/// resolves the pages. Any failure is returned as the validation error.
+pub async fn validate_context<C: TercenContext>(
+    ctx: &C,
+    config: &OperatorConfig,
+) -> Result<(), PipelineError> {
+    print_context_info(ctx, config);
+    print_color_info(ctx);
+
+    if ctx.y_axis_table_id().is_none() {
+        return Err(missing_y_axis_table_error(ctx).await);
+    }
+
+    crate::info_log!("\n  Checking tables...");
+    let table_ids: Vec<String> = [
+        Some(ctx.qt_hash()),
+        Some(ctx.column_hash()),
+        Some(ctx.row_hash()),
+        ctx.y_axis_table_id(),
+        ctx.x_axis_table_id(),
+    ]
+    .into_iter()
+    .flatten()
+    .map(|s| s.to_string())
+    .collect();
+    let n_tables = table_ids.len();
+    prefetch_schemas(ctx.client(), &new_schema_cache(), table_ids).await?;
+    crate::info_log!("  ✓ {} table schemas found", n_tables);
+
+    let page_values = extract_page_values(ctx.client(), ctx.row_hash(), ctx.page_factors()).await?;
+    if page_values.is_empty() {
+        return Err("No pages to generate".into());
+    }
+    crate::info_log!("  ✓ {} page(s)", page_values.len());
+    crate::info_log!("  ✓ Chart kind: {:?}", ctx.chart_kind());
+    Ok(())
+}
+
 /// Print context information
 fn print_context_info<C: TercenContext>(ctx: &C, config: &OperatorConfig) {
-    println!("\n[1/4] Context information...");
-    println!("  Main table: {}", ctx.qt_hash());
-    println!("  Column facets: {}", ctx.column_hash());
-    println!("  Row facets: {}", ctx.row_hash());
-    println!("  Workflow: {}", ctx.workflow_id());
-    println!("  Step: {}", ctx.step_id());
-
-    println!("\n  Configuration:");
-    println!("    Backend: {}", config.backend);
-    println!("    Point size: {}", config.point_size);
-    println!(
+    crate::info_log!("\n[1/4] Context information...");
+    crate::info_log!("  Main table: {}", ctx.qt_hash());
+    crate::info_log!("  Column facets: {}", ctx.column_hash());
+    crate::info_log!("  Row facets: {}", ctx.row_hash());
+    crate::info_log!("  Workflow: {}", ctx.workflow_id());
+    crate::info_log!("  Step: {}", ctx.step_id());
+
+    crate::info_log!("\n  Configuration:");
+    crate::info_log!("    Backend: {}", config.backend);
+    crate::info_log!("    Point size: {}", config.point_size);
+    crate::info_log!(
         "    Plot dimensions: {:?} × {:?}",
-        config.plot_width, config.plot_height
+        config.plot_width,
+        config.plot_height
     );
 
     if let Some(y_table) = ctx.y_axis_table_id() {
-        println!("    Y-axis table: {}", y_table);
+        crate::info_log!("    Y-axis table: {}", y_table);
     }
 }
 
@@ -493,8 +917,8 @@ fn print_color_info<C: TercenContext>(ctx: &C) {
     if let Some(plc) = ctx.per_layer_colors() {
         use tercen_rs::LayerColorConfig;
 
-        println!("  Per-layer color configuration:");
-        println!(
+        crate::info_log!("  Per-layer color configuration:");
+        crate::info_log!(
             "    Layers: {}, has_explicit={}, is_mixed={}",
             plc.n_layers,
             plc.has_explicit_colors(),
@@ -508,12 +932,13 @@ fn print_color_info<C: TercenContext>(ctx: &C) {
                     factor_name,
                     ..
                 } => {
-                    println!(
+                    crate::info_log!(
                         "    Layer {}: continuous factor '{}'",
-                        layer_idx, factor_name
+                        layer_idx,
+                        factor_name
                     );
                     if let Some((min, max)) = palette.range() {
-                        println!(
+                        crate::info_log!(
                             "      Range: {} to {}, {} stops",
                             min,
                             max,
@@ -526,7 +951,7 @@ fn print_color_info<C: TercenContext>(ctx: &C) {
                     factor_name,
                     ..
                 } => {
-                    println!(
+                    crate::info_log!(
                         "    Layer {}: categorical factor '{}' ({} categories)",
                         layer_idx,
                         factor_name,
@@ -534,9 +959,12 @@ fn print_color_info<C: TercenContext>(ctx: &C) {
                     );
                 }
                 LayerColorConfig::Constant { color } => {
-                    println!(
+                    crate::info_log!(
                         "    Layer {}: constant color RGB({},{},{})",
-                        layer_idx, color[0], color[1], color[2]
+                        layer_idx,
+                        color[0],
+                        color[1],
+                        color[2]
                     );
                 }
             }
@@ -546,22 +974,306 @@ fn print_color_info<C: TercenContext>(ctx: &C) {
 
     // Fallback to legacy color_infos
     if ctx.color_infos().is_empty() {
-        println!("  No color factors defined");
+        crate::info_log!("  No color factors defined");
     } else {
         for (i, info) in ctx.color_infos().iter().enumerate() {
-            println!("  Color {} : '{}'", i + 1, info.factor_name);
-            println!("    Type: {}", info.factor_type);
+            crate::info_log!("  Color {} : '{}'", i + 1, info.factor_name);
+            crate::info_log!("    Type: {}", info.factor_type);
             match &info.mapping {
                 ColorMapping::Continuous(palette) => {
                     if let Some((min, max)) = palette.range() {
-                        println!("    Range: {} to {}", min, max);
-                        println!("    Stops: {}", palette.stops.len());
+                        crate::info_log!("    Range: {} to {}", min, max);
+                        crate::info_log!("    Stops: {}", palette.stops.len());
                     }
                 }
                 ColorMapping::Categorical(color_map) => {
-                    println!("    Categories: {}", color_map.mappings.len());
+                    crate::info_log!("    Categories: {}", color_map.mappings.len());
                 }
             }
         }
     }
 }
+
+/// X and Y axis titles: the `axis.x.label` / `axis.y.label` overrides, else the
+/// crosstab's axis factor names
+///
+/// The Y fallback joins the distinct Y factors of the layers. Heatmap axes show
+/// facet levels, so they only get the overrides.
+fn axis_titles<C: TercenContext>(
+    ctx: &C,
+    config: &OperatorConfig,
+) -> (Option<String>, Option<String>) {
+    if matches!(ctx.chart_kind(), ChartKind::Heatmap) {
+        return (config.x_axis_label.clone(), config.y_axis_label.clone());
+    }
+    let x_title = config
+        .x_axis_label
+        .clone()
+        .or_else(|| ctx.x_axis_factor().map(|f| f.name.clone()));
+    let y_title = config.y_axis_label.clone().or_else(|| {
+        let mut names: Vec<&str> = Vec::new();
+        for name in ctx.layer_y_factor_names() {
+            if !name.is_empty() && !names.contains(&name.as_str()) {
+                names.push(name);
+            }
+        }
+        (!names.is_empty()).then(|| names.join(", "))
+    });
+    (x_title, y_title)
+}
+
+/// Look up the CubeQueryTask id (`model.taskId`) of a data step
+pub(crate) async fn cube_query_task_id(
+    client: &TercenClient,
+    workflow_id: &str,
+    step_id: &str,
+) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    use tercen_rs::client::proto::{e_step, e_workflow, GetRequest};
+
+    let mut workflow_service = client.workflow_service()?;
+    let e_wf = workflow_service
+        .get(tonic::Request::new(GetRequest {
+            id: workflow_id.to_string(),
+            ..Default::default()
+        }))
+        .await?
+        .into_inner();
+    let workflow = match e_wf.object {
+        Some(e_workflow::Object::Workflow(wf)) => wf,
+        _ => return Err(format!("Workflow {} has no object", workflow_id).into()),
+    };
+
+    let data_step = workflow
+        .steps
+        .iter()
+        .find_map(|s| match &s.object {
+            Some(e_step::Object::Datastep(ds)) if ds.id == step_id => Some(ds),
+            _ => None,
+        })
+        .ok_or_else(|| format!("DataStep {} not found in workflow", step_id))?;
+
+    Ok(data_step
+        .model
+        .as_ref()
+        .map(|m| m.task_id.clone())
+        .filter(|id| !id.is_empty()))
+}
+
+/// Axis ranges (X, Y) pinned in the crosstab's axis settings
+///
+/// Reads the `min`/`max` meta pairs of the first axis query's `x_axis_settings` /
+/// `y_axis_settings` from the step's CubeQueryTask, as the context doesn't expose
+/// them. An axis is pinned only when both bounds are finite numbers with
+/// `min < max`. Lookup failures are logged and leave both axes auto-scaled.
+async fn crosstab_axis_limits(
+    client: &TercenClient,
+    workflow_id: &str,
+    step_id: &str,
+) -> (Option<(f64, f64)>, Option<(f64, f64)>) {
+    use tercen_rs::client::proto::AxisSettings;
+
+    let axis_query = match cube_query(client, workflow_id, step_id).await {
+        Ok(query) => query.and_then(|q| q.axis_queries.into_iter().next()),
+        Err(e) => {
+            crate::warn_log!("Could not read the crosstab axis settings: {}", e);
+            return (None, None);
+        }
+    };
+    let Some(axis_query) = axis_query else {
+        return (None, None);
+    };
+
+    let limits = |settings: Option<&AxisSettings>| {
+        let bound = |key: &str| -> Option<f64> {
+            let pair = settings?.meta.iter().find(|p| p.key == key)?;
+            pair.value.trim().parse().ok()
+        };
+        match (bound("min"), bound("max")) {
+            (Some(min), Some(max)) if min.is_finite() && max.is_finite() && min < max => {
+                Some((min, max))
+            }
+            _ => None,
+        }
+    };
+    let x_limits = limits(axis_query.x_axis_settings.as_ref());
+    let y_limits = limits(axis_query.y_axis_settings.as_ref());
+    if x_limits.is_some() || y_limits.is_some() {
+        crate::info_log!(
+            "  Crosstab axis limits - X: {:?}, Y: {:?}",
+            x_limits,
+            y_limits
+        );
+    }
+    (x_limits, y_limits)
+}
+
+/// The CubeQuery of a data step's CubeQueryTask (None when the step has no task)
+async fn cube_query(
+    client: &TercenClient,
+    workflow_id: &str,
+    step_id: &str,
+) -> Result<Option<tercen_rs::client::proto::CubeQuery>, PipelineError> {
+    use tercen_rs::client::proto::{e_task, GetRequest};
+
+    let Some(task_id) = cube_query_task_id(client, workflow_id, step_id).await? else {
+        return Ok(None);
+    };
+    let task = client
+        .task_service()?
+        .get(tonic::Request::new(GetRequest {
+            id: task_id.clone(),
+            ..Default::default()
+        }))
+        .await?
+        .into_inner();
+    match task.object {
+        Some(e_task::Object::Cubequerytask(cqt)) => Ok(cqt.query),
+        _ => Err(format!("task {} is not a CubeQueryTask", task_id).into()),
+    }
+}
+
+/// Error for a context without a Y-axis table, listing the tables it does have
+///
+/// The CubeQueryTask's `schema_ids` are printed with their `query_table_type`, to
+/// tell an empty `schema_ids` apart from one without a table of type 'y'.
+async fn missing_y_axis_table_error<C: TercenContext>(ctx: &C) -> PipelineError {
+    let tables = describe_schema_tables(ctx.client(), ctx.workflow_id(), ctx.step_id()).await;
+    format!(
+        "Y-axis table not found: the crosstab needs a Y-axis factor (schema_ids has no table of type 'y'; schema_ids: {})",
+        tables
+    )
+    .into()
+}
+
+/// The CubeQueryTask's schema IDs with their `query_table_type`, e.g.
+/// `"3f2a… (qt), 9c1b… (x)"`
+///
+/// Lookup failures are described in the text rather than returned, since this
+/// only decorates another error.
+async fn describe_schema_tables(client: &TercenClient, workflow_id: &str, step_id: &str) -> String {
+    use tercen_rs::client::proto::{e_schema, e_task, GetRequest};
+
+    let task_id = match cube_query_task_id(client, workflow_id, step_id).await {
+        Ok(Some(id)) => id,
+        Ok(None) => return "unknown, the step has no CubeQueryTask".to_string(),
+        Err(e) => return format!("unknown, {}", e),
+    };
+    let task = match client.task_service() {
+        Ok(mut task_service) => {
+            task_service
+                .get(tonic::Request::new(GetRequest {
+                    id: task_id.clone(),
+                    ..Default::default()
+                }))
+                .await
+        }
+        Err(e) => return format!("unknown, {}", e),
+    };
+    let schema_ids = match task.map(|t| t.into_inner().object) {
+        Ok(Some(e_task::Object::Cubequerytask(cqt))) => cqt.schema_ids,
+        Ok(_) => return format!("unknown, task {} is not a CubeQueryTask", task_id),
+        Err(e) => return format!("unknown, {}", e),
+    };
+    if schema_ids.is_empty() {
+        return "empty".to_string();
+    }
+
+    let streamer = TableStreamer::with_cache(client, new_schema_cache());
+    let mut tables = Vec::with_capacity(schema_ids.len());
+    for id in &schema_ids {
+        let table_type = match streamer.get_schema(id).await.map(|s| s.object) {
+            Ok(Some(e_schema::Object::Cubequerytableschema(s))) => {
+                format!("'{}'", s.query_table_type)
+            }
+            Ok(_) => "not a cube query table".to_string(),
+            Err(e) => format!("schema error: {}", e),
+        };
+        tables.push(format!("{} ({})", id, table_type));
+    }
+    tables.join(", ")
+}
+
+/// Fetch table schemas concurrently into the schema cache
+///
+/// Duplicate IDs are fetched once. Later `get_schema` calls through a streamer
+/// sharing the cache are served without a round-trip.
+async fn prefetch_schemas(
+    client: &TercenClient,
+    cache: &SchemaCache,
+    mut table_ids: Vec<String>,
+) -> Result<(), PipelineError> {
+    use futures::stream::{self, StreamExt, TryStreamExt};
+
+    table_ids.sort();
+    table_ids.dedup();
+
+    let t0 = std::time::Instant::now();
+    let streamer = TableStreamer::with_cache(client, cache.clone());
+    let streamer = &streamer;
+    stream::iter(table_ids.iter())
+        .map(|id| async move {
+            with_retry_from_env(|| streamer.get_schema(id))
+                .await
+                .map(|_| ())
+        })
+        .buffer_unordered(SCHEMA_PREFETCH_CONCURRENCY)
+        .try_collect::<Vec<()>>()
+        .await?;
+
+    crate::debug_log!(
+        "Prefetched {} schemas in {:?}",
+        table_ids.len(),
+        t0.elapsed()
+    );
+    Ok(())
+}
+
+/// Scale crosstab dimensions from the reference facet grid to a page's grid
+///
+/// Keeps the per-cell size constant, so a page with 5 facet rows is taller than
+/// one with 3. Dimensions are returned unchanged for an empty reference grid.
+fn scale_to_grid(
+    (width, height): (i32, i32),
+    (ref_cols, ref_rows): (usize, usize),
+    (cols, rows): (usize, usize),
+) -> (i32, i32) {
+    let scale = |size: i32, n: usize, n_ref: usize| {
+        if n_ref == 0 || n == n_ref {
+            size
+        } else {
+            (size as f64 * n as f64 / n_ref as f64).round() as i32
+        }
+    };
+    (scale(width, cols, ref_cols), scale(height, rows, ref_rows))
+}
+
+/// `time` as `YYYY-MM-DD HH:MM UTC`
+///
+/// Converts days since the epoch to a civil date (Howard Hinnant's algorithm),
+/// which avoids a date-time dependency for a single timestamp.
+pub(crate) fn utc_timestamp(time: std::time::SystemTime) -> String {
+    let secs = time
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    let (days, day_secs) = (secs.div_euclid(86_400), secs.rem_euclid(86_400));
+
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        day_secs / 3600,
+        day_secs % 3600 / 60
+    )
+}
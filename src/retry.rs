@@ -0,0 +1,201 @@
+//! Retry with exponential backoff for transient gRPC failures
+//!
+//! Only `Unavailable` and `DeadlineExceeded` statuses are retried; every other
+//! error is returned immediately so real failures still surface loudly. The status
+//! is found by downcasting the error's source chain (tercen-rs wraps it in
+//! `TercenError::Grpc`), or in the message of an error that only kept the status'
+//! text.
+//!
+//! Configuration (environment variables):
+//! - `TERCEN_RETRY_ATTEMPTS`: total attempts including the first (default: 3)
+//! - `TERCEN_RETRY_BASE_DELAY_MS`: delay before the first retry, doubled each time (default: 200)
+
+use std::error::Error;
+use std::future::Future;
+use std::time::Duration;
+
+/// Default number of attempts (first call + retries)
+const DEFAULT_ATTEMPTS: u32 = 3;
+
+/// Default delay before the first retry
+const DEFAULT_BASE_DELAY_MS: u64 = 200;
+
+/// Retry settings read from the environment
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Total attempts including the first call (minimum 1)
+    pub attempts: u32,
+    /// Delay before the first retry; doubled after each failed attempt
+    pub base_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// Read the policy from `TERCEN_RETRY_ATTEMPTS` / `TERCEN_RETRY_BASE_DELAY_MS`
+    ///
+    /// Unset or unparsable values use the defaults (3 attempts, 200ms).
+    pub fn from_env() -> Self {
+        let attempts = std::env::var("TERCEN_RETRY_ATTEMPTS")
+            .ok()
+            .and_then(|v| v.parse::<u32>().ok())
+            .unwrap_or(DEFAULT_ATTEMPTS)
+            .max(1);
+        let base_delay_ms = std::env::var("TERCEN_RETRY_BASE_DELAY_MS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_BASE_DELAY_MS);
+
+        Self {
+            attempts,
+            base_delay: Duration::from_millis(base_delay_ms),
+        }
+    }
+}
+
+/// gRPC codes worth retrying
+const TRANSIENT_CODES: [tonic::Code; 2] = [tonic::Code::Unavailable, tonic::Code::DeadlineExceeded];
+
+/// Check whether an error (or any error in its source chain) is a transient gRPC status
+///
+/// Errors that were converted to text keep the status' `Display` form
+/// (`code: '<description>', ...`), which is matched when no `tonic::Status`
+/// is in the chain.
+pub fn is_transient(err: &(dyn Error + 'static)) -> bool {
+    let mut current: Option<&(dyn Error + 'static)> = Some(err);
+    while let Some(e) = current {
+        if let Some(status) = e.downcast_ref::<tonic::Status>() {
+            return TRANSIENT_CODES.contains(&status.code());
+        }
+        current = e.source();
+    }
+
+    let message = err.to_string();
+    TRANSIENT_CODES
+        .iter()
+        .any(|code| message.contains(&format!("code: '{}'", code)))
+}
+
+/// Run `f` up to `attempts` times, retrying transient gRPC failures with exponential backoff
+pub async fn with_retry<F, Fut, T, E>(
+    attempts: u32,
+    base_delay: Duration,
+    mut f: F,
+) -> Result<T, Box<dyn Error>>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    E: Into<Box<dyn Error>>,
+{
+    let attempts = attempts.max(1);
+    let mut delay = base_delay;
+
+    for attempt in 1..=attempts {
+        match f().await {
+            Ok(value) => return Ok(value),
+            Err(e) => {
+                let err: Box<dyn Error> = e.into();
+                if attempt == attempts || !is_transient(err.as_ref()) {
+                    return Err(err);
+                }
+                crate::warn_log!(
+                    "transient gRPC failure (attempt {}/{}): {}. Retrying in {}ms",
+                    attempt,
+                    attempts,
+                    err,
+                    delay.as_millis()
+                );
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+            }
+        }
+    }
+
+    unreachable!("retry loop always returns on the last attempt")
+}
+
+/// Run `f` with the retry policy from the environment
+pub async fn with_retry_from_env<F, Fut, T, E>(f: F) -> Result<T, Box<dyn Error>>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    E: Into<Box<dyn Error>>,
+{
+    let policy = RetryPolicy::from_env();
+    with_retry(policy.attempts, policy.base_delay, f).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    #[test]
+    fn test_is_transient_status_codes() {
+        let unavailable: Box<dyn Error> = Box::new(tonic::Status::unavailable("down"));
+        let deadline: Box<dyn Error> = Box::new(tonic::Status::deadline_exceeded("slow"));
+        let not_found: Box<dyn Error> = Box::new(tonic::Status::not_found("missing"));
+        let other: Box<dyn Error> = "plain error".into();
+
+        assert!(is_transient(unavailable.as_ref()));
+        assert!(is_transient(deadline.as_ref()));
+        assert!(!is_transient(not_found.as_ref()));
+        assert!(!is_transient(other.as_ref()));
+    }
+
+    #[test]
+    fn test_is_transient_tercen_errors() {
+        // tercen-rs returns gRPC failures as TercenError::Grpc(#[from] tonic::Status)
+        let wrapped: Box<dyn Error> = Box::new(tercen_rs::TercenError::Grpc(
+            tonic::Status::unavailable("down"),
+        ));
+        let wrapped_permanent: Box<dyn Error> = Box::new(tercen_rs::TercenError::Grpc(
+            tonic::Status::permission_denied("no"),
+        ));
+        assert!(is_transient(wrapped.as_ref()));
+        assert!(!is_transient(wrapped_permanent.as_ref()));
+
+        // The same errors after being converted to text
+        let stringified: Box<dyn Error> = wrapped.to_string().into();
+        let stringified_permanent: Box<dyn Error> = wrapped_permanent.to_string().into();
+        assert!(is_transient(stringified.as_ref()));
+        assert!(!is_transient(stringified_permanent.as_ref()));
+        let deadline: Box<dyn Error> = format!(
+            "stream failed: {}",
+            tonic::Status::deadline_exceeded("slow")
+        )
+        .into();
+        assert!(is_transient(deadline.as_ref()));
+    }
+
+    #[tokio::test]
+    async fn test_with_retry_retries_transient_then_succeeds() {
+        let calls = Cell::new(0);
+        let result = with_retry(3, Duration::from_millis(1), || {
+            calls.set(calls.get() + 1);
+            let n = calls.get();
+            async move {
+                if n < 3 {
+                    Err(tonic::Status::unavailable("down"))
+                } else {
+                    Ok(n)
+                }
+            }
+        })
+        .await;
+
+        assert_eq!(result.unwrap(), 3);
+        assert_eq!(calls.get(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_with_retry_does_not_retry_permanent_errors() {
+        let calls = Cell::new(0);
+        let result: Result<(), _> = with_retry(3, Duration::from_millis(1), || {
+            calls.set(calls.get() + 1);
+            async { Err(tonic::Status::not_found("missing")) }
+        })
+        .await;
+
+        assert!(result.is_err());
+        assert_eq!(calls.get(), 1);
+    }
+}